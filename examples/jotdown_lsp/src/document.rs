@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use jotdown::Diagnostic;
+use jotdown::Parser;
+use jotdown::Resolution;
+
+/// A heading within a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    pub level: u16,
+    /// Text of the heading, with formatting removed.
+    pub text: String,
+    pub id: String,
+    /// Span of the heading itself.
+    pub span: Range<usize>,
+    /// Span from the heading to the next heading of the same or a higher level.
+    pub section: Range<usize>,
+}
+
+/// A reference link, reference image or footnote reference within a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub span: Range<usize>,
+    pub kind: ReferenceKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// A link or an image. The url is only present if the link has been resolved.
+    Link {
+        tag: String,
+        url: Option<String>,
+    },
+    Footnote {
+        label: String,
+    },
+}
+
+/// A link definition within a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkDefinition {
+    pub url: String,
+    pub span: Range<usize>,
+}
+
+/// An open document and the information extracted from it.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub text: String,
+    pub headings: Vec<Heading>,
+    pub link_definitions: HashMap<String, LinkDefinition>,
+    /// Footnote labels and the span of each footnote.
+    pub footnotes: HashMap<String, Range<usize>>,
+    /// All references, in the order they appear.
+    pub references: Vec<Reference>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let parser = Parser::new(&text);
+
+        let mut headings: Vec<Heading> = parser
+            .headings()
+            .iter()
+            .map(|h| Heading {
+                level: h.level(),
+                text: h.text().to_string(),
+                id: h.id().to_string(),
+                span: h.span(),
+                section: h.span().start..text.len(),
+            })
+            .collect();
+        for i in 0..headings.len() {
+            let level = headings[i].level;
+            if let Some(next) = headings[i + 1..].iter().find(|h| h.level <= level) {
+                headings[i].section.end = next.span.start;
+            }
+        }
+
+        let link_definitions = parser
+            .link_definitions()
+            .iter()
+            .map(|d| {
+                let def = LinkDefinition {
+                    url: d.url.to_string(),
+                    span: d.span.clone(),
+                };
+                (d.label.to_string(), def)
+            })
+            .collect::<HashMap<_, _>>();
+        let footnotes = parser
+            .footnote_definitions()
+            .iter()
+            .map(|f| (f.label.to_string(), f.span.clone()))
+            .collect();
+
+        let references = parser
+            .references()
+            .into_iter()
+            .map(|r| {
+                let tag = r.tag.to_string();
+                let kind = match (r.kind, r.resolution) {
+                    (jotdown::ReferenceKind::Footnote, _) => ReferenceKind::Footnote { label: tag },
+                    (_, Resolution::Definition) => ReferenceKind::Link {
+                        url: link_definitions.get(&tag).map(|d| d.url.clone()),
+                        tag,
+                    },
+                    (_, Resolution::Heading { id }) => ReferenceKind::Link {
+                        tag,
+                        url: Some(format!("#{}", id)),
+                    },
+                    (_, Resolution::Callback | Resolution::Unresolved) => {
+                        ReferenceKind::Link { tag, url: None }
+                    }
+                };
+                Reference { span: r.span, kind }
+            })
+            .collect();
+
+        let diagnostics = parser.diagnostics();
+        // the parser borrows the text, which is moved into the document
+        drop(parser);
+
+        Self {
+            text,
+            headings,
+            link_definitions,
+            footnotes,
+            references,
+            diagnostics,
+        }
+    }
+
+    /// The reference whose span includes the byte offset, if any.
+    pub fn reference_at(&self, offset: usize) -> Option<&Reference> {
+        self.references
+            .iter()
+            .find(|r| r.span.start <= offset && offset < r.span.end)
+    }
+
+    /// The span of the element that a reference refers to, if it has been resolved.
+    pub fn definition(&self, reference: &Reference) -> Option<Range<usize>> {
+        match &reference.kind {
+            ReferenceKind::Link { tag, url } => {
+                if let Some(def) = self.link_definitions.get(tag) {
+                    return Some(def.span.clone());
+                }
+                let id = url.as_ref()?.strip_prefix('#')?;
+                self.headings
+                    .iter()
+                    .find(|h| h.id == id)
+                    .map(|h| h.span.clone())
+            }
+            ReferenceKind::Footnote { label } => self.footnotes.get(label).cloned(),
+        }
+    }
+
+    /// Possible completions at the byte offset, as a label and a detail for each completion.
+    ///
+    /// Link and footnote labels are completed within reference tags, heading ids are completed
+    /// within inline link destinations starting with `#`.
+    pub fn completions(&self, offset: usize) -> Vec<(String, String)> {
+        let line_start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let prefix = &self.text[line_start..offset];
+        // the innermost opener that has not been closed before the offset
+        let opener = [("][", ']'), ("[^", ']'), ("](#", ')')]
+            .iter()
+            .filter_map(|(opener, closer)| {
+                let i = prefix.rfind(opener)?;
+                if prefix[i + opener.len()..].contains(*closer) {
+                    None
+                } else {
+                    Some((i, *opener))
+                }
+            })
+            .max()
+            .map(|(_, opener)| opener);
+
+        let mut completions: Vec<(String, String)> = match opener {
+            Some("][") => self
+                .link_definitions
+                .iter()
+                .map(|(label, def)| (label.clone(), def.url.clone()))
+                .collect(),
+            Some("[^") => self
+                .footnotes
+                .iter()
+                .map(|(label, span)| (label.clone(), first_line(&self.text[span.clone()])))
+                .collect(),
+            Some(_) => self
+                .headings
+                .iter()
+                .filter(|h| !h.id.is_empty())
+                .map(|h| (h.id.clone(), h.text.clone()))
+                .collect(),
+            None => Vec::new(),
+        };
+        completions.sort();
+        completions
+    }
+}
+
+fn first_line(s: &str) -> String {
+    s.lines().next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::Document;
+    use super::Reference;
+    use super::ReferenceKind;
+
+    const SRC: &str = concat!(
+        "# Head *x*\n",
+        "\n",
+        "[a][b] [c][] ![i][b] [Head x][] [^f] [u](/u) [v][w]\n",
+        "\n",
+        "## Sub\n",
+        "\n",
+        "[b]: /b\n",
+        "[c]: /c\n",
+        "\n",
+        "[^f]: note\n",
+    );
+
+    #[test]
+    fn headings() {
+        let doc = Document::new(SRC.to_string());
+        let hs: Vec<_> = doc
+            .headings
+            .iter()
+            .map(|h| {
+                (
+                    h.level,
+                    h.text.as_str(),
+                    h.id.as_str(),
+                    &SRC[h.span.clone()],
+                )
+            })
+            .collect();
+        assert_eq!(
+            hs,
+            &[
+                (1, "Head x", "Head-x", "# Head *x*\n"),
+                (2, "Sub", "Sub", "## Sub\n")
+            ]
+        );
+        assert_eq!(doc.headings[0].section, 0..SRC.len());
+    }
+
+    #[test]
+    fn references() {
+        let doc = Document::new(SRC.to_string());
+        let link = |tag: &str, url: Option<&str>| ReferenceKind::Link {
+            tag: tag.to_string(),
+            url: url.map(ToString::to_string),
+        };
+        let refs: Vec<_> = doc
+            .references
+            .iter()
+            .map(|Reference { span, kind }| (&SRC[span.clone()], kind.clone()))
+            .collect();
+        assert_eq!(
+            refs,
+            &[
+                ("[a][b]", link("b", Some("/b"))),
+                ("[c][]", link("c", Some("/c"))),
+                ("![i][b]", link("b", Some("/b"))),
+                ("[Head x][]", link("Head x", Some("#Head-x"))),
+                (
+                    "[^f]",
+                    ReferenceKind::Footnote {
+                        label: "f".to_string()
+                    }
+                ),
+                ("[v][w]", link("w", None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn definition() {
+        let doc = Document::new(SRC.to_string());
+        let def = |s: &str| {
+            let r = doc.reference_at(SRC.find(s).unwrap()).unwrap();
+            doc.definition(r).map(|span| &SRC[span])
+        };
+        assert_eq!(def("[a][b]"), Some("[b]: /b\n"));
+        assert_eq!(def("[Head x]"), Some("# Head *x*\n"));
+        assert_eq!(def("[^f]"), Some("[^f]: note\n"));
+        assert_eq!(def("[v]"), None);
+        assert_eq!(doc.reference_at(0), None);
+        assert_eq!(doc.reference_at(SRC.find("[u]").unwrap()), None);
+    }
+
+    #[test]
+    fn completions() {
+        let doc = Document::new(SRC.to_string());
+        let at = |s: &str| doc.completions(SRC.find(s).unwrap() + s.len());
+        let labels = |cs: Vec<(String, String)>| cs.into_iter().map(|(l, _)| l).collect::<Vec<_>>();
+        assert_eq!(labels(at("[a][")), &["b", "c"]);
+        assert_eq!(
+            at("[a][b] [c][] ![i][b] [Head x][] [^"),
+            &[("f".to_string(), "[^f]: note".to_string())]
+        );
+        assert_eq!(labels(at("[u](")), Vec::<String>::new());
+        assert_eq!(labels(at("# Head")), Vec::<String>::new());
+
+        let doc = Document::new("# A\n\n# B\n\n[x](#".to_string());
+        assert_eq!(labels(doc.completions(doc.text.len())), &["A", "B"]);
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::ops::Range;
+use std::process::exit;
+
+use jotdown::LineColumn;
+use jotdown::LineIndex;
+use jotdown::Severity;
+use serde_json::json;
+use serde_json::Value;
+
+mod document;
+
+use document::Document;
+use document::ReferenceKind;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A language server communicating with a single client.
+struct Server<W: Write> {
+    out: W,
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn new(out: W) -> Self {
+        Self {
+            out,
+            documents: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Handle a single message from the client. Returns false if the server should exit.
+    fn handle(&mut self, msg: &Value) -> io::Result<bool> {
+        let method = msg["method"].as_str().unwrap_or_default();
+        let params = &msg["params"];
+        let id = match msg.get("id") {
+            Some(id) => id,
+            None => {
+                match method {
+                    "exit" => return Ok(false),
+                    "textDocument/didOpen" => {
+                        let doc = &params["textDocument"];
+                        self.update(uri(params), doc["text"].as_str().unwrap_or_default())?;
+                    }
+                    "textDocument/didChange" => {
+                        // full sync, the last change contains the whole document
+                        let changes = params["contentChanges"].as_array();
+                        if let Some(change) = changes.and_then(|cs| cs.last()) {
+                            self.update(uri(params), change["text"].as_str().unwrap_or_default())?;
+                        }
+                    }
+                    "textDocument/didClose" => {
+                        let uri = uri(params);
+                        self.documents.remove(uri);
+                        self.notify(
+                            "textDocument/publishDiagnostics",
+                            json!({ "uri": uri, "diagnostics": [] }),
+                        )?;
+                    }
+                    _ => {}
+                }
+                return Ok(true);
+            }
+        };
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "documentSymbolProvider": true,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["[", "^", "#"] },
+                },
+                "serverInfo": {
+                    "name": env!("CARGO_BIN_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            }),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            _ => {
+                return self
+                    .send(json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("unknown method '{}'", method),
+                        },
+                    }))
+                    .map(|_| true);
+            }
+        };
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }))?;
+        Ok(true)
+    }
+
+    fn update(&mut self, uri: &str, text: &str) -> io::Result<()> {
+        let doc = Document::new(text.to_string());
+        let index = LineIndex::new(&doc.text);
+        let diagnostics: Vec<_> = doc
+            .diagnostics
+            .iter()
+            .map(|d| {
+                json!({
+                    "range": range(&index, d.span.clone()),
+                    "severity": match d.severity {
+                        Severity::Warning => 2,
+                        Severity::Information => 3,
+                    },
+                    "source": "jotdown",
+                    "message": d.to_string(),
+                })
+            })
+            .collect();
+        self.documents.insert(uri.to_string(), doc);
+        self.notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    /// The document of the request along with a line index and the byte offset of the requested
+    /// position.
+    fn document_at(&self, params: &Value) -> Option<(&Document, LineIndex<'_>, usize)> {
+        let doc = self.documents.get(uri(params))?;
+        let index = LineIndex::new(&doc.text);
+        let pos = &params["position"];
+        let offset = index.offset_utf16(LineColumn {
+            line: pos["line"].as_u64()? as usize,
+            column: pos["character"].as_u64()? as usize,
+        })?;
+        Some((doc, index, offset))
+    }
+
+    /// Create an outline of the document from its headings, with subheadings as children.
+    fn document_symbols(&self, params: &Value) -> Value {
+        let doc = match self.documents.get(uri(params)) {
+            Some(doc) => doc,
+            None => return Value::Null,
+        };
+        let index = LineIndex::new(&doc.text);
+
+        fn close(stack: &mut Vec<(u16, Value)>, roots: &mut Vec<Value>) {
+            let (_, symbol) = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some((_, parent)) => parent["children"].as_array_mut().unwrap().push(symbol),
+                None => roots.push(symbol),
+            }
+        }
+
+        let mut roots = Vec::new();
+        let mut stack: Vec<(u16, Value)> = Vec::new();
+        for h in &doc.headings {
+            while matches!(stack.last(), Some((level, _)) if *level >= h.level) {
+                close(&mut stack, &mut roots);
+            }
+            let name = if h.text.trim().is_empty() {
+                format!("#{}", h.id)
+            } else {
+                h.text.trim().to_string()
+            };
+            let symbol = json!({
+                "name": name,
+                "detail": format!("#{}", h.id),
+                "kind": 3, // namespace
+                "range": range(&index, h.section.clone()),
+                "selectionRange": range(&index, h.span.clone()),
+                "children": [],
+            });
+            stack.push((h.level, symbol));
+        }
+        while !stack.is_empty() {
+            close(&mut stack, &mut roots);
+        }
+        Value::Array(roots)
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        self.document_at(params)
+            .and_then(|(doc, index, offset)| {
+                let span = doc.definition(doc.reference_at(offset)?)?;
+                Some(json!({ "uri": uri(params), "range": range(&index, span) }))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Show the url of a link or image, or the source of a footnote.
+    fn hover(&self, params: &Value) -> Value {
+        self.document_at(params)
+            .and_then(|(doc, index, offset)| {
+                let reference = doc.reference_at(offset)?;
+                let value = match &reference.kind {
+                    ReferenceKind::Link { url, .. } => url.clone()?,
+                    ReferenceKind::Footnote { .. } => {
+                        doc.text[doc.definition(reference)?].trim_end().to_string()
+                    }
+                };
+                Some(json!({
+                    "contents": { "kind": "plaintext", "value": value },
+                    "range": range(&index, reference.span.clone()),
+                }))
+            })
+            .unwrap_or_default()
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        self.document_at(params)
+            .map(|(doc, _, offset)| {
+                doc.completions(offset)
+                    .into_iter()
+                    .map(|(label, detail)| {
+                        json!({
+                            "label": label,
+                            "detail": detail,
+                            "kind": 18, // reference
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn send(&mut self, msg: Value) -> io::Result<()> {
+        let content = msg.to_string();
+        write!(
+            self.out,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )?;
+        self.out.flush()
+    }
+}
+
+fn uri(params: &Value) -> &str {
+    params["textDocument"]["uri"].as_str().unwrap_or_default()
+}
+
+fn range(index: &LineIndex, span: Range<usize>) -> Value {
+    let position = |offset| {
+        let pos = index.line_column_utf16(offset).unwrap_or_default();
+        json!({ "line": pos.line, "character": pos.column })
+    };
+    json!({ "start": position(span.start), "end": position(span.end) })
+}
+
+/// Read a single message, or `None` at the end of the input.
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Value>> {
+    let mut len = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                len = value.trim().parse().ok();
+            }
+        }
+    }
+    let len = len.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no content length"))?;
+    let mut buf = vec![0; len];
+    input.read_exact(&mut buf)?;
+    serde_json::from_slice(&buf)
+        .map(Some)
+        .map_err(io::Error::from)
+}
+
+fn run() -> io::Result<bool> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut server = Server::new(io::stdout());
+    while let Some(msg) = read_message(&mut input)? {
+        if !server.handle(&msg)? {
+            break;
+        }
+    }
+    Ok(server.shutdown)
+}
+
+fn main() {
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                eprintln!("usage: {} [--stdio]", env!("CARGO_BIN_NAME"));
+                eprintln!();
+                eprintln!("a language server for djot, communicating over stdio");
+                exit(0);
+            }
+            "-v" | "--version" => {
+                eprintln!("{} v{}", env!("CARGO_BIN_NAME"), env!("CARGO_PKG_VERSION"));
+                exit(0);
+            }
+            "--stdio" => {}
+            flag => {
+                eprintln!("unknown flag: {}", flag);
+                exit(1);
+            }
+        }
+    }
+
+    match run() {
+        Ok(true) => {}
+        Ok(false) => exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::read_message;
+    use super::Server;
+    use serde_json::json;
+    use serde_json::Value;
+
+    fn messages(out: &[u8]) -> Vec<Value> {
+        let mut out = out;
+        std::iter::from_fn(|| read_message(&mut out).unwrap()).collect()
+    }
+
+    #[test]
+    fn session() {
+        let uri = "file:///a.dj";
+        let position = |line, character| {
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character },
+            })
+        };
+        let mut server = Server::new(Vec::new());
+        for msg in &[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": {
+                    "textDocument": {
+                        "uri": uri,
+                        "text": "# A\n\n## B\n\n[x][y] [z][]\n\n[y]: /url\n",
+                    },
+                },
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "textDocument/documentSymbol",
+                "params": { "textDocument": { "uri": uri } },
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "textDocument/definition",
+                "params": position(4, 1),
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 4,
+                "method": "textDocument/hover",
+                "params": position(4, 1),
+            }),
+            json!({ "jsonrpc": "2.0", "id": 5, "method": "unknown", "params": {} }),
+        ] {
+            assert!(server.handle(msg).unwrap());
+        }
+        assert!(!server
+            .handle(&json!({ "jsonrpc": "2.0", "method": "exit" }))
+            .unwrap());
+
+        let msgs = messages(&server.out);
+        assert_eq!(msgs.len(), 6);
+        assert_eq!(msgs[0]["result"]["capabilities"]["hoverProvider"], true);
+        assert_eq!(
+            msgs[1]["params"]["diagnostics"][0]["message"],
+            "unresolved reference 'z'",
+        );
+        assert_eq!(
+            msgs[1]["params"]["diagnostics"][0]["range"],
+            json!({
+                "start": { "line": 4, "character": 7 },
+                "end": { "line": 4, "character": 12 },
+            }),
+        );
+        let symbols = &msgs[2]["result"];
+        assert_eq!(symbols[0]["name"], "A");
+        assert_eq!(symbols[0]["children"][0]["name"], "B");
+        assert_eq!(symbols[0]["children"][0]["detail"], "#B");
+        assert_eq!(msgs[3]["result"]["range"]["start"]["line"], 6);
+        assert_eq!(msgs[4]["result"]["contents"]["value"], "/url");
+        assert_eq!(msgs[5]["error"]["code"], -32601);
+    }
+}
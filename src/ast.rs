@@ -0,0 +1,517 @@
+//! An owned tree representation of a Djot document.
+//!
+//! The [`Parser`](crate::Parser) emits a flat sequence of [`Event`]s. For some tasks, e.g. finding
+//! the caption of a table or moving a section, it is more convenient to work on a tree. A
+//! [`Document`] can be built from any iterator of [`Event`]s and turned back into an iterator of
+//! [`Event`]s, so a modified tree may be rendered with any [`Render`](crate::Render)
+//! implementation.
+//!
+//! # Examples
+//!
+//! Remove all images from a document:
+//!
+//! ```
+//! # #[cfg(feature = "html")]
+//! # {
+//! use jotdown::ast::Document;
+//! use jotdown::Container;
+//! use jotdown::Render;
+//!
+//! let mut doc = Document::from_offset_iter(
+//!     jotdown::Parser::new("an ![image](img.png) here").into_offset_iter(),
+//! );
+//! doc.retain(|node| !matches!(node.container(), Some(Container::Image(..))));
+//!
+//! let mut html = String::new();
+//! jotdown::html::Renderer::default().push(doc.into_iter(), &mut html);
+//! assert_eq!(html, "<p>an  here</p>\n");
+//! # }
+//! ```
+
+use std::ops::Range;
+
+use crate::Attributes;
+use crate::Container;
+use crate::Event;
+
+/// The root of a document tree.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Document<'s> {
+    /// Top-level nodes of the document.
+    pub children: Vec<Node<'s>>,
+}
+
+/// A node within a document tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node<'s> {
+    /// Contents of the node.
+    pub kind: NodeKind<'s>,
+    /// Location of the node within the input.
+    ///
+    /// For containers, the span covers both the start and end event, and thereby all of its
+    /// children. The span is empty if the tree was not built from an offset iterator.
+    pub span: Range<usize>,
+}
+
+/// The kind of a [`Node`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind<'s> {
+    /// A container, corresponding to a matching [`Event::Start`] and [`Event::End`] pair.
+    Container {
+        container: Container<'s>,
+        attributes: Attributes<'s>,
+        children: Vec<Node<'s>>,
+    },
+    /// An atomic element, i.e. any event that is not [`Event::Start`] or [`Event::End`].
+    Atom(Event<'s>),
+}
+
+impl<'s> Node<'s> {
+    /// Create a container node without a location.
+    #[must_use]
+    pub fn new_container(
+        container: Container<'s>,
+        attributes: Attributes<'s>,
+        children: Vec<Node<'s>>,
+    ) -> Self {
+        Self {
+            kind: NodeKind::Container {
+                container,
+                attributes,
+                children,
+            },
+            span: 0..0,
+        }
+    }
+
+    /// Create an atomic node without a location.
+    ///
+    /// # Panics
+    ///
+    /// If the event is an [`Event::Start`] or [`Event::End`].
+    #[must_use]
+    pub fn new_atom(event: Event<'s>) -> Self {
+        assert!(
+            !matches!(event, Event::Start(..) | Event::End(..)),
+            "container events can not be atoms"
+        );
+        Self {
+            kind: NodeKind::Atom(event),
+            span: 0..0,
+        }
+    }
+
+    /// Returns the container of the node, if it is a container.
+    #[must_use]
+    pub fn container(&self) -> Option<&Container<'s>> {
+        match &self.kind {
+            NodeKind::Container { container, .. } => Some(container),
+            NodeKind::Atom(_) => None,
+        }
+    }
+
+    /// Returns the attributes of the node, if it is a container or a thematic break.
+    #[must_use]
+    pub fn attributes(&self) -> Option<&Attributes<'s>> {
+        match &self.kind {
+            NodeKind::Container { attributes, .. }
+            | NodeKind::Atom(Event::ThematicBreak(attributes)) => Some(attributes),
+            NodeKind::Atom(_) => None,
+        }
+    }
+
+    /// Returns a mutable reference to the attributes of the node, if it is a container or a
+    /// thematic break.
+    pub fn attributes_mut(&mut self) -> Option<&mut Attributes<'s>> {
+        match &mut self.kind {
+            NodeKind::Container { attributes, .. }
+            | NodeKind::Atom(Event::ThematicBreak(attributes)) => Some(attributes),
+            NodeKind::Atom(_) => None,
+        }
+    }
+
+    /// Returns the children of the node, empty if the node is an atom.
+    #[must_use]
+    pub fn children(&self) -> &[Node<'s>] {
+        match &self.kind {
+            NodeKind::Container { children, .. } => children,
+            NodeKind::Atom(_) => &[],
+        }
+    }
+
+    /// Returns a mutable reference to the children of the node, if it is a container.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<Node<'s>>> {
+        match &mut self.kind {
+            NodeKind::Container { children, .. } => Some(children),
+            NodeKind::Atom(_) => None,
+        }
+    }
+
+    /// Returns an iterator over the node and all of its descendants, in document order.
+    pub fn descendants(&self) -> Descendants<'_, 's> {
+        Descendants {
+            stack: vec![std::slice::from_ref(self).iter()],
+        }
+    }
+
+    /// Returns an iterator over the events that represent the node.
+    pub fn events(&self) -> Events<'_, 's> {
+        Events {
+            stack: vec![(None, std::slice::from_ref(self).iter())],
+        }
+    }
+
+    /// Retain only the descendants specified by the predicate.
+    ///
+    /// The predicate is applied to a node before any of its children. If a node is removed, its
+    /// children will not be visited.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Node<'s>) -> bool,
+    {
+        if let Some(children) = self.children_mut() {
+            retain(children, &mut f);
+        }
+    }
+}
+
+impl<'s> Document<'s> {
+    /// Create an empty document.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a tree from an iterator of events and their locations, e.g. from
+    /// [`Parser::into_offset_iter`](crate::Parser::into_offset_iter).
+    ///
+    /// [`Event::End`] events without a corresponding [`Event::Start`] are ignored and containers
+    /// that are not closed are closed at the end of the input.
+    pub fn from_offset_iter<I>(events: I) -> Self
+    where
+        I: IntoIterator<Item = (Event<'s>, Range<usize>)>,
+    {
+        let mut builder = Builder::default();
+        events
+            .into_iter()
+            .for_each(|(e, span)| builder.push(e, span));
+        builder.finish()
+    }
+
+    /// Returns an iterator over all nodes of the document, in document order.
+    pub fn descendants(&self) -> Descendants<'_, 's> {
+        Descendants {
+            stack: vec![self.children.iter()],
+        }
+    }
+
+    /// Returns an iterator over the events that represent the document.
+    pub fn events(&self) -> Events<'_, 's> {
+        Events {
+            stack: vec![(None, self.children.iter())],
+        }
+    }
+
+    /// Retain only the nodes specified by the predicate.
+    ///
+    /// The predicate is applied to a node before any of its children. If a node is removed, its
+    /// children will not be visited.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Node<'s>) -> bool,
+    {
+        retain(&mut self.children, &mut f);
+    }
+}
+
+fn retain<'s, F>(nodes: &mut Vec<Node<'s>>, f: &mut F)
+where
+    F: FnMut(&Node<'s>) -> bool,
+{
+    nodes.retain(|n| f(n));
+    for n in nodes {
+        if let Some(children) = n.children_mut() {
+            retain(children, f);
+        }
+    }
+}
+
+impl<'s> FromIterator<Event<'s>> for Document<'s> {
+    /// Build a tree from an iterator of events, without any locations.
+    ///
+    /// See [`Document::from_offset_iter`].
+    fn from_iter<I: IntoIterator<Item = Event<'s>>>(events: I) -> Self {
+        Self::from_offset_iter(events.into_iter().map(|e| (e, 0..0)))
+    }
+}
+
+impl<'s> IntoIterator for Document<'s> {
+    type Item = Event<'s>;
+    type IntoIter = IntoEvents<'s>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoEvents {
+            stack: vec![(None, self.children.into_iter())],
+        }
+    }
+}
+
+impl<'s> IntoIterator for Node<'s> {
+    type Item = Event<'s>;
+    type IntoIter = IntoEvents<'s>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoEvents {
+            stack: vec![(None, vec![self].into_iter())],
+        }
+    }
+}
+
+#[derive(Default)]
+struct Builder<'s> {
+    /// Open containers, with their start location and attributes.
+    open: Vec<(Container<'s>, Attributes<'s>, Range<usize>, Vec<Node<'s>>)>,
+    /// Finished top-level nodes.
+    children: Vec<Node<'s>>,
+}
+
+impl<'s> Builder<'s> {
+    fn push(&mut self, event: Event<'s>, span: Range<usize>) {
+        match event {
+            Event::Start(container, attributes) => {
+                self.open.push((container, attributes, span, Vec::new()));
+            }
+            Event::End(container) => {
+                if let Some(i) = self.open.iter().rposition(|(c, ..)| *c == container) {
+                    while self.open.len() > i {
+                        self.close(span.clone());
+                    }
+                }
+            }
+            event => self.children().push(Node {
+                kind: NodeKind::Atom(event),
+                span,
+            }),
+        }
+    }
+
+    fn children(&mut self) -> &mut Vec<Node<'s>> {
+        self.open
+            .last_mut()
+            .map_or(&mut self.children, |(.., children)| children)
+    }
+
+    fn close(&mut self, end: Range<usize>) {
+        let (container, attributes, start, children) = self.open.pop().unwrap();
+        // caption end may be located before its start, as it is emitted before the table rows
+        let span = start.start.min(end.start)..start.end.max(end.end);
+        self.children().push(Node {
+            kind: NodeKind::Container {
+                container,
+                attributes,
+                children,
+            },
+            span,
+        });
+    }
+
+    fn finish(mut self) -> Document<'s> {
+        while let Some((.., start, _)) = self.open.last() {
+            let end = start.end..start.end;
+            self.close(end);
+        }
+        Document {
+            children: self.children,
+        }
+    }
+}
+
+/// An iterator over the nodes of a tree, in document order.
+///
+/// See [`Document::descendants`] and [`Node::descendants`].
+pub struct Descendants<'a, 's> {
+    stack: Vec<std::slice::Iter<'a, Node<'s>>>,
+}
+
+impl<'a, 's> Iterator for Descendants<'a, 's> {
+    type Item = &'a Node<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(nodes) = self.stack.last_mut() {
+            if let Some(node) = nodes.next() {
+                self.stack.push(node.children().iter());
+                return Some(node);
+            }
+            self.stack.pop();
+        }
+        None
+    }
+}
+
+/// An iterator over the events of a borrowed tree.
+///
+/// See [`Document::events`] and [`Node::events`].
+pub struct Events<'a, 's> {
+    stack: Vec<(Option<&'a Container<'s>>, std::slice::Iter<'a, Node<'s>>)>,
+}
+
+impl<'a, 's> Iterator for Events<'a, 's> {
+    type Item = Event<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (container, nodes) = self.stack.last_mut()?;
+        if let Some(node) = nodes.next() {
+            Some(match &node.kind {
+                NodeKind::Container {
+                    container,
+                    attributes,
+                    children,
+                } => {
+                    self.stack.push((Some(container), children.iter()));
+                    Event::Start(container.clone(), attributes.clone())
+                }
+                NodeKind::Atom(e) => e.clone(),
+            })
+        } else {
+            let container = *container;
+            self.stack.pop();
+            container
+                .map(|c| Event::End(c.clone()))
+                .or_else(|| self.next())
+        }
+    }
+}
+
+/// An iterator over the events of an owned tree.
+///
+/// See [`Document::into_iter`] and [`Node::into_iter`].
+pub struct IntoEvents<'s> {
+    stack: Vec<(Option<Container<'s>>, std::vec::IntoIter<Node<'s>>)>,
+}
+
+impl<'s> Iterator for IntoEvents<'s> {
+    type Item = Event<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, nodes) = self.stack.last_mut()?;
+        if let Some(node) = nodes.next() {
+            Some(match node.kind {
+                NodeKind::Container {
+                    container,
+                    attributes,
+                    children,
+                } => {
+                    self.stack
+                        .push((Some(container.clone()), children.into_iter()));
+                    Event::Start(container, attributes)
+                }
+                NodeKind::Atom(e) => e,
+            })
+        } else {
+            let (container, _) = self.stack.pop().unwrap();
+            container.map(Event::End).or_else(|| self.next())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Document;
+    use super::Node;
+    use crate::Attributes;
+    use crate::Container::*;
+    use crate::Event;
+    use crate::Event::*;
+    use crate::Parser;
+
+    #[test]
+    fn roundtrip() {
+        let src = concat!(
+            "# heading\n",
+            "\n",
+            "{.class}\n",
+            "> quote with _emph_ and [link](url)\n",
+            "\n",
+            "| a | b |\n",
+            "^ caption\n",
+            "\n",
+            "- item[^fn]\n",
+            "\n",
+            "[^fn]: note\n",
+        );
+        let events = Parser::new(src).collect::<Vec<_>>();
+        let doc = events.iter().cloned().collect::<Document>();
+        assert_eq!(doc.events().collect::<Vec<_>>(), events);
+        assert_eq!(doc.into_iter().collect::<Vec<_>>(), events);
+    }
+
+    #[test]
+    fn span() {
+        let src = "para _emph_\n";
+        let doc = Document::from_offset_iter(Parser::new(src).into_offset_iter());
+        let para = &doc.children[0];
+        assert_eq!(para.container(), Some(&Paragraph));
+        let emph = &para.children()[1];
+        assert_eq!(emph.container(), Some(&Emphasis));
+        assert_eq!(&src[emph.span.clone()], "_emph_");
+        assert_eq!(&src[emph.children()[0].span.clone()], "emph");
+    }
+
+    #[test]
+    fn span_caption() {
+        let src = "| a |\n^ caption\n";
+        let doc = Document::from_offset_iter(Parser::new(src).into_offset_iter());
+        let caption = doc
+            .descendants()
+            .find(|n| matches!(n.container(), Some(Caption)))
+            .unwrap();
+        assert_eq!(&src[caption.children()[0].span.clone()], "caption");
+        assert!(src[caption.span.clone()].ends_with("caption\n"));
+    }
+
+    #[test]
+    fn modify() {
+        let mut doc = Parser::new("a *b* c").collect::<Document>();
+        let para = &mut doc.children[0];
+        para.children_mut().unwrap()[1] = Node::new_atom(Str("x".into()));
+        assert_eq!(
+            doc.into_iter().collect::<Vec<_>>(),
+            &[
+                Start(Paragraph, Attributes::new()),
+                Str("a ".into()),
+                Str("x".into()),
+                Str(" c".into()),
+                End(Paragraph),
+            ]
+        );
+    }
+
+    #[test]
+    fn unbalanced() {
+        let doc = [
+            Event::End(Paragraph),
+            Start(Blockquote, Attributes::new()),
+            Start(Paragraph, Attributes::new()),
+            Str("a".into()),
+            End(Blockquote),
+            Start(Paragraph, Attributes::new()),
+        ]
+        .into_iter()
+        .collect::<Document>();
+        assert_eq!(
+            doc.children,
+            &[
+                Node::new_container(
+                    Blockquote,
+                    Attributes::new(),
+                    vec![Node::new_container(
+                        Paragraph,
+                        Attributes::new(),
+                        vec![Node::new_atom(Str("a".into()))]
+                    )],
+                ),
+                Node::new_container(Paragraph, Attributes::new(), vec![]),
+            ]
+        );
+    }
+}
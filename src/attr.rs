@@ -1,5 +1,6 @@
 use crate::CowStr;
 use std::fmt;
+use std::ops::Range;
 
 /// Parse attributes, assumed to be valid.
 pub(crate) fn parse(src: &str) -> Attributes {
@@ -32,6 +33,8 @@ pub fn valid(src: &str) -> usize {
 /// Stores an attribute value that supports backslash escapes of ASCII punctuation upon displaying,
 /// without allocating.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct AttributeValue<'s> {
     raw: CowStr<'s>,
 }
@@ -43,6 +46,14 @@ impl<'s> AttributeValue<'s> {
         AttributeValueParts { ahead: &self.raw }
     }
 
+    /// Convert the value into one that owns its data, i.e. that does not borrow from the input.
+    #[must_use]
+    pub fn into_owned(self) -> AttributeValue<'static> {
+        AttributeValue {
+            raw: self.raw.into_owned().into(),
+        }
+    }
+
     // lifetime is 's to avoid allocation if empty value is concatenated with single value
     fn extend(&mut self, s: &'s str) {
         match &mut self.raw {
@@ -114,12 +125,55 @@ impl<'s> Iterator for AttributeValueParts<'s> {
     }
 }
 
+/// A comment within attributes, e.g. `%comment%` in `{.class %comment%}`, see
+/// [`Attributes::from_str_with_comments`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeComment<'s> {
+    /// The text of the comment, without the surrounding `%`.
+    pub text: &'s str,
+    /// The location of the comment within the input, including the surrounding `%`.
+    pub span: Range<usize>,
+}
+
+/// An error that occurred while parsing attributes with [`Attributes::from_str_with_comments`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeError {
+    /// Byte offset in the input where the error was detected.
+    pub offset: usize,
+    /// The kind of error.
+    pub kind: AttributeErrorKind,
+}
+
+/// The kind of an [`AttributeError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeErrorKind {
+    /// A character that is not allowed by the attribute syntax at its position.
+    UnexpectedCharacter,
+    /// The input ended before the attributes were closed.
+    UnexpectedEnd,
+}
+
+impl fmt::Display for AttributeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            AttributeErrorKind::UnexpectedCharacter => {
+                write!(f, "unexpected character at byte {}", self.offset)
+            }
+            AttributeErrorKind::UnexpectedEnd => {
+                write!(f, "unexpected end of attributes at byte {}", self.offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AttributeError {}
+
 /// A collection of attributes, i.e. a key-value map.
 // Attributes are relatively rare, we choose to pay 8 bytes always and sometimes an extra
 // indirection instead of always 24 bytes.
 #[allow(clippy::box_vec)]
 #[derive(Clone, PartialEq, Eq, Default)]
-pub struct Attributes<'s>(Option<Box<Vec<(&'s str, AttributeValue<'s>)>>>);
+pub struct Attributes<'s>(Option<Box<Vec<(CowStr<'s>, AttributeValue<'s>)>>>);
 
 impl<'s> Attributes<'s> {
     /// Create an empty collection.
@@ -128,6 +182,78 @@ impl<'s> Attributes<'s> {
         Self::default()
     }
 
+    /// Parse attributes from their Djot syntax, e.g. `{.class #id key="value"}`.
+    ///
+    /// The input must consist solely of one or more consecutive attribute sets, e.g.
+    /// `{.a}{.b}`, without any surrounding whitespace. The parsed attributes are returned along
+    /// with any comments within them, in the order they appear.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let src = r#"{.warning #intro %note% key="val"}"#;
+    /// let (attrs, comments) = Attributes::from_str_with_comments(src).unwrap();
+    /// assert_eq!(attrs.classes().collect::<Vec<_>>(), &["warning"]);
+    /// assert_eq!(attrs.id().map(ToString::to_string), Some("intro".to_string()));
+    /// assert_eq!(attrs.get("key").map(ToString::to_string), Some("val".to_string()));
+    /// assert_eq!(comments[0].text, "note");
+    /// assert_eq!(comments[0].span, 17..23);
+    ///
+    /// let err = Attributes::from_str_with_comments("{.a .}").unwrap_err();
+    /// assert_eq!(err.offset, 5);
+    /// assert_eq!(err.kind, AttributeErrorKind::UnexpectedCharacter);
+    /// ```
+    pub fn from_str_with_comments(
+        src: &'s str,
+    ) -> Result<(Self, Vec<AttributeComment<'s>>), AttributeError> {
+        let mut comments = Vec::new();
+        let mut comment_start = 0;
+        let mut state = State::Start;
+        for (i, c) in src.bytes().enumerate() {
+            if matches!(state, State::Done) {
+                state = State::Start;
+            }
+            let next = state.step(c);
+            match (state, next) {
+                (_, State::Invalid) => {
+                    return Err(AttributeError {
+                        offset: i,
+                        kind: AttributeErrorKind::UnexpectedCharacter,
+                    });
+                }
+                (State::Whitespace, State::Comment) => comment_start = i,
+                (State::Comment, State::Whitespace) => comments.push(AttributeComment {
+                    text: &src[comment_start + 1..i],
+                    span: comment_start..i + 1,
+                }),
+                _ => {}
+            }
+            state = next;
+        }
+
+        if matches!(state, State::Done) {
+            Ok((parse(src), comments))
+        } else {
+            Err(AttributeError {
+                offset: src.len(),
+                kind: AttributeErrorKind::UnexpectedEnd,
+            })
+        }
+    }
+
+    /// Convert the attributes into ones that own all of their data, i.e. that do not borrow from
+    /// the input.
+    #[must_use]
+    pub fn into_owned(self) -> Attributes<'static> {
+        Attributes(self.0.map(|v| {
+            v.into_iter()
+                .map(|(k, v)| (k.into_owned().into(), v.into_owned()))
+                .collect::<Vec<_>>()
+                .into()
+        }))
+    }
+
     #[must_use]
     pub(crate) fn take(&mut self) -> Self {
         Self(self.0.take())
@@ -141,7 +267,27 @@ impl<'s> Attributes<'s> {
     }
 
     /// Combine all attributes from both objects, prioritizing self on conflicts.
-    pub(crate) fn union(&mut self, other: Self) {
+    ///
+    /// Attributes from other whose key already exists in self are ignored, except for classes
+    /// which are appended after the classes of self.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let mut attrs = Attributes::new();
+    /// attrs.insert("id", "a".into());
+    /// attrs.insert("class", "b".into());
+    /// let mut other = Attributes::new();
+    /// other.insert("id", "c".into());
+    /// other.insert("class", "d".into());
+    /// other.insert("key", "e".into());
+    /// attrs.union(other);
+    /// assert_eq!(attrs.id().map(ToString::to_string), Some("a".to_string()));
+    /// assert_eq!(attrs.classes().collect::<Vec<_>>(), &["b", "d"]);
+    /// assert_eq!(attrs.get("key").map(ToString::to_string), Some("e".to_string()));
+    /// ```
+    pub fn union(&mut self, other: Self) {
         if let Some(attrs0) = &mut self.0 {
             if let Some(mut attrs1) = other.0 {
                 for (key, val) in attrs1.drain(..) {
@@ -155,15 +301,37 @@ impl<'s> Attributes<'s> {
         }
     }
 
+    /// Inverse of [`Attributes::union`], find the attributes that result in self when combined
+    /// with other.
+    #[cfg(any(feature = "djot", feature = "json"))]
+    pub(crate) fn difference(&self, other: &Self) -> Option<Self> {
+        let attrs = self.0.as_deref().map_or(&[][..], Vec::as_slice);
+        (0..=attrs.len()).rev().find_map(|n| {
+            let (own, rest) = attrs.split_at(n);
+            let added = other
+                .iter()
+                .filter(|(k, _)| *k == "class" || !own.iter().any(|(o, _)| o == k));
+            if added.eq(rest.iter().map(|(k, v)| (k.as_ref(), v))) {
+                Some(if own.is_empty() {
+                    Self::new()
+                } else {
+                    Self(Some(own.to_vec().into()))
+                })
+            } else {
+                None
+            }
+        })
+    }
+
     /// Insert an attribute. If the attribute already exists, the previous value will be
     /// overwritten, unless it is a "class" attribute. In that case the provided value will be
     /// appended to the existing value.
-    pub fn insert(&mut self, key: &'s str, val: AttributeValue<'s>) {
-        self.insert_pos(key, val);
+    pub fn insert<K: Into<CowStr<'s>>>(&mut self, key: K, val: AttributeValue<'s>) {
+        self.insert_pos(key.into(), val);
     }
 
     // duplicate of insert but returns position of inserted value
-    fn insert_pos(&mut self, key: &'s str, val: AttributeValue<'s>) -> usize {
+    fn insert_pos(&mut self, key: CowStr<'s>, val: AttributeValue<'s>) -> usize {
         if self.0.is_none() {
             self.0 = Some(Vec::new().into());
         };
@@ -201,9 +369,144 @@ impl<'s> Attributes<'s> {
         self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
     }
 
+    /// Returns a reference to the value of the id attribute.
+    #[must_use]
+    pub fn id(&self) -> Option<&AttributeValue<'s>> {
+        self.get("id")
+    }
+
+    /// Set an attribute, overwriting any previous value.
+    ///
+    /// Unlike [`Attributes::insert`], the value replaces all existing classes if the key is
+    /// "class". An existing attribute keeps its position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let mut attrs = Attributes::new();
+    /// attrs.insert("class", "a".into());
+    /// attrs.insert("id", "b".into());
+    /// attrs.insert("class", "c".into());
+    /// attrs.set("class", "d".into());
+    /// attrs.set("id", "e".into());
+    /// assert_eq!(
+    ///     attrs.iter().map(|(k, v)| (k, v.to_string())).collect::<Vec<_>>(),
+    ///     &[("class", "d".to_string()), ("id", "e".to_string())],
+    /// );
+    /// ```
+    pub fn set<K: Into<CowStr<'s>>>(&mut self, key: K, val: AttributeValue<'s>) {
+        let key = key.into();
+        let attrs = self.0.get_or_insert_with(Default::default);
+        if let Some(i) = attrs.iter().position(|(k, _)| *k == key) {
+            attrs[i].1 = val;
+            let mut j = 0;
+            attrs.retain(|(k, _)| {
+                j += 1;
+                j - 1 == i || *k != key
+            });
+        } else {
+            attrs.push((key, val));
+        }
+    }
+
+    /// Remove an attribute and return its value, if it existed.
+    ///
+    /// If the key is "class", all classes are removed and returned, separated by spaces.
+    pub fn remove(&mut self, key: &str) -> Option<AttributeValue<'s>> {
+        let attrs = self.0.as_mut()?;
+        let mut removed: Option<AttributeValue<'s>> = None;
+        let mut i = 0;
+        while i < attrs.len() {
+            if attrs[i].0 == key {
+                let (_, val) = attrs.remove(i);
+                if let Some(prev) = &mut removed {
+                    *prev = format!("{} {}", prev.raw, val.raw).into();
+                } else {
+                    removed = Some(val);
+                }
+            } else {
+                i += 1;
+            }
+        }
+        if attrs.is_empty() {
+            self.0 = None;
+        }
+        removed
+    }
+
+    /// Returns an iterator over all classes, i.e. the whitespace separated words of all class
+    /// attributes. The classes of each class attribute are given in order, one attribute after
+    /// another.
+    ///
+    /// The classes are given as written, i.e. without processing backslash escapes.
+    pub fn classes(&self) -> impl Iterator<Item = &str> + '_ {
+        self.iter()
+            .filter(|(k, _)| *k == "class")
+            .flat_map(|(_, v)| v.raw.split_ascii_whitespace())
+    }
+
+    /// Returns true if the class is one of the classes of the collection.
+    #[must_use]
+    pub fn has_class(&self, class: &str) -> bool {
+        self.classes().any(|c| c == class)
+    }
+
+    /// Add a class, unless it already exists.
+    ///
+    /// The class is appended to the first class attribute, or a new class attribute is inserted if
+    /// there is none. With several class attributes, e.g. after [`Attributes::union`], it may
+    /// thus not be the last of [`Attributes::classes`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let mut attrs = Attributes::new();
+    /// attrs.add_class("a");
+    /// attrs.add_class("b");
+    /// attrs.add_class("a");
+    /// assert_eq!(attrs.get("class").map(ToString::to_string), Some("a b".to_string()));
+    /// attrs.remove_class("a");
+    /// assert_eq!(attrs.classes().collect::<Vec<_>>(), &["b"]);
+    /// ```
+    pub fn add_class<C: Into<CowStr<'s>>>(&mut self, class: C) {
+        let class = class.into();
+        if !self.has_class(&class) {
+            self.insert_pos("class".into(), class.into());
+        }
+    }
+
+    /// Remove all occurrences of a class. Returns true if the class existed.
+    ///
+    /// Class attributes that become empty are removed.
+    pub fn remove_class(&mut self, class: &str) -> bool {
+        if !self.has_class(class) {
+            return false;
+        }
+        if let Some(attrs) = &mut self.0 {
+            for (_, val) in attrs.iter_mut().filter(|(k, _)| *k == "class") {
+                let classes = val
+                    .raw
+                    .split_ascii_whitespace()
+                    .filter(|c| *c != class)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                val.raw = classes.into();
+            }
+            attrs.retain(|(k, v)| *k != "class" || !v.raw.is_empty());
+            if attrs.is_empty() {
+                self.0 = None;
+            }
+        }
+        true
+    }
+
     /// Returns an iterator over the attributes in undefined order.
-    pub fn iter(&self) -> impl Iterator<Item = (&'s str, &AttributeValue<'s>)> + '_ {
-        self.0.iter().flat_map(|v| v.iter().map(|(a, b)| (*a, b)))
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AttributeValue<'s>)> + '_ {
+        self.0
+            .iter()
+            .flat_map(|v| v.iter().map(|(a, b)| (a.as_ref(), b)))
     }
 }
 
@@ -212,7 +515,7 @@ impl<'s> FromIterator<(&'s str, &'s str)> for Attributes<'s> {
     fn from_iter<I: IntoIterator<Item = (&'s str, &'s str)>>(iter: I) -> Self {
         let attrs = iter
             .into_iter()
-            .map(|(a, v)| (a, v.into()))
+            .map(|(a, v)| (a.into(), v.into()))
             .collect::<Vec<_>>();
         if attrs.is_empty() {
             Attributes::new()
@@ -237,6 +540,27 @@ impl<'s> std::fmt::Debug for Attributes<'s> {
     }
 }
 
+/// Attributes are serialized as a sequence of key-value pairs, in order to preserve the order
+/// and any repeated keys.
+#[cfg(feature = "serde")]
+impl<'s> serde::Serialize for Attributes<'s> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 's> serde::Deserialize<'de> for Attributes<'s> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let attrs = Vec::<(CowStr<'s>, AttributeValue<'s>)>::deserialize(deserializer)?;
+        Ok(if attrs.is_empty() {
+            Self::new()
+        } else {
+            Self(Some(attrs.into()))
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct Validator {
     state: State,
@@ -308,7 +632,7 @@ impl<'s> Parser<'s> {
                 match st {
                     Class => self.attrs.insert("class", content.into()),
                     Identifier => self.attrs.insert("id", content.into()),
-                    Key => self.i_prev = self.attrs.insert_pos(content, "".into()),
+                    Key => self.i_prev = self.attrs.insert_pos(content.into(), "".into()),
                     Value | ValueQuoted | ValueContinued => {
                         self.attrs.0.as_mut().unwrap()[self.i_prev]
                             .1
@@ -553,4 +877,144 @@ mod test {
         assert_eq!(super::valid("abc"), 0);
         assert_eq!(super::valid("{.abc.}"), 0);
     }
+
+    #[test]
+    fn from_str_with_comments() {
+        use super::AttributeComment;
+        use super::AttributeError;
+        use super::AttributeErrorKind::*;
+        use super::Attributes;
+
+        let src = "{.a %x% #b}{%%key=\"v%\"}";
+        let (attrs, comments) = Attributes::from_str_with_comments(src).unwrap();
+        assert_eq!(attrs, super::parse(src));
+        assert_eq!(
+            comments,
+            &[
+                AttributeComment {
+                    text: "x",
+                    span: 4..7,
+                },
+                AttributeComment {
+                    text: "",
+                    span: 12..14,
+                },
+            ]
+        );
+
+        let err = |src, offset, kind| {
+            assert_eq!(
+                Attributes::from_str_with_comments(src),
+                Err(AttributeError { offset, kind }),
+                "{:?}",
+                src,
+            );
+        };
+        err("", 0, UnexpectedEnd);
+        err("{.a", 3, UnexpectedEnd);
+        err("{%a}", 4, UnexpectedEnd);
+        err(" {.a}", 0, UnexpectedCharacter);
+        err("{.a} ", 4, UnexpectedCharacter);
+        err("{.a}x", 4, UnexpectedCharacter);
+        err("{#}", 2, UnexpectedCharacter);
+        err("{a=\"b\"c}", 7, UnexpectedCharacter);
+    }
+
+    #[test]
+    fn remove() {
+        let mut attrs = super::Attributes::new();
+        attrs.parse("{#a .b key=val}");
+        let mut other = super::Attributes::new();
+        other.parse("{.c}");
+        attrs.union(other);
+        assert_eq!(attrs.remove("class").unwrap().to_string(), "b c");
+        assert_eq!(attrs.remove("class"), None);
+        assert_eq!(attrs.remove("id").unwrap().to_string(), "a");
+        assert_eq!(attrs.remove("key").unwrap().to_string(), "val");
+        assert!(attrs.is_empty());
+        assert_eq!(attrs, super::Attributes::new());
+    }
+
+    #[test]
+    fn set() {
+        let mut attrs = super::Attributes::new();
+        attrs.parse("{.a #b}");
+        let mut other = super::Attributes::new();
+        other.parse("{.c}");
+        attrs.union(other);
+        attrs.set("class", "d".into());
+        attrs.set("key", "val".into());
+        assert_eq!(
+            attrs
+                .iter()
+                .map(|(k, v)| (k, v.to_string()))
+                .collect::<Vec<_>>(),
+            &[
+                ("class", "d".to_string()),
+                ("id", "b".to_string()),
+                ("key", "val".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn classes() {
+        let mut attrs = super::Attributes::new();
+        attrs.parse("{.a #b .c}");
+        let mut other = super::Attributes::new();
+        other.parse("{.d .a}");
+        attrs.union(other);
+        assert_eq!(attrs.classes().collect::<Vec<_>>(), &["a", "c", "d", "a"]);
+        assert!(attrs.has_class("d"));
+        assert!(!attrs.has_class("b"));
+        assert_eq!(attrs.id().map(ToString::to_string).as_deref(), Some("b"));
+
+        attrs.add_class("d");
+        attrs.add_class("e");
+        assert_eq!(
+            attrs.classes().collect::<Vec<_>>(),
+            &["a", "c", "e", "d", "a"]
+        );
+
+        assert!(attrs.remove_class("a"));
+        assert!(!attrs.remove_class("a"));
+        assert_eq!(attrs.classes().collect::<Vec<_>>(), &["c", "e", "d"]);
+        assert!(attrs.remove_class("d"));
+        assert_eq!(attrs.iter().filter(|(k, _)| *k == "class").count(), 1);
+        assert!(attrs.remove_class("c"));
+        assert!(attrs.remove_class("e"));
+        assert_eq!(attrs.get("class"), None);
+        assert_eq!(attrs.iter().count(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde_test::Token;
+        let mut attr = super::Attributes::new();
+        attr.parse(r#"{#a .b .c key="v\"al"}"#);
+        serde_test::assert_tokens(
+            &attr,
+            &[
+                Token::Seq { len: None },
+                Token::Tuple { len: 2 },
+                Token::BorrowedStr("id"),
+                Token::BorrowedStr("a"),
+                Token::TupleEnd,
+                Token::Tuple { len: 2 },
+                Token::BorrowedStr("class"),
+                Token::String("b c"),
+                Token::TupleEnd,
+                Token::Tuple { len: 2 },
+                Token::BorrowedStr("key"),
+                Token::BorrowedStr(r#"v\"al"#),
+                Token::TupleEnd,
+                Token::SeqEnd,
+            ],
+        );
+        serde_test::assert_tokens(
+            &super::Attributes::new(),
+            &[Token::Seq { len: Some(0) }, Token::SeqEnd],
+        );
+    }
 }
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::Container;
+use crate::Event;
+use crate::LinkType;
+use crate::Map;
+use crate::Parser;
+use crate::Resolution;
+use crate::Set;
+use crate::SpanLinkType;
+
+/// A potential problem within a document, found by [`Parser::diagnostics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// Location of the problem within the input.
+    pub span: Range<usize>,
+    /// The kind of problem.
+    pub kind: DiagnosticKind,
+}
+
+/// Severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The document is likely to not be rendered as intended.
+    Warning,
+    /// The document is valid, but may contain a mistake.
+    Information,
+}
+
+/// The kind of problem reported by a [`Diagnostic`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// A reference link or image whose tag matches neither a link definition nor a heading.
+    UnresolvedReference { tag: String },
+    /// A footnote reference without a footnote definition.
+    UndefinedFootnote { label: String },
+    /// An explicit id that has already been used by a previous element.
+    DuplicateId { id: String },
+    /// A link definition that is not used by any reference.
+    UnusedLinkDefinition { label: String },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            DiagnosticKind::UnresolvedReference { tag } => {
+                write!(f, "unresolved reference '{}'", tag)
+            }
+            DiagnosticKind::UndefinedFootnote { label } => {
+                write!(f, "undefined footnote '{}'", label)
+            }
+            DiagnosticKind::DuplicateId { id } => write!(f, "duplicate id '{}'", id),
+            DiagnosticKind::UnusedLinkDefinition { label } => {
+                write!(f, "unused link definition '{}'", label)
+            }
+        }
+    }
+}
+
+/// Parse the remaining input of the parser and collect all diagnostics, sorted by location.
+pub(crate) fn collect(mut parser: Parser) -> Vec<Diagnostic> {
+    parser.references = Some(Vec::new());
+
+    let mut diagnostics = Vec::new();
+    let mut footnotes = Set::new();
+    let mut footnote_references = Vec::new();
+    let mut link_definitions = Vec::new();
+    let mut ids = Set::new();
+    // start offset of open unresolved references
+    let mut references = Vec::new();
+
+    let mut events = parser.into_offset_iter();
+    for (e, span) in &mut events {
+        let attrs = match &e {
+            Event::Start(Container::LinkDefinition { label }, _) => {
+                link_definitions.push((label.to_string(), span.clone()));
+                None
+            }
+            Event::Start(c, attrs) => {
+                if let Container::Footnote { label } = c {
+                    footnotes.insert(label.to_string());
+                }
+                if is_unresolved(c) {
+                    references.push(span.start);
+                }
+                Some(attrs)
+            }
+            Event::End(c) if is_unresolved(c) => {
+                let tag = match c {
+                    Container::Link(tag, _) | Container::Image(tag, _) => tag.to_string(),
+                    _ => unreachable!(),
+                };
+                let start = references.pop().unwrap_or(span.start);
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    span: start..span.end,
+                    kind: DiagnosticKind::UnresolvedReference { tag },
+                });
+                None
+            }
+            Event::FootnoteReference(label) => {
+                footnote_references.push((label.to_string(), span.clone()));
+                None
+            }
+            Event::ThematicBreak(attrs) => Some(attrs),
+            _ => None,
+        };
+        if let Some(id) = attrs.and_then(|attrs| attrs.get("id")) {
+            let id = id.to_string();
+            if !ids.insert(id.clone()) {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    span,
+                    kind: DiagnosticKind::DuplicateId { id },
+                });
+            }
+        }
+    }
+
+    diagnostics.extend(
+        footnote_references
+            .into_iter()
+            .filter(|(label, _)| !footnotes.contains(label))
+            .map(|(label, span)| Diagnostic {
+                severity: Severity::Warning,
+                span,
+                kind: DiagnosticKind::UndefinedFootnote { label },
+            }),
+    );
+
+    let used = events
+        .parser
+        .references
+        .take()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|r| r.resolution == Resolution::Definition)
+        .map(|r| r.tag)
+        .collect::<Set<_>>();
+    let mut defined = Map::new();
+    for (label, span) in link_definitions {
+        // only the last definition of a label is used
+        defined.insert(label, span);
+    }
+    diagnostics.extend(
+        defined
+            .into_iter()
+            .filter(|(label, _)| !used.contains::<str>(label))
+            .map(|(label, span)| Diagnostic {
+                severity: Severity::Information,
+                span,
+                kind: DiagnosticKind::UnusedLinkDefinition { label },
+            }),
+    );
+
+    diagnostics.sort_by_key(|d| (d.span.start, d.span.end));
+    diagnostics
+}
+
+fn is_unresolved(c: &Container) -> bool {
+    matches!(
+        c,
+        Container::Link(_, LinkType::Span(SpanLinkType::Unresolved))
+            | Container::Image(_, SpanLinkType::Unresolved)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::Diagnostic;
+    use super::DiagnosticKind::*;
+    use super::Severity::*;
+    use crate::Parser;
+
+    #[test]
+    fn unresolved_reference() {
+        assert_eq!(
+            Parser::new("[a][b] ![c][]\n\n# d\n\n[d][]").diagnostics(),
+            &[
+                Diagnostic {
+                    severity: Warning,
+                    span: 0..6,
+                    kind: UnresolvedReference { tag: "b".into() },
+                },
+                Diagnostic {
+                    severity: Warning,
+                    span: 7..13,
+                    kind: UnresolvedReference { tag: "c".into() },
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn undefined_footnote() {
+        assert_eq!(
+            Parser::new("a[^b] c[^d]\n\n[^d]: e").diagnostics(),
+            &[Diagnostic {
+                severity: Warning,
+                span: 1..5,
+                kind: UndefinedFootnote { label: "b".into() },
+            }],
+        );
+    }
+
+    #[test]
+    fn duplicate_id() {
+        let src = "{#a}\n# b\n\n[c]{#a} [d]{#b}\n\n{#a}\n---\n";
+        assert_eq!(
+            Parser::new(src).diagnostics(),
+            &[
+                Diagnostic {
+                    severity: Warning,
+                    span: 10..11,
+                    kind: DuplicateId { id: "a".into() },
+                },
+                Diagnostic {
+                    severity: Warning,
+                    span: 27..35,
+                    kind: DuplicateId { id: "a".into() },
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn unused_link_definition() {
+        let src = "[a][b]\n\n[b]: /b\n\n[c]: /c\n\n{#i}\n[d]: /d\n\n[e][d]";
+        assert_eq!(
+            Parser::new(src).diagnostics(),
+            &[Diagnostic {
+                severity: Information,
+                span: 17..21,
+                kind: UnusedLinkDefinition { label: "c".into() },
+            }],
+        );
+        assert_eq!(
+            Parser::new("[a]: /a").diagnostics()[0].to_string(),
+            "unused link definition 'a'",
+        );
+    }
+}
@@ -0,0 +1,1492 @@
+//! A Djot renderer that takes an iterator of [`Event`]s and emits Djot source.
+//!
+//! The output is designed such that parsing it again yields an equivalent sequence of events,
+//! i.e. the same events apart from [`Event::Blankline`] and [`Event::Escape`] events, and
+//! consecutive [`Event::Str`] events may be split or merged differently. This makes it possible
+//! to write tools that e.g. rewrite links or renumber headings of a document.
+//!
+//! Some event sequences can not be represented in Djot and will not survive a roundtrip, e.g.
+//! inline link destinations containing a closing parenthesis, or body rows of a table with a
+//! different alignment than the previous rows. Sequences emitted by the [`Parser`](crate::Parser)
+//! generally do.
+//!
+//! # Examples
+//!
+//! ```
+//! use jotdown::Render;
+//! let djot = "a _link_ to [somewhere](https://example.com)\n";
+//! let events = jotdown::Parser::new(djot);
+//! let mut out = String::new();
+//! jotdown::djot::Renderer::default().push(events, &mut out);
+//! assert_eq!(out, djot);
+//! ```
+
+use crate::Alignment;
+use crate::Attributes;
+use crate::Container;
+use crate::Event;
+use crate::LinkType;
+use crate::ListKind;
+use crate::OrderedListNumbering::*;
+use crate::OrderedListStyle::*;
+use crate::Parser;
+use crate::Render;
+use crate::SpanLinkType;
+
+/// [`Render`] implementor that writes Djot output.
+///
+/// All events are buffered before any output is written, as e.g. the fences of divs and labels of
+/// reference links depend on later events.
+#[derive(Default)]
+pub struct Renderer {}
+
+impl Render for Renderer {
+    fn push<'s, I, W>(&self, events: I, out: W) -> std::fmt::Result
+    where
+        I: Iterator<Item = Event<'s>>,
+        W: std::fmt::Write,
+    {
+        let events = events.collect::<Vec<_>>();
+        Writer::new(&events).write(out)
+    }
+
+    fn push_borrowed<'s, E, I, W>(&self, events: I, out: W) -> std::fmt::Result
+    where
+        E: AsRef<Event<'s>>,
+        I: Iterator<Item = E>,
+        W: std::fmt::Write,
+    {
+        let events = events.map(|e| e.as_ref().clone()).collect::<Vec<_>>();
+        Writer::new(&events).write(out)
+    }
+}
+
+/// A block that affects the prefix of each line, and the separation of its children.
+struct Block {
+    /// Prefix of the first line within the block, e.g. a list marker.
+    first: Option<String>,
+    /// Prefix of all other lines within the block.
+    rest: String,
+    /// At least one child block has been written.
+    has_child: bool,
+    /// Items of a tight list, or children of its items, are not separated by blank lines,
+    /// except before nested lists.
+    tight: bool,
+    /// The block is a list, along with its bullet or next number.
+    list: Option<(ListKind, char, u64)>,
+    /// Bullet of the previous child, if it was a bullet or task list.
+    bullet_prev: Option<char>,
+    /// The previous child was a paragraph.
+    paragraph_prev: bool,
+    /// Line written at the end of the block, e.g. a closing fence.
+    closer: Option<String>,
+}
+
+impl Block {
+    fn new(first: Option<String>, rest: String) -> Self {
+        Self {
+            first,
+            rest,
+            has_child: false,
+            tight: false,
+            list: None,
+            bullet_prev: None,
+            paragraph_prev: false,
+            closer: None,
+        }
+    }
+}
+
+/// An inline container to be closed.
+struct Inline<'a, 's> {
+    /// Closing delimiter, written at the end event.
+    closer: String,
+    /// Attributes, written after the closing delimiter.
+    attributes: Attributes<'s>,
+    /// The container, used to avoid ambiguous nesting.
+    container: &'a Container<'s>,
+}
+
+struct Writer<'a, 's> {
+    events: &'a [Event<'s>],
+    /// Index of the matching end event, for each start event.
+    ends: Vec<usize>,
+    /// Length of the fence of each div, by index of the start event.
+    div_fences: Vec<(usize, usize)>,
+    /// Link definitions with their label, destination and attributes.
+    link_definitions: Vec<(&'a str, String, &'a Attributes<'s>)>,
+    /// Heading ids and their text, for implicit heading references.
+    headings: Vec<(&'a str, String)>,
+
+    blocks: Vec<Block>,
+    inlines: Vec<Inline<'a, 's>>,
+    /// Output buffer, may be temporarily replaced when writing a table caption.
+    buf: String,
+    /// Main output buffer and line state, saved while writing a table caption.
+    caption_outer: Option<(String, bool)>,
+    /// Finished caption of the current table.
+    caption: Option<String>,
+    /// Alignment of the current table columns.
+    alignments: Vec<Alignment>,
+    /// Within a table, no rows written yet.
+    table_start: bool,
+    /// Nothing has been written on the current line, not even the prefix.
+    line_start: bool,
+    /// Only the prefix has been written on the current line of a leaf block.
+    inline_start: bool,
+    /// Within a container whose text is written verbatim.
+    verbatim: bool,
+    /// Last character written.
+    prev: Option<char>,
+    /// Position within the buffer of the current line of a paragraph, heading or term, after its
+    /// prefix. The line may still need to be guarded, as it could be parsed as another block.
+    line_begin: Option<usize>,
+    /// A line of a paragraph, heading or term has started, but nothing has been written on it yet.
+    line_pending: bool,
+    /// A verbatim block has been left unclosed, as its content does not end with a newline.
+    unclosed: bool,
+}
+
+impl<'a, 's> Writer<'a, 's> {
+    fn new(events: &'a [Event<'s>]) -> Self {
+        let mut ends = vec![0; events.len()];
+        let mut div_fences = Vec::new();
+        let mut link_definitions = Vec::new();
+        let mut headings = Vec::new();
+
+        let mut open: Vec<(usize, usize)> = Vec::new();
+        let mut text: Option<String> = None;
+        for (i, e) in events.iter().enumerate() {
+            match e {
+                Event::Start(c, ..) => {
+                    if matches!(
+                        c,
+                        Container::Heading { .. } | Container::LinkDefinition { .. }
+                    ) {
+                        text = Some(String::new());
+                    }
+                    open.push((i, 0));
+                }
+                Event::End(c) => {
+                    if let Some((start, height)) = open.pop() {
+                        ends[start] = i;
+                        let mut height = height;
+                        if let Container::Div { .. } = c {
+                            height += 1;
+                            div_fences.push((start, height + 2));
+                        }
+                        if let Some((_, h)) = open.last_mut() {
+                            *h = (*h).max(height);
+                        }
+                        match (&events[start], text.take()) {
+                            (
+                                Event::Start(Container::LinkDefinition { label }, attrs),
+                                Some(url),
+                            ) => link_definitions.push((label.as_ref(), url, attrs)),
+                            (Event::Start(Container::Heading { id, .. }, ..), Some(text)) => {
+                                headings.push((id.as_ref(), text));
+                            }
+                            (_, t) => text = t,
+                        }
+                    }
+                }
+                Event::Str(s) => {
+                    if let Some(text) = &mut text {
+                        text.push_str(s);
+                    }
+                }
+                Event::Softbreak => {
+                    if let Some(text) = &mut text {
+                        text.push(' ');
+                    }
+                }
+                _ => {}
+            }
+        }
+        div_fences.sort_unstable();
+
+        Self {
+            events,
+            ends,
+            div_fences,
+            link_definitions,
+            headings,
+            blocks: vec![Block::new(None, String::new())],
+            inlines: Vec::new(),
+            buf: String::new(),
+            caption_outer: None,
+            caption: None,
+            alignments: Vec::new(),
+            table_start: false,
+            line_start: true,
+            inline_start: false,
+            verbatim: false,
+            prev: None,
+            line_begin: None,
+            line_pending: false,
+            unclosed: false,
+        }
+    }
+
+    fn write<W>(&mut self, mut out: W) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        for i in 0..self.events.len() {
+            self.render_event(i);
+            // the current line may still need to be guarded
+            if self.caption_outer.is_none() && self.line_begin.is_none() {
+                out.write_str(&self.buf)?;
+                self.buf.clear();
+            }
+        }
+        out.write_str(&self.buf)
+    }
+
+    fn render_event(&mut self, i: usize) {
+        let events = self.events;
+        match &events[i] {
+            Event::Start(c, attrs) if c.is_block() => self.render_block_start(i, c, attrs),
+            Event::End(c) if c.is_block() => self.render_block_end(i, c),
+            Event::Start(c, attrs) => self.render_inline_start(i, c, attrs),
+            Event::End(..) => {
+                self.verbatim = false;
+                if let Some(Inline {
+                    closer, attributes, ..
+                }) = self.inlines.pop()
+                {
+                    self.write_str(&closer);
+                    if !attributes.is_empty() {
+                        self.write_attributes(&attributes);
+                    }
+                }
+            }
+            Event::Str(s) => {
+                let prev = events[..i]
+                    .iter()
+                    .rev()
+                    .find(|e| !matches!(e, Event::Escape));
+                if self.verbatim {
+                    self.write_verbatim(s);
+                } else if !matches!(prev, Some(Event::Str(..))) {
+                    // escaping depends on surrounding characters, write all consecutive strings
+                    let n = events[i..]
+                        .iter()
+                        .take_while(|e| matches!(e, Event::Str(..) | Event::Escape))
+                        .count();
+                    let s = events[i..i + n]
+                        .iter()
+                        .filter_map(|e| match e {
+                            Event::Str(s) => Some(s.as_ref()),
+                            _ => None,
+                        })
+                        .collect::<String>();
+                    self.write_text(&s, i + n);
+                }
+            }
+            Event::FootnoteReference(label) => {
+                self.write_str("[^");
+                self.write_str(label);
+                self.write_str("]");
+            }
+            Event::Symbol(sym) => {
+                if self.inline_start {
+                    // empty attributes, to avoid a div fence, e.g. `::::`
+                    self.write_str("{}");
+                }
+                self.write_str(":");
+                self.write_str(sym);
+                self.write_str(":");
+            }
+            Event::LeftSingleQuote | Event::LeftDoubleQuote => {
+                let single = matches!(events[i], Event::LeftSingleQuote);
+                let explicit = self.explicit_quote(i);
+                self.write_str(match (single, explicit) {
+                    (true, true) => "{'",
+                    (true, false) => "'",
+                    (false, true) => "{\"",
+                    (false, false) => "\"",
+                });
+                if matches!(
+                    events.get(i + 1),
+                    Some(Event::RightSingleQuote | Event::RightDoubleQuote)
+                ) {
+                    // discarded attributes, an empty pair would otherwise not be matched
+                    self.write_str("{.c}");
+                }
+            }
+            Event::RightSingleQuote | Event::RightDoubleQuote => {
+                let single = matches!(events[i], Event::RightSingleQuote);
+                let explicit = self.explicit_quote(i);
+                let after_space = !matches!(self.prev, Some(c) if !c.is_whitespace());
+                let after_word = |j: usize| {
+                    matches!(
+                        events[..j].iter().rev().find(|e| !matches!(e, Event::Escape)),
+                        Some(Event::Str(s)) if !s.ends_with(char::is_whitespace)
+                    )
+                };
+                if explicit
+                    && after_space
+                    && !matches!(self.opening_quote(i), Some(j) if after_word(j))
+                {
+                    // empty attributes, the quotes would otherwise not be matched
+                    self.write_str("{}");
+                }
+                self.write_str(match (single, explicit) {
+                    (true, true) => "'}",
+                    (true, false) => "'",
+                    (false, true) => "\"}",
+                    (false, false) => "\"",
+                });
+            }
+            Event::Ellipsis => self.write_str("..."),
+            Event::EnDash | Event::EmDash => {
+                if self.inline_start || self.prev == Some('-') {
+                    // empty attributes, to avoid a thematic break, e.g. `- --`, or a different
+                    // split of a run of hyphens
+                    self.write_str("{}");
+                }
+                self.write_str(if matches!(events[i], Event::EnDash) {
+                    "--"
+                } else {
+                    "---"
+                });
+            }
+            Event::NonBreakingSpace => self.write_str("\\ "),
+            Event::Softbreak => {
+                if self.inline_start && !matches!(events[i - 1], Event::Start(..)) {
+                    // empty attributes, to avoid a blank line
+                    self.write_str("{}");
+                    self.line_begin = None;
+                } else if self.inline_start || matches!(events[i - 1], Event::NonBreakingSpace) {
+                    // empty attributes, to avoid a hard break or a blank first line, which is
+                    // guarded further when the line ends
+                    self.write_str("{}");
+                }
+                self.newline();
+                self.inline_start = true;
+                self.line_pending = self.caption_outer.is_none();
+            }
+            Event::Hardbreak => {
+                self.write_str("\\");
+                self.newline();
+                self.inline_start = true;
+                self.line_pending = self.caption_outer.is_none();
+            }
+            Event::Escape | Event::Blankline => {}
+            Event::ThematicBreak(attrs) => {
+                self.start_block(None, attrs);
+                self.write_str("* * *");
+                self.newline();
+            }
+        }
+    }
+
+    fn render_block_start(&mut self, i: usize, c: &'a Container<'s>, attrs: &Attributes<'s>) {
+        match c {
+            Container::TableRow { head } => {
+                if !self.line_start {
+                    self.newline();
+                }
+                let alignments = self.events[i..self.ends[i]]
+                    .iter()
+                    .filter_map(|e| match e {
+                        Event::Start(Container::TableCell { alignment, .. }, ..) => {
+                            Some(*alignment)
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                if !head
+                    && self.table_start
+                    && alignments.iter().any(|a| *a != Alignment::Unspecified)
+                {
+                    self.write_separator(&alignments);
+                }
+                self.alignments = alignments;
+                self.table_start = false;
+                self.write_str("|");
+                return;
+            }
+            Container::TableCell { .. } => {
+                self.write_str(" ");
+                return;
+            }
+            Container::Caption => {
+                let outer = std::mem::take(&mut self.buf);
+                self.caption_outer = Some((outer, self.line_start));
+                self.line_start = false;
+                self.inline_start = true;
+                return;
+            }
+            _ => {}
+        }
+
+        let bullet_prev = self.parent().bullet_prev;
+        self.start_block(Some(c), attrs);
+
+        let block = match c {
+            Container::Blockquote => Block::new(Some("> ".to_string()), "> ".to_string()),
+            Container::List { kind, tight } => {
+                // adjacent lists with the same bullet would be merged
+                let bullet = match bullet_prev {
+                    Some('-') => '*',
+                    Some('*') => '+',
+                    _ => '-',
+                };
+                let start = if let ListKind::Ordered { start, .. } = kind {
+                    *start
+                } else {
+                    1
+                };
+                Block {
+                    tight: *tight,
+                    list: Some((*kind, bullet, start)),
+                    ..Block::new(None, String::new())
+                }
+            }
+            Container::ListItem | Container::TaskListItem { .. } => {
+                let list = self.blocks.last_mut().and_then(|b| b.list.as_mut());
+                let (kind, bullet, number) = list.map_or((ListKind::Unordered, '-', 1), |l| {
+                    let n = l.2;
+                    if let ListKind::Ordered {
+                        numbering: Decimal, ..
+                    } = l.0
+                    {
+                        l.2 += 1;
+                    }
+                    (l.0, l.1, n)
+                });
+                let mut marker = match kind {
+                    ListKind::Unordered | ListKind::Task => format!("{} ", bullet),
+                    ListKind::Ordered {
+                        numbering, style, ..
+                    } => {
+                        let n = match numbering {
+                            Decimal => number.to_string(),
+                            AlphaLower | AlphaUpper if (1..=26).contains(&number) => {
+                                let a = if matches!(numbering, AlphaLower) {
+                                    b'a'
+                                } else {
+                                    b'A'
+                                };
+                                char::from(a + (number - 1) as u8).to_string()
+                            }
+                            AlphaLower | AlphaUpper => number.to_string(),
+                            RomanLower => roman(number),
+                            RomanUpper => roman(number).to_ascii_uppercase(),
+                        };
+                        match style {
+                            Period => format!("{}. ", n),
+                            Paren => format!("{}) ", n),
+                            ParenParen => format!("({}) ", n),
+                        }
+                    }
+                };
+                let indent = " ".repeat(marker.len());
+                if let Container::TaskListItem { checked } = c {
+                    marker.push_str(if *checked { "[x] " } else { "[ ] " });
+                }
+                Block {
+                    tight: self.parent().tight,
+                    ..Block::new(Some(marker), indent)
+                }
+            }
+            Container::DescriptionList | Container::Section { .. } => {
+                Block::new(None, String::new())
+            }
+            Container::DescriptionDetails => Block::new(None, "  ".to_string()),
+            Container::Footnote { label } => {
+                Block::new(Some(format!("[^{}]: ", label)), "  ".to_string())
+            }
+            Container::Div { class } => {
+                let n = self
+                    .div_fences
+                    .binary_search_by_key(&i, |(j, _)| *j)
+                    .map_or(3, |k| self.div_fences[k].1);
+                let fence = ":".repeat(n);
+                self.write_str(&fence);
+                if !class.is_empty() {
+                    self.write_str(" ");
+                    self.write_str(class);
+                }
+                self.newline();
+                Block {
+                    closer: Some(fence),
+                    ..Block::new(None, String::new())
+                }
+            }
+            Container::Table => {
+                self.table_start = true;
+                self.alignments.clear();
+                Block::new(None, String::new())
+            }
+            Container::Paragraph | Container::DescriptionTerm | Container::Heading { .. } => {
+                match c {
+                    Container::Heading { level, .. } => {
+                        self.write_str(&"#".repeat(usize::from(*level)));
+                        self.write_str(" ");
+                    }
+                    Container::DescriptionTerm => {
+                        self.write_str(": ");
+                        self.line_begin = Some(self.buf.len());
+                    }
+                    _ => self.line_pending = true,
+                }
+                self.inline_start = true;
+                return;
+            }
+            Container::CodeBlock { .. } | Container::RawBlock { .. } => {
+                let content = self.events[i..self.ends[i]].iter().filter_map(|e| {
+                    if let Event::Str(s) = e {
+                        Some(s.as_ref())
+                    } else {
+                        None
+                    }
+                });
+                let fence = "`".repeat(content.map(backtick_run).max().unwrap_or(0).max(2) + 1);
+                self.write_str(&fence);
+                match c {
+                    Container::CodeBlock { language } => self.write_str(language),
+                    Container::RawBlock { format } => {
+                        self.write_str("=");
+                        self.write_str(format);
+                    }
+                    _ => {}
+                }
+                self.newline();
+                self.verbatim = true;
+                self.inlines.push(Inline {
+                    closer: fence,
+                    attributes: Attributes::new(),
+                    container: c,
+                });
+                return;
+            }
+            Container::LinkDefinition { label } => {
+                self.write_str("[");
+                self.write_str(label);
+                self.write_str("]: ");
+                self.verbatim = true;
+                self.inlines.push(Inline {
+                    closer: String::new(),
+                    attributes: Attributes::new(),
+                    container: c,
+                });
+                return;
+            }
+            _ => unreachable!(),
+        };
+        self.blocks.push(block);
+    }
+
+    fn render_block_end(&mut self, i: usize, c: &Container<'s>) {
+        match c {
+            Container::TableRow { head } => {
+                if matches!(self.events[i - 1], Event::Start(..)) {
+                    // a cell with an unclosed verbatim is discarded, leaving the row empty
+                    self.write_str("` |");
+                }
+                self.newline();
+                if *head {
+                    let alignments = std::mem::take(&mut self.alignments);
+                    self.write_separator(&alignments);
+                    self.alignments = alignments;
+                }
+            }
+            Container::TableCell { .. } => {
+                if matches!(&self.events[i - 1], Event::Str(s) if s.ends_with(char::is_whitespace))
+                {
+                    // empty attributes, to keep trailing whitespace
+                    self.write_str("{}");
+                }
+                self.write_str(" |");
+            }
+            Container::Caption => {
+                if let Some((outer, line_start)) = self.caption_outer.take() {
+                    self.caption = Some(std::mem::replace(&mut self.buf, outer));
+                    self.line_start = line_start;
+                }
+            }
+            Container::Paragraph | Container::DescriptionTerm | Container::Heading { .. } => {
+                match &self.events[i - 1] {
+                    Event::Softbreak => {
+                        // the marker or attributes need no guard
+                        self.line_pending = false;
+                        if let Container::Heading { level, .. } = c {
+                            // empty attributes do not continue a heading
+                            self.write_str(&"#".repeat(usize::from(*level)));
+                        } else {
+                            // empty attributes, to keep the soft break
+                            self.write_str("{}");
+                        }
+                    }
+                    Event::Str(s) if s.ends_with(char::is_whitespace) => {
+                        // empty attributes, to keep trailing whitespace
+                        self.write_str("{}");
+                    }
+                    Event::Start(..) | Event::Hardbreak | Event::NonBreakingSpace => {
+                        // discarded attributes, as empty attributes are not treated as content
+                        self.write_str("{.c}");
+                    }
+                    _ => {}
+                }
+                self.inline_start = false;
+                self.newline();
+            }
+            Container::CodeBlock { .. } | Container::RawBlock { .. } => {
+                self.verbatim = false;
+                if let Some(Inline { closer, .. }) = self.inlines.pop() {
+                    let raw = matches!(c, Container::RawBlock { .. });
+                    if raw && matches!(self.events[i - 1], Event::Str(..)) {
+                        // the last newline is not part of the content of a raw block
+                        self.newline();
+                    }
+                    if self.line_start {
+                        self.write_str(&closer);
+                        self.newline();
+                    } else {
+                        // content without a trailing newline is only parsed from an unclosed block
+                        self.unclosed = true;
+                    }
+                }
+            }
+            Container::LinkDefinition { .. } => {
+                self.verbatim = false;
+                self.inlines.pop();
+                self.newline();
+            }
+            _ => {
+                if self.parent().first.is_some() {
+                    // empty list item, footnote or blockquote, write marker only
+                    if matches!(c, Container::ListItem | Container::TaskListItem { .. }) {
+                        // discarded attributes, as a blank item would make the list loose
+                        self.write_str("{.c}");
+                    } else {
+                        self.write_str("");
+                    }
+                    self.newline();
+                }
+                let block = self.blocks.pop().unwrap();
+                if let Some(closer) = block.closer.as_ref().filter(|_| !self.unclosed) {
+                    if !self.line_start {
+                        self.newline();
+                    }
+                    self.write_str(closer);
+                    self.newline();
+                }
+                match c {
+                    Container::Table => {
+                        if self.table_start {
+                            self.write_separator(&[Alignment::Unspecified]);
+                        }
+                        if let Some(caption) = self.caption.take() {
+                            if !self.line_start {
+                                self.newline();
+                            }
+                            self.write_str("^ ");
+                            self.buf.push_str(&caption);
+                            self.newline();
+                        }
+                    }
+                    Container::List { kind, .. } => {
+                        if let Some((_, bullet, _)) = block.list {
+                            if matches!(kind, ListKind::Unordered | ListKind::Task) {
+                                self.parent().bullet_prev = Some(bullet);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn render_inline_start(&mut self, i: usize, c: &'a Container<'s>, attrs: &Attributes<'s>) {
+        let mut attributes = attrs.clone();
+        let (opener, closer): (String, String) = match c {
+            Container::Span if attrs.is_empty() => (String::new(), String::new()),
+            Container::Span => ("[".to_string(), "]".to_string()),
+            Container::Link(_, LinkType::AutoLink | LinkType::Email) => {
+                self.verbatim = true;
+                ("<".to_string(), ">".to_string())
+            }
+            Container::Link(dst, LinkType::Span(ty)) | Container::Image(dst, ty) => {
+                let opener = if matches!(c, Container::Image(..)) {
+                    "!["
+                } else {
+                    "["
+                };
+                let closer = match ty {
+                    SpanLinkType::Inline => format!("]({})", dst),
+                    SpanLinkType::Unresolved => format!("][{}]", dst),
+                    SpanLinkType::Reference => {
+                        if let Some((label, attrs_own)) = self.reference(dst, attrs) {
+                            attributes = attrs_own;
+                            let collapsed = self.ends[i] == i + 2
+                                && matches!(&self.events[i + 1], Event::Str(s) if *s == label);
+                            if collapsed {
+                                "][]".to_string()
+                            } else {
+                                format!("][{}]", label)
+                            }
+                        } else {
+                            format!("]({})", dst)
+                        }
+                    }
+                };
+                (opener.to_string(), closer)
+            }
+            Container::Verbatim | Container::Math { .. } | Container::RawInline { .. } => {
+                let content = self.events[i..self.ends[i]]
+                    .iter()
+                    .filter_map(|e| {
+                        if let Event::Str(s) = e {
+                            Some(s.as_ref())
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<String>();
+                let fence = "`".repeat(backtick_run(&content) + 1);
+                let mut opener = match c {
+                    Container::Math { display: false } => "$".to_string(),
+                    Container::Math { display: true } => "$$".to_string(),
+                    _ => String::new(),
+                };
+                opener.push_str(&fence);
+                let mut closer = String::new();
+                if content.starts_with('`') {
+                    opener.push(' ');
+                }
+                // a backtick after an odd number of backslashes is escaped, and a trailing space
+                // would then be kept in the content
+                let t = content.trim_end_matches('`');
+                let escaped = (t.len() - t.trim_end_matches('\\').len()) % 2 == 1;
+                let backticks = content.len() - t.len();
+                if backticks > usize::from(escaped) {
+                    closer.push(' ');
+                }
+                if !escaped || backticks > 0 {
+                    closer.push_str(&fence);
+                }
+                if let Container::RawInline { format } = c {
+                    closer.push_str("{=");
+                    closer.push_str(format);
+                    closer.push('}');
+                }
+                self.verbatim = true;
+                (opener, closer)
+            }
+            Container::Subscript
+            | Container::Superscript
+            | Container::Insert
+            | Container::Delete
+            | Container::Strong
+            | Container::Emphasis
+            | Container::Mark => {
+                let d = match c {
+                    Container::Subscript => '~',
+                    Container::Superscript => '^',
+                    Container::Insert => '+',
+                    Container::Delete => '-',
+                    Container::Strong => '*',
+                    Container::Emphasis => '_',
+                    _ => '=',
+                };
+                let braces = matches!(c, Container::Insert | Container::Delete | Container::Mark)
+                    || self.inlines.iter().any(|o| o.container == c)
+                    || !self.plain_delimiters(i)
+                    // avoid footnote references, e.g. `[^a^]{.b}`
+                    || (d == '^' && self.prev == Some('['));
+                if self.ends[i] == i + 1 {
+                    // only parsed as a container if not empty, the attributes are discarded
+                    (format!("{{{}{{.c}}", d), format!("{}}}", d))
+                } else if braces {
+                    (format!("{{{}", d), format!("{}}}", d))
+                } else {
+                    (d.to_string(), d.to_string())
+                }
+            }
+            _ => unreachable!("block container"),
+        };
+        self.write_str(&opener);
+        self.inlines.push(Inline {
+            closer,
+            attributes,
+            container: c,
+        });
+    }
+
+    /// Determine whether the content of the container at `i` allows for unbraced delimiters.
+    fn plain_delimiters(&self, i: usize) -> bool {
+        let mut inner = self.events[i + 1..self.ends[i]]
+            .iter()
+            .filter(|e| !matches!(e, Event::Escape));
+        let first = inner.next();
+        let last = inner.next_back().or(first);
+        matches!((first, last), (Some(first), Some(last))
+            if !space(first, true) && !space(last, false))
+    }
+
+    /// Determine whether the quote at `i` must be written as `{'`, `'}`, `{"` or `"}`, as a plain
+    /// quote would be parsed with another direction or paired with another quote.
+    fn explicit_quote(&self, i: usize) -> bool {
+        let events = self.events;
+        match &events[i] {
+            Event::LeftSingleQuote | Event::LeftDoubleQuote => {
+                // a plain opening quote may not follow a string without whitespace in between
+                let plain = self.after_space(i)
+                    && matches!(self.closing_quote(i), Some(j) if j > i + 1
+                        && !space(&events[i + 1], true)
+                        && !space(&events[j - 1], false));
+                !plain
+            }
+            Event::RightSingleQuote | Event::RightDoubleQuote => {
+                if let Some(j) = self.opening_quote(i) {
+                    self.explicit_quote(j)
+                } else {
+                    // an unpaired plain quote directly after a word is always a right quote
+                    !matches!(&events[i - 1], Event::Str(s) if s.ends_with(char::is_alphanumeric))
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Determine whether the event at `i` follows whitespace or the start of a block.
+    fn after_space(&self, i: usize) -> bool {
+        match self.events[..i]
+            .iter()
+            .rev()
+            .find(|e| !matches!(e, Event::Escape))
+        {
+            None | Some(Event::Softbreak | Event::Hardbreak | Event::NonBreakingSpace) => true,
+            Some(Event::Start(c, ..)) => c.is_block(),
+            Some(Event::Str(s)) => s.ends_with(|c: char| c.is_whitespace()),
+            Some(_) => false,
+        }
+    }
+
+    /// Find the right quote that closes the left quote at `i`, within the same container.
+    fn closing_quote(&self, i: usize) -> Option<usize> {
+        let (left, right) = quote_pair(&self.events[i]);
+        let mut depth = 0usize;
+        let mut open = 0usize;
+        for (j, e) in self.events.iter().enumerate().skip(i + 1) {
+            match e {
+                Event::Start(..) => depth += 1,
+                Event::End(..) if depth == 0 => return None,
+                Event::End(..) => depth -= 1,
+                e if depth == 0 && *e == left => open += 1,
+                e if depth == 0 && *e == right => {
+                    if open == 0 {
+                        return Some(j);
+                    }
+                    open -= 1;
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Find the left quote that is closed by the right quote at `i`, within the same container.
+    fn opening_quote(&self, i: usize) -> Option<usize> {
+        let (left, right) = quote_pair(&self.events[i]);
+        let mut depth = 0usize;
+        let mut closed = 0usize;
+        for (j, e) in self.events[..i].iter().enumerate().rev() {
+            match e {
+                Event::End(..) => depth += 1,
+                Event::Start(..) if depth == 0 => return None,
+                Event::Start(..) => depth -= 1,
+                e if depth == 0 && *e == right => closed += 1,
+                e if depth == 0 && *e == left => {
+                    if closed == 0 {
+                        return Some(j);
+                    }
+                    closed -= 1;
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Find a label that resolves to the reference link destination and the attributes of the
+    /// link that do not originate from the link definition.
+    fn reference(&self, dst: &str, attrs: &Attributes<'s>) -> Option<(String, Attributes<'s>)> {
+        self.link_definitions
+            .iter()
+            .filter(|(_, url, _)| url == dst)
+            .find_map(|(label, _, attrs_def)| {
+                // attributes from the definition have been appended to the ones of the link
+                let attrs_own = attrs.difference(attrs_def)?;
+                Some((label.to_string(), attrs_own))
+            })
+            .or_else(|| {
+                let id = dst.strip_prefix('#')?;
+                self.headings
+                    .iter()
+                    .find(|(i, _)| *i == id)
+                    .map(|(_, text)| (text.clone(), attrs.clone()))
+            })
+    }
+
+    fn parent(&mut self) -> &mut Block {
+        self.blocks.last_mut().unwrap()
+    }
+
+    /// Separate the block from its previous sibling and write its attributes.
+    fn start_block(&mut self, c: Option<&Container>, attrs: &Attributes) {
+        if !self.line_start {
+            self.newline();
+        }
+        let parent = self.parent();
+        let separate = parent.has_child;
+        // within items, only blockquotes may interrupt a paragraph
+        let tight = parent.tight
+            && (parent.list.is_some()
+                || matches!(c, Some(Container::Blockquote))
+                || (!parent.paragraph_prev && !matches!(c, Some(Container::List { .. }))));
+        parent.has_child = true;
+        parent.bullet_prev = None;
+        parent.paragraph_prev = matches!(c, Some(Container::Paragraph));
+        if separate && !tight {
+            self.newline();
+        }
+        if !attrs.is_empty() {
+            self.write_attributes(attrs);
+            self.newline();
+        }
+    }
+
+    fn write_separator(&mut self, alignments: &[Alignment]) {
+        self.write_str("|");
+        for a in alignments {
+            self.write_str(match a {
+                Alignment::Unspecified => "---|",
+                Alignment::Left => ":--|",
+                Alignment::Center => ":-:|",
+                Alignment::Right => "--:|",
+            });
+        }
+        self.newline();
+    }
+
+    fn write_attributes(&mut self, attrs: &Attributes) {
+        let mut s = String::from("{");
+        for (k, v) in attrs.iter() {
+            if s.len() > 1 {
+                s.push(' ');
+            }
+            let v = v.to_string();
+            let is_name = |s: &str| !s.is_empty() && s.bytes().all(crate::attr::is_name);
+            if k == "id" && is_name(&v) {
+                s.push('#');
+                s.push_str(&v);
+            } else if k == "class"
+                && !v.trim().is_empty()
+                && v.split_ascii_whitespace().all(is_name)
+            {
+                let classes = v.split_ascii_whitespace().collect::<Vec<_>>();
+                s.push('.');
+                s.push_str(&classes.join(" ."));
+            } else {
+                s.push_str(k);
+                s.push_str("=\"");
+                for c in v.chars() {
+                    if matches!(c, '"' | '\\') {
+                        s.push('\\');
+                    }
+                    s.push(c);
+                }
+                s.push('"');
+            }
+        }
+        s.push('}');
+        self.write_str(&s);
+    }
+
+    /// Write text that is not interpreted, e.g. the content of a code block.
+    fn write_verbatim(&mut self, s: &str) {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            if !line.is_empty() {
+                self.write_str(line);
+            }
+        }
+    }
+
+    /// Write text, escaping any characters that would otherwise be interpreted as markup.
+    fn write_text(&mut self, s: &str, next: usize) {
+        let next_event = self.next_char(next);
+        let line_start = self.inline_start;
+        let mut out = String::with_capacity(s.len());
+        if line_start && s.starts_with(char::is_whitespace) {
+            // empty attributes, to avoid the whitespace being stripped
+            out.push_str("{}");
+        }
+        let mut prev = if line_start { None } else { self.prev };
+        let mut chars = s.chars().peekable();
+        let mut pos = 0;
+        // a list marker, e.g. `1.` or `a)`, at the start of a line
+        let marker_end = if line_start {
+            let n = s.bytes().take_while(|b| b.is_ascii_alphanumeric()).count();
+            if n > 0 && matches!(s.as_bytes().get(n), Some(b'.' | b')')) {
+                Some(n)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+        while let Some(c) = chars.next() {
+            let next = chars.peek().copied().or(next_event);
+            let escape = match c {
+                '\\' | '*' | '_' | '^' | '~' | '[' | ']' | '{' | '}' | '`' | '"' | '\'' | '|'
+                | '<' => true,
+                '$' => next == Some('`'),
+                '!' => next == Some('['),
+                ':' => {
+                    prev.is_none()
+                        || matches!(next, Some(n) if n.is_ascii_alphanumeric() || matches!(n, '-' | '+' | '_' | ':'))
+                }
+                '.' | '-' => prev == Some(c) || next == Some(c) || (c == '-' && prev.is_none()),
+                '+' | '#' | '>' | '(' => prev.is_none(),
+                _ => false,
+            } || marker_end == Some(pos);
+            if escape {
+                out.push('\\');
+            }
+            out.push(c);
+            prev = Some(c);
+            pos += c.len_utf8();
+        }
+        self.write_str(&out);
+    }
+
+    /// First character that will be written by the event at `i`, if it may affect escaping.
+    fn next_char(&self, i: usize) -> Option<char> {
+        match self.events.get(i)? {
+            Event::Str(s) => s.chars().next(),
+            Event::Start(c, ..) => match c {
+                Container::Verbatim | Container::RawInline { .. } => Some('`'),
+                Container::Math { .. } => Some('$'),
+                Container::Link(..) | Container::Span => Some('['),
+                Container::Image(..) => Some('!'),
+                Container::Strong => Some('*'),
+                Container::Emphasis => Some('_'),
+                Container::Subscript => Some('~'),
+                Container::Superscript => Some('^'),
+                _ => None,
+            },
+            Event::End(c) if !c.is_block() => {
+                self.inlines.last().and_then(|o| o.closer.chars().next())
+            }
+            Event::FootnoteReference(..) => Some('['),
+            Event::Symbol(..) => Some(':'),
+            Event::Ellipsis => Some('.'),
+            Event::EnDash | Event::EmDash => Some('-'),
+            Event::LeftSingleQuote | Event::LeftDoubleQuote if self.explicit_quote(i) => Some('{'),
+            Event::LeftSingleQuote | Event::RightSingleQuote => Some('\''),
+            Event::LeftDoubleQuote | Event::RightDoubleQuote => Some('"'),
+            _ => None,
+        }
+    }
+
+    /// Write a string without any newlines, preceded by the line prefix if at the start of a line.
+    fn write_str(&mut self, s: &str) {
+        if self.line_start {
+            for b in &mut self.blocks {
+                if let Some(first) = b.first.take() {
+                    self.buf.push_str(&first);
+                } else {
+                    self.buf.push_str(&b.rest);
+                }
+            }
+            self.line_start = false;
+            if self.line_pending {
+                self.line_pending = false;
+                self.line_begin = Some(self.buf.len());
+            }
+        }
+        if !s.is_empty() {
+            self.inline_start = false;
+            self.buf.push_str(s);
+            self.prev = s.chars().last();
+        }
+    }
+
+    /// End the current line. If nothing has been written on it, only the trimmed prefix will be
+    /// written.
+    fn newline(&mut self) {
+        self.line_pending = false;
+        if let Some(pos) = self.line_begin.take() {
+            if starts_block(&self.buf[pos..]) {
+                // empty attributes, to keep the line within the paragraph
+                self.buf.insert_str(pos, "{}");
+            }
+        }
+        if self.line_start {
+            let prefix = self
+                .blocks
+                .iter()
+                .map(|b| b.rest.as_str())
+                .collect::<String>();
+            self.buf.push_str(prefix.trim_end());
+        }
+        self.buf.push('\n');
+        self.line_start = true;
+        self.prev = None;
+    }
+}
+
+/// Whether a line of a paragraph would not be parsed as part of it on its own, e.g. as it starts a
+/// list or a link definition.
+fn starts_block(line: &str) -> bool {
+    line.starts_with(char::is_whitespace)
+        || !matches!(
+            Parser::new(line).next(),
+            Some(Event::Start(Container::Paragraph, _))
+        )
+}
+
+/// Whether the event begins (or ends if not `first`) with whitespace.
+fn space(e: &Event, first: bool) -> bool {
+    match e {
+        Event::Str(s) => {
+            let c = if first {
+                s.chars().next()
+            } else {
+                s.chars().last()
+            };
+            !matches!(c, Some(c) if !c.is_whitespace())
+        }
+        Event::Softbreak | Event::Hardbreak | Event::NonBreakingSpace => true,
+        _ => false,
+    }
+}
+
+/// The left and right quote of the same kind as the given quote.
+fn quote_pair<'s>(e: &Event) -> (Event<'s>, Event<'s>) {
+    match e {
+        Event::LeftSingleQuote | Event::RightSingleQuote => {
+            (Event::LeftSingleQuote, Event::RightSingleQuote)
+        }
+        _ => (Event::LeftDoubleQuote, Event::RightDoubleQuote),
+    }
+}
+
+/// Length of the longest sequence of backticks.
+fn backtick_run(s: &str) -> usize {
+    s.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+fn roman(mut n: u64) -> String {
+    const NUMERALS: &[(u64, &str)] = &[
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+    let mut s = String::new();
+    for (v, numeral) in NUMERALS {
+        while n >= *v {
+            s.push_str(numeral);
+            n -= v;
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use super::Renderer;
+    use crate::Attributes;
+    use crate::Container::*;
+    use crate::Event;
+    use crate::Event::*;
+    use crate::LinkType;
+    use crate::Parser;
+    use crate::Render;
+    use crate::SpanLinkType;
+
+    fn render<'s>(events: impl Iterator<Item = Event<'s>>) -> String {
+        let mut out = String::new();
+        Renderer::default().push(events, &mut out).unwrap();
+        out
+    }
+
+    /// Events without blanklines and escapes, with adjacent strings merged.
+    fn normalize(src: &str) -> Vec<Event<'_>> {
+        let mut events: Vec<Event> = Vec::new();
+        for e in Parser::new(src) {
+            match (e, events.last_mut()) {
+                (Blankline | Escape, _) => {}
+                (Str(s), Some(Str(prev))) => *prev = format!("{}{}", prev, s).into(),
+                (e, _) => events.push(e),
+            }
+        }
+        events
+    }
+
+    macro_rules! test_render {
+        ($src:expr $(,)?) => {
+            test_render!($src, $src);
+        };
+        ($src:expr, $expected:expr $(,)?) => {
+            let src = $src;
+            let djot = render(Parser::new(src));
+            assert_eq!(djot, $expected, "\n\nsrc:\n{}\n", src);
+            assert_eq!(normalize(&djot), normalize(src), "\n\noutput:\n{}\n", djot);
+        };
+    }
+
+    #[test]
+    fn blocks() {
+        test_render!(
+            concat!(
+                "# heading\n",
+                "\n",
+                "para\n",
+                "\n",
+                "> quote\n",
+                ">\n",
+                "> - list\n",
+                "> - items\n",
+                "\n",
+                "* * *\n",
+                "\n",
+                "``` rust\n",
+                "fn main() {}\n",
+                "```\n",
+            ),
+            concat!(
+                "# heading\n",
+                "\n",
+                "para\n",
+                "\n",
+                "> quote\n",
+                ">\n",
+                "> - list\n",
+                "> - items\n",
+                "\n",
+                "* * *\n",
+                "\n",
+                "```rust\n",
+                "fn main() {}\n",
+                "```\n",
+            )
+        );
+    }
+
+    #[test]
+    fn lists() {
+        test_render!(concat!(
+            "1. one\n",
+            "2. two\n",
+            "\n",
+            "   - nested\n",
+            "3. three\n",
+            "\n",
+            "- other\n",
+            "\n",
+            "(c) c\n",
+            "\n",
+            "(c) d\n",
+            "\n",
+            "- [ ] task\n",
+            "- [x] done\n",
+        ));
+    }
+
+    #[test]
+    fn div_nested() {
+        test_render!(":::: a\n::: b\ninner\n:::\n::::\n");
+    }
+
+    #[test]
+    fn table() {
+        test_render!(concat!(
+            "{.t}\n",
+            "| a | b |\n",
+            "|:--|--:|\n",
+            "| _c_ | d |\n",
+            "^ caption\n",
+        ));
+    }
+
+    #[test]
+    fn footnote_definition() {
+        test_render!(concat!(
+            "text[^a]\n",
+            "\n",
+            "[^a]: note\n",
+            "\n",
+            "  more\n",
+            "\n",
+            ": term\n",
+            "\n",
+            "  details\n",
+        ));
+    }
+
+    #[test]
+    fn inline() {
+        test_render!(
+            "*a* _b_ {*c*} ^d^ ~e~ {+f+} {-g-} {=h=} [i]{.j} k{#l}\n",
+            "*a* _b_ *c* ^d^ ~e~ {+f+} {-g-} {=h=} [i]{.j} [k]{#l}\n",
+        );
+        test_render!("{* a *} x{_b_}y\n", "{* a *} x_b_y\n");
+        test_render!("`a` ``b`c`` `` `d` `` $`e` $$`f` `g`{=html}\n");
+        test_render!("<https://a.com> <a@b.c> :smile: [^fn] \"q\" ... -- ---\n");
+        test_render!("a\\\nb\nc\n");
+    }
+
+    #[test]
+    fn quotes() {
+        test_render!("'a' don't \"b 'c'\" 5'\n");
+        test_render!("_{'&\n", "\\_{'&\n");
+        test_render!("a{'b {\"c\n");
+        test_render!("x{\" a \"}y ('}b')\n");
+    }
+
+    #[test]
+    fn trailing_space() {
+        test_render!("{.a}\n# head {#h}\n", "{.a}\n# head {}\n");
+        test_render!("a\n{.x}\n", "a\n{}\n");
+        test_render!("> a\n> {.x}\n", "> a\n> {}\n");
+    }
+
+    #[test]
+    fn links() {
+        test_render!("[a](b){.c} ![d](e)\n");
+        test_render!(concat!(
+            "[a][r]{.own} [b][]\n",
+            "\n",
+            "{.def}\n",
+            "[r]: /url\n",
+            "\n",
+            "{.x}\n",
+            "[b]: /other\n",
+        ));
+        test_render!(
+            "# Heading\n\n[link][Heading]\n",
+            "# Heading\n\n[link][Heading]\n",
+        );
+    }
+
+    #[test]
+    fn escape() {
+        test_render!(
+            concat!(
+                "\\# not heading\n",
+                "\\- not list\n",
+                "1\\. not list\n",
+                "\\*not strong\\* \\_\\[\\]\\{\\}\n",
+                "a\\.\\.. \\-\\- \\:b\\: c\\\\\n",
+            ),
+            concat!(
+                "\\# not heading\n",
+                "\\- not list\n",
+                "1\\. not list\n",
+                "\\*not strong\\* \\_\\[\\]\\{\\}\n",
+                "a\\.\\.\\. \\-\\- \\:b: c\\\\\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        test_render!(
+            "{#i .a .b key=\"v \\\" \\\\\" k2=x}\npara\n",
+            "{#i .a .b key=\"v \\\" \\\\\" k2=\"x\"}\npara\n",
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        test_render!("::::{.c}\n", "{}::::\n");
+        test_render!("$){#i}[^a]: ]\n", "{}[$)]{#i}[^a]: \\]\n");
+        test_render!("{#i}\t> a\n", "{}\t> a\n");
+        test_render!("\\\ta\n", "{}\ta\n");
+        test_render!("_{.c}_ '{#i}'\n", "{_{.c}_} {'{.c}'}\n");
+        test_render!("{.c}{.c}\n", "{}{.c}\n");
+        test_render!("a\\\n{.c}\n", "a\\\n{}{.c}\n");
+        test_render!("## a\n##\n");
+        test_render!("+ --\n", "- {}--\n");
+        test_render!("| a\t{} |\n|` |\n");
+        test_render!("a `b\\\n");
+        test_render!("```\ncode");
+        test_render!("```=html\n<br>\n\n", "```=html\n<br>\n\n```\n");
+    }
+
+    /// Render random combinations of syntax and check that the output parses to the same events.
+    #[test]
+    fn roundtrip_random() {
+        const TOKENS: &[&str] = &[
+            "a", "b", " ", "\t", "\n", "\n\n", "*", "_", "{", "}", "[", "]", "(", ")", "#", ">",
+            "-", "+", ":", ".", "'", "\"", "`", "$", "\\", "^", "~", "=", "|", "!", "<", "%", "1",
+            "{.c}", "{#i}", "[^a]", "::::", "```", "- ", "> ", "# ", "| a |", "{=", "=}", "{-",
+            "-}", "{+", "+}", "a@b.c", "<a>", ": ", "^ ", "[a]: b", "*a*", "_a_", "'a'", "\"a\"",
+            "..", "--",
+        ];
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut rand = move |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % n as u64) as usize
+        };
+        for _ in 0..5000 {
+            let len = rand(12) + 1;
+            let src = (0..len)
+                .map(|_| TOKENS[rand(TOKENS.len())])
+                .collect::<String>();
+            let djot = render(Parser::new(&src));
+            assert_eq!(
+                normalize(&djot),
+                normalize(&src),
+                "\n\nsrc:\n{}\n\noutput:\n{}\n",
+                src,
+                djot
+            );
+        }
+    }
+
+    #[test]
+    fn events() {
+        let mut attrs = Attributes::new();
+        attrs.insert("class", "c".into());
+        let events = [
+            Start(Paragraph, Attributes::new()),
+            Start(
+                Link("#id".into(), LinkType::Span(SpanLinkType::Inline)),
+                attrs,
+            ),
+            Str("a".into()),
+            End(Link("#id".into(), LinkType::Span(SpanLinkType::Inline))),
+            Start(Span, Attributes::new()),
+            Str(" b".into()),
+            End(Span),
+            Softbreak,
+            Softbreak,
+            Str("c".into()),
+            End(Paragraph),
+        ];
+        assert_eq!(render(events.into_iter()), "[a](#id){.c} b\n{}\nc\n");
+    }
+}
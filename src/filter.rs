@@ -0,0 +1,557 @@
+//! Composable adapters that filter a stream of [`Event`]s.
+//!
+//! The [`Filter`] trait is implemented for all iterators of [`Event`]s. It provides adapters that
+//! operate on whole containers, i.e. everything from an [`Event::Start`] up to and including its
+//! matching [`Event::End`], so that filters do not have to keep track of nesting themselves. The
+//! adapters are lazy and may be chained, much like the adapters of [`Iterator`].
+//!
+//! # Examples
+//!
+//! Turn notes into sections, remove all images and upper case the text of headings:
+//!
+//! ```
+//! # #[cfg(feature = "html")]
+//! # {
+//! use jotdown::filter::Filter;
+//! use jotdown::Container;
+//! use jotdown::Render;
+//!
+//! let src = "# Title\n\n::: note\nsee ![img](a.png)\n:::\n";
+//! let events = jotdown::Parser::new(src)
+//!     .map_containers(|c| match c {
+//!         Container::Div { class } if class == "note" => Container::Section { id: "".into() },
+//!         c => c,
+//!     })
+//!     .remove_subtrees(|c, _| matches!(c, Container::Image(..)))
+//!     .map_text(
+//!         |c, _| matches!(c, Container::Heading { .. }),
+//!         |text| text.to_uppercase().into(),
+//!     );
+//! let mut html = String::new();
+//! jotdown::html::Renderer::default().push(events, &mut html);
+//! assert_eq!(
+//!     html,
+//!     concat!(
+//!         "<section id=\"Title\">\n",
+//!         "<h1>TITLE</h1>\n",
+//!         "<section>\n",
+//!         "<p>see </p>\n",
+//!         "</section>\n",
+//!         "</section>\n",
+//!     ),
+//! );
+//! # }
+//! ```
+
+use crate::Attributes;
+use crate::Container;
+use crate::CowStr;
+use crate::Event;
+
+/// Adapters for iterators of [`Event`]s, see the [module documentation](self).
+///
+/// The predicates given to the adapters are called with the container and attributes of each
+/// [`Event::Start`]. If a predicate matches, the adapter applies to the container along with all
+/// events up to and including its matching [`Event::End`].
+pub trait Filter<'s>: Iterator<Item = Event<'s>> + Sized {
+    /// Replace the container of each [`Event::Start`] and its matching [`Event::End`].
+    ///
+    /// The closure is called once per container, the result is used for both events.
+    fn map_containers<F>(self, f: F) -> MapContainers<'s, Self, F>
+    where
+        F: FnMut(Container<'s>) -> Container<'s>,
+    {
+        MapContainers {
+            inner: self,
+            f,
+            open: Vec::new(),
+        }
+    }
+
+    /// Modify the attributes of each [`Event::Start`].
+    ///
+    /// # Examples
+    ///
+    /// Add a class to all tables:
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// use jotdown::filter::Filter;
+    ///
+    /// let events = Parser::new("|a|")
+    ///     .map_attributes(|c, attrs| {
+    ///         if matches!(c, Container::Table) {
+    ///             attrs.insert("class", "wide".into());
+    ///         }
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// let mut attrs = Attributes::new();
+    /// attrs.insert("class", "wide".into());
+    /// assert_eq!(events[0], Event::Start(Container::Table, attrs));
+    /// ```
+    fn map_attributes<F>(self, f: F) -> MapAttributes<Self, F>
+    where
+        F: FnMut(&Container<'s>, &mut Attributes<'s>),
+    {
+        MapAttributes { inner: self, f }
+    }
+
+    /// Replace each matching container, including its content, with the events returned by the
+    /// closure.
+    ///
+    /// The closure is given all events of the container, from its [`Event::Start`] to its
+    /// [`Event::End`]. Containers within a replaced container are not matched.
+    ///
+    /// # Examples
+    ///
+    /// Replace block quotes with their plain text:
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// use jotdown::filter::Filter;
+    ///
+    /// let events = Parser::new("> a _b_")
+    ///     .replace_subtrees(
+    ///         |c, _| matches!(c, Container::Blockquote),
+    ///         |events| {
+    ///             let text: String = events
+    ///                 .into_iter()
+    ///                 .filter_map(|e| match e {
+    ///                     Event::Str(s) => Some(s),
+    ///                     _ => None,
+    ///                 })
+    ///                 .collect();
+    ///             vec![Event::Str(text.into())]
+    ///         },
+    ///     )
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(events, &[Event::Str("a b".into())]);
+    /// ```
+    fn replace_subtrees<P, R, J>(self, predicate: P, replace: R) -> ReplaceSubtrees<Self, P, R, J>
+    where
+        P: FnMut(&Container<'s>, &Attributes<'s>) -> bool,
+        R: FnMut(Vec<Event<'s>>) -> J,
+        J: IntoIterator<Item = Event<'s>>,
+    {
+        ReplaceSubtrees {
+            inner: self,
+            predicate,
+            replace,
+            replacement: None,
+        }
+    }
+
+    /// Remove each matching container, including its content.
+    fn remove_subtrees<P>(self, predicate: P) -> RemoveSubtrees<Self, P>
+    where
+        P: FnMut(&Container<'s>, &Attributes<'s>) -> bool,
+    {
+        RemoveSubtrees {
+            inner: self,
+            predicate,
+        }
+    }
+
+    /// Wrap each matching container in the container returned by the closure.
+    ///
+    /// # Examples
+    ///
+    /// Wrap all images in a div:
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// use jotdown::filter::Filter;
+    ///
+    /// let events = Parser::new("![alt](a.png)")
+    ///     .wrap_subtrees(
+    ///         |c, _| matches!(c, Container::Image(..)),
+    ///         |_, _| (Container::Span, Attributes::new()),
+    ///     )
+    ///     .skip(1)
+    ///     .take(3)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     events,
+    ///     &[
+    ///         Event::Start(Container::Span, Attributes::new()),
+    ///         Event::Start(
+    ///             Container::Image("a.png".into(), SpanLinkType::Inline),
+    ///             Attributes::new(),
+    ///         ),
+    ///         Event::Str("alt".into()),
+    ///     ],
+    /// );
+    /// ```
+    fn wrap_subtrees<P, W>(self, predicate: P, wrapper: W) -> WrapSubtrees<'s, Self, P, W>
+    where
+        P: FnMut(&Container<'s>, &Attributes<'s>) -> bool,
+        W: FnMut(&Container<'s>, &Attributes<'s>) -> (Container<'s>, Attributes<'s>),
+    {
+        WrapSubtrees {
+            inner: self,
+            predicate,
+            wrapper,
+            depth: 0,
+            wrappers: Vec::new(),
+            pending: None,
+        }
+    }
+
+    /// Replace the text of each [`Event::Str`] within a matching container.
+    fn map_text<P, F>(self, predicate: P, f: F) -> MapText<Self, P, F>
+    where
+        P: FnMut(&Container<'s>, &Attributes<'s>) -> bool,
+        F: FnMut(CowStr<'s>) -> CowStr<'s>,
+    {
+        MapText {
+            inner: self,
+            predicate,
+            f,
+            open: Vec::new(),
+            matched: 0,
+        }
+    }
+}
+
+impl<'s, I: Iterator<Item = Event<'s>>> Filter<'s> for I {}
+
+/// Pull all events of a container whose [`Event::Start`] has already been pulled, up to and
+/// including its matching [`Event::End`].
+fn skip_container<'s, I: Iterator<Item = Event<'s>>>(events: &mut I, mut f: impl FnMut(Event<'s>)) {
+    let mut depth = 1;
+    while depth > 0 {
+        if let Some(e) = events.next() {
+            match &e {
+                Event::Start(..) => depth += 1,
+                Event::End(..) => depth -= 1,
+                _ => {}
+            }
+            f(e);
+        } else {
+            break;
+        }
+    }
+}
+
+/// Adapter returned by [`Filter::map_containers`].
+pub struct MapContainers<'s, I, F> {
+    inner: I,
+    f: F,
+    /// Replacement of each open container.
+    open: Vec<Container<'s>>,
+}
+
+impl<'s, I, F> Iterator for MapContainers<'s, I, F>
+where
+    I: Iterator<Item = Event<'s>>,
+    F: FnMut(Container<'s>) -> Container<'s>,
+{
+    type Item = Event<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|e| match e {
+            Event::Start(c, attrs) => {
+                let c = (self.f)(c);
+                self.open.push(c.clone());
+                Event::Start(c, attrs)
+            }
+            Event::End(c) => Event::End(self.open.pop().unwrap_or(c)),
+            e => e,
+        })
+    }
+}
+
+/// Adapter returned by [`Filter::map_attributes`].
+pub struct MapAttributes<I, F> {
+    inner: I,
+    f: F,
+}
+
+impl<'s, I, F> Iterator for MapAttributes<I, F>
+where
+    I: Iterator<Item = Event<'s>>,
+    F: FnMut(&Container<'s>, &mut Attributes<'s>),
+{
+    type Item = Event<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|e| match e {
+            Event::Start(c, mut attrs) => {
+                (self.f)(&c, &mut attrs);
+                Event::Start(c, attrs)
+            }
+            e => e,
+        })
+    }
+}
+
+/// Adapter returned by [`Filter::replace_subtrees`].
+pub struct ReplaceSubtrees<I, P, R, J: IntoIterator> {
+    inner: I,
+    predicate: P,
+    replace: R,
+    /// Remaining events of the current replacement.
+    replacement: Option<J::IntoIter>,
+}
+
+impl<'s, I, P, R, J> Iterator for ReplaceSubtrees<I, P, R, J>
+where
+    I: Iterator<Item = Event<'s>>,
+    P: FnMut(&Container<'s>, &Attributes<'s>) -> bool,
+    R: FnMut(Vec<Event<'s>>) -> J,
+    J: IntoIterator<Item = Event<'s>>,
+{
+    type Item = Event<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(replacement) = &mut self.replacement {
+                if let Some(e) = replacement.next() {
+                    return Some(e);
+                }
+                self.replacement = None;
+            }
+
+            let e = self.inner.next()?;
+            if let Event::Start(c, attrs) = &e {
+                if (self.predicate)(c, attrs) {
+                    let mut events = vec![e];
+                    skip_container(&mut self.inner, |e| events.push(e));
+                    self.replacement = Some((self.replace)(events).into_iter());
+                    continue;
+                }
+            }
+            return Some(e);
+        }
+    }
+}
+
+/// Adapter returned by [`Filter::remove_subtrees`].
+pub struct RemoveSubtrees<I, P> {
+    inner: I,
+    predicate: P,
+}
+
+impl<'s, I, P> Iterator for RemoveSubtrees<I, P>
+where
+    I: Iterator<Item = Event<'s>>,
+    P: FnMut(&Container<'s>, &Attributes<'s>) -> bool,
+{
+    type Item = Event<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let e = self.inner.next()?;
+            if let Event::Start(c, attrs) = &e {
+                if (self.predicate)(c, attrs) {
+                    skip_container(&mut self.inner, |_| {});
+                    continue;
+                }
+            }
+            return Some(e);
+        }
+    }
+}
+
+/// Adapter returned by [`Filter::wrap_subtrees`].
+pub struct WrapSubtrees<'s, I, P, W> {
+    inner: I,
+    predicate: P,
+    wrapper: W,
+    /// Number of currently open containers.
+    depth: usize,
+    /// Open wrapper containers, and the depth of the container they wrap.
+    wrappers: Vec<(usize, Container<'s>)>,
+    /// Event to emit after the current one.
+    pending: Option<Event<'s>>,
+}
+
+impl<'s, I, P, W> Iterator for WrapSubtrees<'s, I, P, W>
+where
+    I: Iterator<Item = Event<'s>>,
+    P: FnMut(&Container<'s>, &Attributes<'s>) -> bool,
+    W: FnMut(&Container<'s>, &Attributes<'s>) -> (Container<'s>, Attributes<'s>),
+{
+    type Item = Event<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.pending.take() {
+            return Some(e);
+        }
+
+        let e = self.inner.next()?;
+        match &e {
+            Event::Start(c, attrs) => {
+                self.depth += 1;
+                if (self.predicate)(c, attrs) {
+                    let (wrapper, wrapper_attrs) = (self.wrapper)(c, attrs);
+                    self.wrappers.push((self.depth, wrapper.clone()));
+                    self.pending = Some(e);
+                    return Some(Event::Start(wrapper, wrapper_attrs));
+                }
+            }
+            Event::End(..) => {
+                if matches!(self.wrappers.last(), Some((depth, _)) if *depth == self.depth) {
+                    let (_, wrapper) = self.wrappers.pop().unwrap();
+                    self.pending = Some(Event::End(wrapper));
+                }
+                self.depth = self.depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+        Some(e)
+    }
+}
+
+/// Adapter returned by [`Filter::map_text`].
+pub struct MapText<I, P, F> {
+    inner: I,
+    predicate: P,
+    f: F,
+    /// Whether each open container matched the predicate.
+    open: Vec<bool>,
+    /// Number of open containers that matched the predicate.
+    matched: usize,
+}
+
+impl<'s, I, P, F> Iterator for MapText<I, P, F>
+where
+    I: Iterator<Item = Event<'s>>,
+    P: FnMut(&Container<'s>, &Attributes<'s>) -> bool,
+    F: FnMut(CowStr<'s>) -> CowStr<'s>,
+{
+    type Item = Event<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|e| match e {
+            Event::Start(c, attrs) => {
+                let matched = (self.predicate)(&c, &attrs);
+                self.open.push(matched);
+                if matched {
+                    self.matched += 1;
+                }
+                Event::Start(c, attrs)
+            }
+            Event::End(c) => {
+                if self.open.pop() == Some(true) {
+                    self.matched -= 1;
+                }
+                Event::End(c)
+            }
+            Event::Str(s) if self.matched > 0 => Event::Str((self.f)(s)),
+            e => e,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Filter;
+    use crate::Attributes;
+    use crate::Container::*;
+    use crate::Event::*;
+    use crate::LinkType;
+    use crate::Parser;
+    use crate::SpanLinkType;
+
+    #[test]
+    fn map_containers() {
+        let events = Parser::new("_a_ *b*")
+            .map_containers(|c| match c {
+                Emphasis => Strong,
+                Strong => Emphasis,
+                c => c,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            &[
+                Start(Paragraph, Attributes::new()),
+                Start(Strong, Attributes::new()),
+                Str("a".into()),
+                End(Strong),
+                Str(" ".into()),
+                Start(Emphasis, Attributes::new()),
+                Str("b".into()),
+                End(Emphasis),
+                End(Paragraph),
+            ]
+        );
+    }
+
+    #[test]
+    fn replace_subtrees() {
+        let events = Parser::new("a [b _c_](d) e")
+            .replace_subtrees(
+                |c, _| matches!(c, Link(..)),
+                |events| events.into_iter().skip(1).take(1),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            &[
+                Start(Paragraph, Attributes::new()),
+                Str("a ".into()),
+                Str("b ".into()),
+                Str(" e".into()),
+                End(Paragraph),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_subtrees() {
+        let events = Parser::new("> a\n>\n> > b\n\nc")
+            .remove_subtrees(|c, _| matches!(c, Blockquote))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            &[
+                Blankline,
+                Start(Paragraph, Attributes::new()),
+                Str("c".into()),
+                End(Paragraph),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_subtrees() {
+        let events = Parser::new("[a [b](c)](d)")
+            .wrap_subtrees(
+                |c, _| matches!(c, Link(..)),
+                |_, _| (Span, Attributes::new()),
+            )
+            .collect::<Vec<_>>();
+        let link = |url| Link(url, LinkType::Span(SpanLinkType::Inline));
+        assert_eq!(
+            events,
+            &[
+                Start(Paragraph, Attributes::new()),
+                Start(Span, Attributes::new()),
+                Start(link("d".into()), Attributes::new()),
+                Str("a ".into()),
+                Start(Span, Attributes::new()),
+                Start(link("c".into()), Attributes::new()),
+                Str("b".into()),
+                End(link("c".into())),
+                End(Span),
+                End(link("d".into())),
+                End(Span),
+                End(Paragraph),
+            ]
+        );
+    }
+
+    #[test]
+    fn map_text() {
+        let events = Parser::new("a _b *c*_ d")
+            .map_text(|c, _| matches!(c, Emphasis), |s| s.to_uppercase().into())
+            .filter_map(|e| match e {
+                Str(s) => Some(s),
+                _ => None,
+            })
+            .collect::<String>();
+        assert_eq!(events, "a B C d");
+    }
+}
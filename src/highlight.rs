@@ -0,0 +1,593 @@
+//! A built-in syntax highlighter for common programming languages.
+//!
+//! The [`BuiltinHighlighter`] may be used with [`html::Renderer::highlighter`] in order to
+//! highlight code blocks and inline verbatim, without depending on any external syntax
+//! definitions or themes.
+//!
+//! # Examples
+//!
+//! ```
+//! # use jotdown::*;
+//! # use jotdown::html::*;
+//! use jotdown::highlight::BuiltinHighlighter;
+//!
+//! let src = "``` rust\nlet x = 1; // one\n```\n";
+//! let mut html = String::new();
+//! Renderer::default()
+//!     .highlighter(BuiltinHighlighter)
+//!     .push(Parser::new(src), &mut html)
+//!     .unwrap();
+//! assert_eq!(
+//!     html,
+//!     concat!(
+//!         "<pre><code class=\"language-rust\">",
+//!         "<span class=\"hl-keyword\">let</span> x = <span class=\"hl-number\">1</span>; ",
+//!         "<span class=\"hl-comment\">// one</span>\n",
+//!         "</code></pre>\n",
+//!     ),
+//! );
+//! ```
+//!
+//! [`html::Renderer::highlighter`]: crate::html::Renderer::highlighter
+
+use crate::html::write_text;
+use crate::html::Highlighter;
+use crate::Attributes;
+
+/// [`Highlighter`] for common programming languages.
+///
+/// Keywords, literals, strings, comments and numbers are wrapped in `<span>` elements with the
+/// classes `hl-keyword`, `hl-literal`, `hl-string`, `hl-comment` and `hl-number`, respectively.
+/// The elements never span multiple lines, so the output may be split into lines with
+/// [`html::Renderer::code_block_lines`].
+///
+/// The supported languages are `bash`, `c`, `cpp`, `go`, `java`, `javascript`, `json`, `python`,
+/// `rust`, `sh`, `toml` and `typescript`, as well as common abbreviations of them, e.g. `rs` or
+/// `js`. Code in other languages is rendered as plain text.
+///
+/// [`html::Renderer::code_block_lines`]: crate::html::Renderer::code_block_lines
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuiltinHighlighter;
+
+impl BuiltinHighlighter {
+    /// Returns `true` if the language is supported by the highlighter.
+    #[must_use]
+    pub fn supports(language: &str) -> bool {
+        Language::find(language).is_some()
+    }
+}
+
+impl Highlighter for BuiltinHighlighter {
+    fn highlight(&self, language: &str, _attrs: &Attributes, code: &str) -> Option<String> {
+        let lang = Language::find(language)?;
+        let mut out = String::with_capacity(code.len());
+        let mut plain = 0;
+        let mut i = 0;
+        while i < code.len() {
+            let rest = &code[i..];
+            let (class, len) = if let Some(len) = lang.comment(rest) {
+                ("hl-comment", len)
+            } else if let Some(len) = lang.string(rest) {
+                ("hl-string", len)
+            } else if rest.starts_with(|c: char| c.is_ascii_digit())
+                && !code[..i].ends_with(is_ident)
+            {
+                ("hl-number", number(rest))
+            } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                let len = rest.find(|c| !is_ident(c)).unwrap_or(rest.len());
+                let word = &rest[..len];
+                if lang.keywords.contains(&word) {
+                    ("hl-keyword", len)
+                } else if lang.literals.contains(&word) {
+                    ("hl-literal", len)
+                } else {
+                    i += len;
+                    continue;
+                }
+            } else {
+                i += rest.chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+            write_text(&code[plain..i], &mut out).ok()?;
+            write_span(class, &rest[..len], &mut out).ok()?;
+            i += len;
+            plain = i;
+        }
+        write_text(&code[plain..], &mut out).ok()?;
+        Some(out)
+    }
+}
+
+/// Write a token within a span, split at line breaks so that no span contains a line break.
+fn write_span(class: &str, token: &str, out: &mut String) -> std::fmt::Result {
+    for (i, line) in token.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        if !line.is_empty() {
+            out.push_str("<span class=\"");
+            out.push_str(class);
+            out.push_str("\">");
+            write_text(line, &mut *out)?;
+            out.push_str("</span>");
+        }
+    }
+    Ok(())
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Length of a number at the start of the input, e.g. `0x1f`, `1_000` or `3.14`.
+fn number(s: &str) -> usize {
+    let b = s.as_bytes();
+    let mut n = 0;
+    while n < b.len()
+        && (is_ident(char::from(b[n]))
+            || (b[n] == b'.' && matches!(b.get(n + 1), Some(c) if c.is_ascii_digit())))
+    {
+        n += 1;
+    }
+    n
+}
+
+/// Lexical syntax of a language.
+struct Language {
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    literals: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// String delimiters, longer delimiters must be placed before their prefixes.
+    strings: &'static [&'static str],
+}
+
+impl Language {
+    fn find(name: &str) -> Option<&'static Self> {
+        LANGUAGES
+            .iter()
+            .find(|l| l.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+    }
+
+    /// Length of a comment at the start of the input.
+    fn comment(&self, s: &str) -> Option<usize> {
+        if self.line_comments.iter().any(|c| s.starts_with(c)) {
+            return Some(s.find('\n').unwrap_or(s.len()));
+        }
+        let (start, end) = self.block_comment?;
+        let content = s.strip_prefix(start)?;
+        Some(
+            content
+                .find(end)
+                .map_or(s.len(), |i| start.len() + i + end.len()),
+        )
+    }
+
+    /// Length of a string at the start of the input, including its delimiters.
+    fn string(&self, s: &str) -> Option<usize> {
+        let delim = self.strings.iter().find(|d| s.starts_with(*d))?;
+        let mut chars = s[delim.len()..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c == '\\' {
+                chars.next();
+            } else if s[delim.len() + i..].starts_with(delim) {
+                return Some(delim.len() + i + delim.len());
+            }
+        }
+        Some(s.len())
+    }
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while",
+];
+
+const CPP_KEYWORDS: &[&str] = &[
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "constexpr",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "explicit",
+    "extern",
+    "float",
+    "for",
+    "friend",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "operator",
+    "override",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "throw",
+    "try",
+    "typedef",
+    "typename",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+const JS_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "of",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+const TS_KEYWORDS: &[&str] = &[
+    "abstract",
+    "any",
+    "as",
+    "async",
+    "await",
+    "boolean",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "declare",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "keyof",
+    "let",
+    "namespace",
+    "never",
+    "new",
+    "number",
+    "of",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "return",
+    "static",
+    "string",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "type",
+    "typeof",
+    "unknown",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+const JS_LITERALS: &[&str] = &["true", "false", "null", "undefined", "NaN", "Infinity"];
+
+static LANGUAGES: &[Language] = &[
+    Language {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+            "type", "unsafe", "use", "where", "while",
+        ],
+        literals: &["true", "false"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\""],
+    },
+    Language {
+        names: &["c", "h"],
+        keywords: C_KEYWORDS,
+        literals: &["NULL", "true", "false"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'"],
+    },
+    Language {
+        names: &["cpp", "c++", "cc", "cxx", "hpp"],
+        keywords: CPP_KEYWORDS,
+        literals: &["nullptr", "true", "false"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'"],
+    },
+    Language {
+        names: &["go", "golang"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "fallthrough",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "type",
+            "var",
+        ],
+        literals: &["true", "false", "nil", "iota"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'", "`"],
+    },
+    Language {
+        names: &["java"],
+        keywords: &[
+            "abstract",
+            "boolean",
+            "break",
+            "byte",
+            "case",
+            "catch",
+            "char",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extends",
+            "final",
+            "finally",
+            "float",
+            "for",
+            "if",
+            "implements",
+            "import",
+            "instanceof",
+            "int",
+            "interface",
+            "long",
+            "native",
+            "new",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "record",
+            "return",
+            "short",
+            "static",
+            "super",
+            "switch",
+            "synchronized",
+            "this",
+            "throw",
+            "throws",
+            "try",
+            "var",
+            "void",
+            "volatile",
+            "while",
+        ],
+        literals: &["true", "false", "null"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'"],
+    },
+    Language {
+        names: &["javascript", "js", "jsx", "mjs"],
+        keywords: JS_KEYWORDS,
+        literals: JS_LITERALS,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'", "`"],
+    },
+    Language {
+        names: &["typescript", "ts", "tsx"],
+        keywords: TS_KEYWORDS,
+        literals: JS_LITERALS,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'", "`"],
+    },
+    Language {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "case", "class", "continue", "def",
+            "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "match", "nonlocal", "not", "or", "pass", "raise", "return",
+            "try", "while", "with", "yield",
+        ],
+        literals: &["True", "False", "None"],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"\"\"", "'''", "\"", "'"],
+    },
+    Language {
+        names: &["sh", "bash", "shell", "zsh"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "exit", "export", "fi", "for",
+            "function", "if", "in", "local", "return", "then", "until", "while",
+        ],
+        literals: &["true", "false"],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"", "'"],
+    },
+    Language {
+        names: &["json"],
+        keywords: &[],
+        literals: &["true", "false", "null"],
+        line_comments: &[],
+        block_comment: None,
+        strings: &["\""],
+    },
+    Language {
+        names: &["toml"],
+        keywords: &[],
+        literals: &["true", "false"],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"\"\"", "'''", "\"", "'"],
+    },
+];
+
+#[cfg(test)]
+mod test {
+    use super::BuiltinHighlighter;
+    use crate::html::Highlighter;
+    use crate::Attributes;
+
+    fn highlight(language: &str, code: &str) -> Option<String> {
+        BuiltinHighlighter.highlight(language, &Attributes::new(), code)
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(highlight("", "let x"), None);
+        assert_eq!(highlight("brainfuck", "+[-]"), None);
+        assert!(BuiltinHighlighter::supports("Rust"));
+        assert!(!BuiltinHighlighter::supports("brainfuck"));
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            highlight("rust", "fn f() -> bool { x2 < 0x1f && true }").unwrap(),
+            concat!(
+                "<span class=\"hl-keyword\">fn</span> f() -&gt; bool { x2 &lt; ",
+                "<span class=\"hl-number\">0x1f</span> &amp;&amp; ",
+                "<span class=\"hl-literal\">true</span> }",
+            ),
+        );
+        assert_eq!(
+            highlight("py", "s = 'a\\'b' # c").unwrap(),
+            concat!(
+                "s = <span class=\"hl-string\">'a\\'b'</span> ",
+                "<span class=\"hl-comment\"># c</span>",
+            ),
+        );
+        assert_eq!(
+            highlight("json", "[1.5, \"<\", null]").unwrap(),
+            concat!(
+                "[<span class=\"hl-number\">1.5</span>, ",
+                "<span class=\"hl-string\">\"&lt;\"</span>, ",
+                "<span class=\"hl-literal\">null</span>]",
+            ),
+        );
+    }
+
+    #[test]
+    fn multiline() {
+        assert_eq!(
+            highlight("c", "/* a\n\nb */ x\n\"c").unwrap(),
+            concat!(
+                "<span class=\"hl-comment\">/* a</span>\n",
+                "\n",
+                "<span class=\"hl-comment\">b */</span> x\n",
+                "<span class=\"hl-string\">\"c</span>",
+            ),
+        );
+        assert_eq!(
+            highlight("python", "\"\"\"a\nb\"\"\"").unwrap(),
+            concat!(
+                "<span class=\"hl-string\">\"\"\"a</span>\n",
+                "<span class=\"hl-string\">b\"\"\"</span>",
+            ),
+        );
+    }
+}
@@ -1,7 +1,9 @@
 //! An HTML renderer that takes an iterator of [`Event`]s and emits HTML.
 
 use crate::Alignment;
+use crate::Attributes;
 use crate::Container;
+use crate::CowStr;
 use crate::Event;
 use crate::LinkType;
 use crate::ListKind;
@@ -11,29 +13,961 @@ use crate::Render;
 use crate::SpanLinkType;
 
 /// [`Render`] implementor that writes HTML output.
-#[derive(Default)]
-pub struct Renderer {}
+///
+/// The output may be configured using the builder methods, e.g.
+///
+/// ```
+/// # use jotdown::*;
+/// # use jotdown::html::*;
+/// let src = "# Title\n\n- [x] done\n";
+/// let mut html = String::new();
+/// Renderer::default()
+///     .sections(false)
+///     .task_list_checkboxes(true)
+///     .push(Parser::new(src), &mut html)
+///     .unwrap();
+/// assert_eq!(
+///     html,
+///     concat!(
+///         "<h1 id=\"Title\">Title</h1>\n",
+///         "<ul class=\"task-list\">\n",
+///         "<li class=\"checked\">\n",
+///         "<input type=\"checkbox\" disabled checked> done\n",
+///         "</li>\n",
+///         "</ul>\n",
+///     ),
+/// );
+/// ```
+pub struct Renderer<H = DefaultHandler> {
+    toc: Option<TableOfContents>,
+    options: Options,
+    handler: H,
+    highlighter: Option<Box<dyn Highlighter>>,
+    sanitizer: Option<Sanitizer>,
+    standalone: Option<Standalone>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            toc: None,
+            options: Options::default(),
+            handler: DefaultHandler,
+            highlighter: None,
+            sanitizer: None,
+            standalone: None,
+        }
+    }
+}
+
+/// Output choices of a [`Renderer`].
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    self_closing_tags: bool,
+    softbreak: SoftbreakStyle,
+    ascii_quotes: bool,
+    sections: bool,
+    task_list_checkboxes: bool,
+    table_row_groups: bool,
+    code_block_lines: bool,
+    mathml: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            self_closing_tags: false,
+            softbreak: SoftbreakStyle::Newline,
+            ascii_quotes: false,
+            sections: true,
+            task_list_checkboxes: false,
+            table_row_groups: false,
+            code_block_lines: false,
+            mathml: false,
+        }
+    }
+}
+
+/// How to render a softbreak, see [`Renderer::softbreak`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftbreakStyle {
+    /// A newline, i.e. the line break is kept as is in the output.
+    Newline,
+    /// A space, i.e. the whole paragraph is placed on a single line.
+    Space,
+    /// A `<br>` followed by a newline, i.e. the same as a hardbreak.
+    Break,
+}
+
+/// Restrictions for rendering untrusted input, see [`Renderer::sanitizer`].
+///
+/// By default, raw HTML is omitted, only the `http`, `https` and `mailto` URL schemes are allowed,
+/// and only the `id`, `class`, `title`, `lang` and `dir` attributes are allowed.
+#[derive(Debug, Clone)]
+pub struct Sanitizer {
+    raw_html: RawHtml,
+    url_schemes: Vec<String>,
+    attributes: Vec<String>,
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Self {
+            raw_html: RawHtml::Omit,
+            url_schemes: ["http", "https", "mailto"].map(String::from).to_vec(),
+            attributes: ["id", "class", "title", "lang", "dir"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// How raw HTML is rendered by a [`Sanitizer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawHtml {
+    /// The raw HTML is not rendered at all.
+    Omit,
+    /// The raw HTML is rendered as text, i.e. escaped.
+    Escape,
+}
+
+impl Sanitizer {
+    /// Choose how raw HTML is rendered, it is omitted by default. Raw content of other formats is
+    /// always omitted.
+    #[must_use]
+    pub fn raw_html(mut self, raw_html: RawHtml) -> Self {
+        self.raw_html = raw_html;
+        self
+    }
+
+    /// Set the URL schemes that are allowed in the destinations of links and images, e.g.
+    /// `https`.
+    ///
+    /// Relative URLs are always allowed. Links with other destinations are rendered without an
+    /// `href` attribute and images without a `src` attribute.
+    #[must_use]
+    pub fn url_schemes<I, S>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.url_schemes = schemes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the names of the attributes that are allowed on elements, e.g. `title`. Other
+    /// attributes are not rendered.
+    ///
+    /// Attributes produced by the renderer itself, e.g. the `href` of a link or the ids of
+    /// headings, are not affected.
+    #[must_use]
+    pub fn attributes<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.attributes = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Determine whether a URL is relative or has an allowed scheme.
+    #[must_use]
+    pub fn url_allowed(&self, url: &str) -> bool {
+        // browsers ignore leading control characters and spaces, and tabs and newlines anywhere
+        let url = url.trim_start_matches(|c: char| c <= ' ');
+        match url.find([':', '/', '?', '#']) {
+            Some(i) if url[i..].starts_with(':') => {
+                let scheme = url[..i]
+                    .chars()
+                    .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+                    .collect::<String>();
+                self.scheme_allowed(&scheme)
+            }
+            _ => true,
+        }
+    }
+
+    /// Determine whether an attribute is allowed.
+    #[must_use]
+    pub fn attribute_allowed(&self, name: &str) -> bool {
+        self.attributes.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+
+    fn scheme_allowed(&self, scheme: &str) -> bool {
+        self.url_schemes
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(scheme))
+    }
+
+    /// Remove a destination of a link or image that is not allowed.
+    fn container<'s>(&self, c: &Container<'s>) -> Container<'s> {
+        let allowed = match c {
+            Container::Link(_, LinkType::Email) => self.scheme_allowed("mailto"),
+            Container::Link(dst, _) => self.url_allowed(dst),
+            Container::Image(src, _) => self.url_allowed(src),
+            _ => true,
+        };
+        match c {
+            Container::Link(..) if !allowed => {
+                Container::Link("".into(), LinkType::Span(SpanLinkType::Unresolved))
+            }
+            Container::Image(_, ty) if !allowed => Container::Image("".into(), *ty),
+            c => c.clone(),
+        }
+    }
+
+    /// Remove attributes that are not allowed.
+    fn attributes_of<'s>(&self, attrs: &Attributes<'s>) -> Attributes<'s> {
+        let mut allowed = attrs.clone();
+        for (a, _) in attrs.iter().filter(|(a, _)| !self.attribute_allowed(a)) {
+            allowed.remove(a);
+        }
+        allowed
+    }
+}
+
+impl<H: HtmlHandler> Renderer<H> {
+    /// Use a handler to override how specific elements are rendered, see [`HtmlHandler`].
+    #[must_use]
+    pub fn handler<H2: HtmlHandler>(self, handler: H2) -> Renderer<H2> {
+        Renderer {
+            toc: self.toc,
+            options: self.options,
+            handler,
+            highlighter: self.highlighter,
+            sanitizer: self.sanitizer,
+            standalone: self.standalone,
+        }
+    }
+
+    /// Use a highlighter to render the content of code blocks and inline verbatim, see
+    /// [`Highlighter`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// # use jotdown::html::*;
+    /// let src = "``` shout\nhello\n```\n";
+    /// let mut html = String::new();
+    /// Renderer::default()
+    ///     .highlighter(|language: &str, _: &Attributes, code: &str| {
+    ///         if language == "shout" {
+    ///             Some(format!("<b>{}</b>", code.to_uppercase()))
+    ///         } else {
+    ///             None
+    ///         }
+    ///     })
+    ///     .push(Parser::new(src), &mut html)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     html,
+    ///     "<pre><code class=\"language-shout\"><b>HELLO\n</b></code></pre>\n",
+    /// );
+    /// ```
+    #[must_use]
+    pub fn highlighter<L: Highlighter + 'static>(mut self, highlighter: L) -> Self {
+        self.highlighter = Some(Box::new(highlighter));
+        self
+    }
+
+    /// Restrict the output in order to safely render untrusted input, see [`Sanitizer`].
+    ///
+    /// Raw HTML is omitted or escaped, link and image destinations with schemes that are not
+    /// allowed, e.g. `javascript:`, are removed, and attributes that are not allowed, e.g.
+    /// `onclick`, are not rendered. Handlers receive the sanitized containers and attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// # use jotdown::html::*;
+    /// let src = "[a](javascript:void){onclick=x title=t} `<b>`{=html}\n";
+    /// let mut html = String::new();
+    /// Renderer::default()
+    ///     .sanitizer(Sanitizer::default().raw_html(RawHtml::Escape))
+    ///     .push(Parser::new(src), &mut html)
+    ///     .unwrap();
+    /// assert_eq!(html, "<p><a title=\"t\">a</a> &lt;b&gt;</p>\n");
+    /// ```
+    #[must_use]
+    pub fn sanitizer(mut self, sanitizer: Sanitizer) -> Self {
+        self.sanitizer = Some(sanitizer);
+        self
+    }
+
+    /// Split code blocks into lines that may be numbered or highlighted, using their attributes.
+    ///
+    /// If a code block has the `line-numbers` class, each line is preceded by its number, e.g.
+    /// `<span class="line-number">1</span>`. If it has a `highlight` attribute with line numbers
+    /// or ranges of line numbers, e.g. `{highlight="1,3-5"}`, those lines are given the
+    /// `highlighted` class. The `highlight` attribute itself is not rendered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// # use jotdown::html::*;
+    /// let src = "{.line-numbers highlight=\"2\"}\n```\na\nb\n```\n";
+    /// let mut html = String::new();
+    /// Renderer::default()
+    ///     .code_block_lines(true)
+    ///     .push(Parser::new(src), &mut html)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     html,
+    ///     concat!(
+    ///         "<pre class=\"line-numbers\"><code>",
+    ///         "<span class=\"line\"><span class=\"line-number\">1</span>a</span>\n",
+    ///         "<span class=\"line highlighted\"><span class=\"line-number\">2</span>b</span>\n",
+    ///         "</code></pre>\n",
+    ///     ),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn code_block_lines(mut self, enable: bool) -> Self {
+        self.options.code_block_lines = enable;
+        self
+    }
+
+    /// Render math as MathML, see [`math::to_mathml`].
+    ///
+    /// Math that contains LaTeX constructs that are not supported is rendered as usual, i.e. as
+    /// LaTeX within a `<span class="math">` element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// # use jotdown::html::*;
+    /// let src = "$`\\sqrt{x}` and $`\\begin{x}`\n";
+    /// let mut html = String::new();
+    /// Renderer::default()
+    ///     .mathml(true)
+    ///     .push(Parser::new(src), &mut html)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     html,
+    ///     concat!(
+    ///         "<p><math><msqrt><mi>x</mi></msqrt></math> and ",
+    ///         "<span class=\"math inline\">\\(\\begin{x}\\)</span></p>\n",
+    ///     ),
+    /// );
+    /// ```
+    ///
+    /// [`math::to_mathml`]: crate::math::to_mathml
+    #[cfg(feature = "math")]
+    #[must_use]
+    pub fn mathml(mut self, enable: bool) -> Self {
+        self.options.mathml = enable;
+        self
+    }
+
+    fn hooks(&self) -> Hooks<'_> {
+        Hooks {
+            handler: &self.handler,
+            highlighter: self.highlighter.as_deref(),
+            sanitizer: self.sanitizer.as_ref(),
+        }
+    }
+
+    /// Close void elements XHTML-style, e.g. `<br />`, `<hr />` and `<img ... />`.
+    ///
+    /// Boolean attributes are also given values, e.g. `disabled="disabled"`.
+    #[must_use]
+    pub fn self_closing_tags(mut self, enable: bool) -> Self {
+        self.options.self_closing_tags = enable;
+        self
+    }
+
+    /// Choose how softbreaks, i.e. line breaks within paragraphs, are rendered. Newlines are used
+    /// by default.
+    #[must_use]
+    pub fn softbreak(mut self, style: SoftbreakStyle) -> Self {
+        self.options.softbreak = style;
+        self
+    }
+
+    /// Render smart quotes as straight ASCII quotes, i.e. `'` and `"`, instead of HTML entities
+    /// for curly quotes, e.g. `&ldquo;`.
+    #[must_use]
+    pub fn ascii_quotes(mut self, enable: bool) -> Self {
+        self.options.ascii_quotes = enable;
+        self
+    }
+
+    /// Wrap each heading and its content in a `<section>` element, enabled by default.
+    ///
+    /// If disabled, the id and attributes of the section are instead placed on the heading. The
+    /// section of the footnotes is not affected.
+    #[must_use]
+    pub fn sections(mut self, enable: bool) -> Self {
+        self.options.sections = enable;
+        self
+    }
+
+    /// Place a disabled checkbox, i.e. `<input type="checkbox" disabled>`, at the start of each
+    /// task list item.
+    #[must_use]
+    pub fn task_list_checkboxes(mut self, enable: bool) -> Self {
+        self.options.task_list_checkboxes = enable;
+        self
+    }
+
+    /// Group the rows of tables within `<thead>` and `<tbody>` elements.
+    ///
+    /// Header rows at the start of a table are placed in the `<thead>` element, all following
+    /// rows are placed in the `<tbody>` element.
+    #[must_use]
+    pub fn table_row_groups(mut self, enable: bool) -> Self {
+        self.options.table_row_groups = enable;
+        self
+    }
+
+    /// Generate a nested table of contents from the headings of the document.
+    ///
+    /// The table of contents is a `<nav class="toc">` element with a list of links to the
+    /// headings. It is omitted if the document has no headings within the depth. As it requires
+    /// all headings of the document, the events are buffered before they are rendered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// # use jotdown::html::*;
+    /// let src = "::: toc\n:::\n\n# A\n\n## B\n\n### C\n";
+    /// let mut html = String::new();
+    /// Renderer::default()
+    ///     .table_of_contents(TableOfContents {
+    ///         depth: 2,
+    ///         placement: TocPlacement::Marker,
+    ///     })
+    ///     .push(Parser::new(src), &mut html)
+    ///     .unwrap();
+    /// assert!(html.starts_with(concat!(
+    ///     "<nav class=\"toc\">\n",
+    ///     "<ul>\n",
+    ///     "<li><a href=\"#A\">A</a>\n",
+    ///     "<ul>\n",
+    ///     "<li><a href=\"#B\">B</a></li>\n",
+    ///     "</ul>\n",
+    ///     "</li>\n",
+    ///     "</ul>\n",
+    ///     "</nav>\n",
+    ///     "<section id=\"A\">\n",
+    /// )));
+    /// ```
+    #[must_use]
+    pub fn table_of_contents(mut self, toc: TableOfContents) -> Self {
+        self.toc = Some(toc);
+        self
+    }
+
+    /// Wrap the output in a complete HTML5 document, see [`Standalone`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// # use jotdown::html::*;
+    /// let src = "# Hello *world*\n";
+    /// let mut html = String::new();
+    /// Renderer::default()
+    ///     .sections(false)
+    ///     .standalone(Standalone::default().stylesheet("style.css"))
+    ///     .push(Parser::new(src), &mut html)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     html,
+    ///     concat!(
+    ///         "<!DOCTYPE html>\n",
+    ///         "<html>\n",
+    ///         "<head>\n",
+    ///         "<meta charset=\"utf-8\">\n",
+    ///         "<title>Hello world</title>\n",
+    ///         "<link rel=\"stylesheet\" href=\"style.css\">\n",
+    ///         "</head>\n",
+    ///         "<body>\n",
+    ///         "<h1 id=\"Hello-world\">Hello <strong>world</strong></h1>\n",
+    ///         "</body>\n",
+    ///         "</html>\n",
+    ///     ),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn standalone(mut self, standalone: Standalone) -> Self {
+        self.standalone = Some(standalone);
+        self
+    }
+
+    /// Render the events as a fragment, i.e. without a surrounding document.
+    fn push_fragment<'s, E, I, W>(&self, mut events: I, mut out: W) -> std::fmt::Result
+    where
+        E: AsRef<Event<'s>>,
+        I: Iterator<Item = E>,
+        W: std::fmt::Write,
+    {
+        let mut w = Writer::new(self.options);
+        if let Some(toc) = &self.toc {
+            let events = events.collect::<Vec<_>>();
+            let mut nav = String::new();
+            write_toc(
+                events.iter().map(AsRef::as_ref),
+                toc.depth,
+                self.options,
+                self.hooks(),
+                &mut nav,
+            )?;
+            w.toc = Some(nav).filter(|nav| !nav.is_empty());
+            match toc.placement {
+                TocPlacement::Start => w.render_toc(&mut out)?,
+                TocPlacement::Marker => w.toc_marker = true,
+            }
+            events
+                .iter()
+                .try_for_each(|e| w.render_event(self.hooks(), e.as_ref(), &mut out))?;
+        } else {
+            events.try_for_each(|e| w.render_event(self.hooks(), e.as_ref(), &mut out))?;
+        }
+        w.render_epilogue(self.hooks(), &mut out)
+    }
+
+    /// Render the events as a complete document by filling in the placeholders of the template.
+    fn push_document<'s, W>(
+        &self,
+        standalone: &Standalone,
+        events: &[&Event<'s>],
+        mut out: W,
+    ) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        let title = match &standalone.title {
+            Some(title) => title.clone(),
+            None => heading_text(events),
+        };
+        let template = standalone.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let end = match rest[start..].find("}}") {
+                Some(n) => start + n + 2,
+                None => break,
+            };
+            out.write_str(&rest[..start])?;
+            match rest[start + 2..end - 2].trim() {
+                "title" => write_text(&title, &mut out)?,
+                "head" => {
+                    for url in &standalone.stylesheets {
+                        out.write_str(r#"<link rel="stylesheet" href=""#)?;
+                        write_attr(url, &mut out)?;
+                        out.write_char('"')?;
+                        out.write_str(if self.options.self_closing_tags {
+                            " />\n"
+                        } else {
+                            ">\n"
+                        })?;
+                    }
+                    for url in &standalone.scripts {
+                        out.write_str(r#"<script src=""#)?;
+                        write_attr(url, &mut out)?;
+                        out.write_str("\"></script>\n")?;
+                    }
+                }
+                "toc" => write_toc(
+                    events.iter().copied(),
+                    self.toc.unwrap_or_default().depth,
+                    self.options,
+                    self.hooks(),
+                    &mut out,
+                )?,
+                "body" => self.push_fragment(events.iter(), &mut out)?,
+                _ => out.write_str(&rest[start..end])?,
+            }
+            rest = &rest[end..];
+        }
+        out.write_str(rest)
+    }
+}
+
+/// Template of a standalone document that is used by default, see [`Standalone::template`].
+const DEFAULT_TEMPLATE: &str = "\
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{{title}}</title>
+{{head}}</head>
+<body>
+{{body}}</body>
+</html>
+";
+
+/// Extract the plain text of the first heading of a document.
+fn heading_text(events: &[&Event]) -> String {
+    let mut text = String::new();
+    let mut footnote_depth = 0;
+    let mut raw = false;
+    let mut in_heading = false;
+    for e in events {
+        match e {
+            Event::Start(Container::Footnote { .. }, ..) => footnote_depth += 1,
+            Event::End(Container::Footnote { .. }) => footnote_depth -= 1,
+            Event::Start(Container::Heading { .. }, ..) if footnote_depth == 0 => {
+                in_heading = true;
+            }
+            Event::End(Container::Heading { .. }) if in_heading => break,
+            _ if !in_heading => {}
+            Event::Start(Container::RawInline { .. }, ..) => raw = true,
+            Event::End(Container::RawInline { .. }) => raw = false,
+            Event::Str(s) if !raw => text.push_str(s),
+            Event::Symbol(sym) => {
+                text.push(':');
+                text.push_str(sym);
+                text.push(':');
+            }
+            Event::LeftSingleQuote => text.push('‘'),
+            Event::RightSingleQuote => text.push('’'),
+            Event::LeftDoubleQuote => text.push('“'),
+            Event::RightDoubleQuote => text.push('”'),
+            Event::Ellipsis => text.push('…'),
+            Event::EnDash => text.push('–'),
+            Event::EmDash => text.push('—'),
+            Event::NonBreakingSpace => text.push('\u{a0}'),
+            Event::Softbreak | Event::Hardbreak => text.push(' '),
+            _ => {}
+        }
+    }
+    text.trim().to_string()
+}
+
+/// Configuration of a table of contents, see [`Renderer::table_of_contents`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableOfContents {
+    /// Deepest heading level to include, e.g. 2 to only include `#` and `##` headings.
+    pub depth: u16,
+    /// Where to place the table of contents.
+    pub placement: TocPlacement,
+}
+
+impl Default for TableOfContents {
+    fn default() -> Self {
+        Self {
+            depth: 3,
+            placement: TocPlacement::Start,
+        }
+    }
+}
+
+/// Configuration of a standalone document, see [`Renderer::standalone`].
+///
+/// The document is created from a template with placeholders that are replaced by the rendered
+/// content:
+///
+/// - `{{title}}`: the title of the document,
+/// - `{{head}}`: the links to the stylesheets and scripts of the document,
+/// - `{{toc}}`: a table of contents, see [`Renderer::table_of_contents`],
+/// - `{{body}}`: the rendered content of the document.
+///
+/// The default template is a minimal HTML5 document with a `<meta charset="utf-8">` element, a
+/// `<title>` element and the links to stylesheets and scripts in the `<head>`, and the rendered
+/// content in the `<body>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Standalone {
+    title: Option<String>,
+    stylesheets: Vec<String>,
+    scripts: Vec<String>,
+    template: Option<String>,
+}
+
+impl Standalone {
+    /// Set the title of the document, e.g. from metadata of the document. The text of the first
+    /// heading is used by default.
+    #[must_use]
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Add a link to a CSS stylesheet.
+    #[must_use]
+    pub fn stylesheet<S: Into<String>>(mut self, url: S) -> Self {
+        self.stylesheets.push(url.into());
+        self
+    }
+
+    /// Add a link to a JavaScript file.
+    #[must_use]
+    pub fn script<S: Into<String>>(mut self, url: S) -> Self {
+        self.scripts.push(url.into());
+        self
+    }
+
+    /// Use a custom template with placeholders, e.g. `<main>{{body}}</main>`. Unknown
+    /// placeholders are left as is.
+    #[must_use]
+    pub fn template<S: Into<String>>(mut self, template: S) -> Self {
+        self.template = Some(template.into());
+        self
+    }
+}
+
+/// Location of a table of contents within the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TocPlacement {
+    /// Before the first element of the document.
+    Start,
+    /// In place of each div with the `toc` class, e.g. `::: toc` followed by `:::`. The div and
+    /// any content within it is replaced by the table of contents.
+    Marker,
+}
+
+/// Handler that writes the HTML of individual elements for a [`Renderer`].
+///
+/// Each method has a default implementation that writes the same output as the renderer does by
+/// default. Methods may be overridden in order to change the output of specific elements, the
+/// corresponding methods of [`Output`] can then be used to fall back to the default output for
+/// all other elements.
+///
+/// The renderer still takes care of everything surrounding the elements, e.g. footnotes are
+/// collected and rendered at the end of the document, text is escaped and the content of images
+/// is written as alt text.
+///
+/// # Examples
+///
+/// Render divs with the `note` class as `<aside>` elements:
+///
+/// ```
+/// # use jotdown::*;
+/// # use jotdown::html::*;
+/// use std::fmt::Write;
+///
+/// struct Notes;
+///
+/// impl HtmlHandler for Notes {
+///     fn enter<'s>(
+///         &self,
+///         out: &mut Output<'_, 's>,
+///         container: &Container<'s>,
+///         attrs: &Attributes<'s>,
+///     ) -> std::fmt::Result {
+///         match container {
+///             Container::Div { class } if class == "note" => {
+///                 out.write_str("<aside")?;
+///                 out.write_attributes(attrs)?;
+///                 out.write_str(">")
+///             }
+///             c => out.enter(c, attrs),
+///         }
+///     }
+///
+///     fn exit<'s>(
+///         &self,
+///         out: &mut Output<'_, 's>,
+///         container: &Container<'s>,
+///     ) -> std::fmt::Result {
+///         match container {
+///             Container::Div { class } if class == "note" => out.write_str("</aside>"),
+///             c => out.exit(c),
+///         }
+///     }
+/// }
+///
+/// let src = "{#n}\n::: note\n_a_\n:::\n";
+/// let mut html = String::new();
+/// Renderer::default()
+///     .handler(Notes)
+///     .push(Parser::new(src), &mut html)
+///     .unwrap();
+/// assert_eq!(html, "<aside id=\"n\">\n<p><em>a</em></p>\n</aside>\n");
+/// ```
+pub trait HtmlHandler {
+    /// Write the start of a container, e.g. its start tag and attributes.
+    ///
+    /// The content of an image is written as its alt text, so the output for an image must end
+    /// within an open attribute value, e.g. `<img alt="`. Raw blocks and raw inlines are not
+    /// passed to the handler.
+    fn enter<'s>(
+        &self,
+        out: &mut Output<'_, 's>,
+        container: &Container<'s>,
+        attrs: &Attributes<'s>,
+    ) -> std::fmt::Result {
+        out.enter(container, attrs)
+    }
+
+    /// Write the end of a container, e.g. its end tag.
+    fn exit<'s>(&self, out: &mut Output<'_, 's>, container: &Container<'s>) -> std::fmt::Result {
+        out.exit(container)
+    }
+
+    /// Write a symbol, e.g. `:sym:`.
+    fn symbol<'s>(&self, out: &mut Output<'_, 's>, sym: &str) -> std::fmt::Result {
+        out.symbol(sym)
+    }
+
+    /// Write a reference to a footnote, with the number of the footnote within the document.
+    fn footnote_reference<'s>(
+        &self,
+        out: &mut Output<'_, 's>,
+        label: &str,
+        number: usize,
+    ) -> std::fmt::Result {
+        out.footnote_reference(label, number)
+    }
+
+    /// Write a thematic break, e.g. `<hr>`.
+    fn thematic_break<'s>(
+        &self,
+        out: &mut Output<'_, 's>,
+        attrs: &Attributes<'s>,
+    ) -> std::fmt::Result {
+        out.thematic_break(attrs)
+    }
+}
+
+/// Hook that renders the content of code blocks and inline verbatim, see
+/// [`Renderer::highlighter`].
+///
+/// It is implemented for all closures with the same signature as [`Highlighter::highlight`].
+pub trait Highlighter {
+    /// Render code as HTML, to be placed within a `<code>` element.
+    ///
+    /// The language is the language of a code block, or the first class of inline verbatim, e.g.
+    /// `rust` for `` `x`{.rust} ``. If `None` is returned, the code is rendered as plain text.
+    fn highlight(&self, language: &str, attrs: &Attributes, code: &str) -> Option<String>;
+}
+
+impl<F> Highlighter for F
+where
+    F: Fn(&str, &Attributes, &str) -> Option<String>,
+{
+    fn highlight(&self, language: &str, attrs: &Attributes, code: &str) -> Option<String> {
+        self(language, attrs, code)
+    }
+}
+
+/// Handler, highlighter and sanitizer of a [`Renderer`].
+#[derive(Clone, Copy)]
+struct Hooks<'r> {
+    handler: &'r dyn HtmlHandler,
+    highlighter: Option<&'r dyn Highlighter>,
+    sanitizer: Option<&'r Sanitizer>,
+}
 
-impl Render for Renderer {
+/// [`HtmlHandler`] that writes the default output for all elements.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultHandler;
+
+impl HtmlHandler for DefaultHandler {}
+
+/// Output of a [`Renderer`], passed to an [`HtmlHandler`].
+///
+/// It implements [`std::fmt::Write`] for writing raw HTML and has methods for writing escaped
+/// text and the default output of each element.
+pub struct Output<'a, 's> {
+    writer: &'a mut Writer<'s>,
+    out: &'a mut dyn std::fmt::Write,
+}
+
+impl<'a, 's> Output<'a, 's> {
+    fn new(writer: &'a mut Writer<'s>, out: &'a mut dyn std::fmt::Write) -> Self {
+        Self { writer, out }
+    }
+
+    /// Write text, with `<`, `>` and `&` escaped.
+    pub fn write_text(&mut self, s: &str) -> std::fmt::Result {
+        write_text(s, &mut self.out)
+    }
+
+    /// Write the value of an attribute, with `<`, `>`, `&` and `"` escaped.
+    pub fn write_attr(&mut self, s: &str) -> std::fmt::Result {
+        write_attr(s, &mut self.out)
+    }
+
+    /// Write attributes within a start tag, e.g. ` id="a" class="b c"`.
+    ///
+    /// All classes are combined into a single `class` attribute.
+    pub fn write_attributes(&mut self, attrs: &Attributes<'s>) -> std::fmt::Result {
+        for (a, v) in attrs.iter().filter(|(a, _)| *a != "class") {
+            write!(self.out, r#" {}=""#, a)?;
+            v.parts()
+                .try_for_each(|part| write_attr(part, &mut self.out))?;
+            self.out.write_char('"')?;
+        }
+        if attrs.iter().any(|(a, _)| a == "class") {
+            self.out.write_str(r#" class=""#)?;
+            for (i, (_, cls)) in attrs.iter().filter(|(a, _)| *a == "class").enumerate() {
+                if i > 0 {
+                    self.out.write_char(' ')?;
+                }
+                cls.parts()
+                    .try_for_each(|part| write_attr(part, &mut self.out))?;
+            }
+            self.out.write_char('"')?;
+        }
+        Ok(())
+    }
+
+    /// Write the default start of a container.
+    pub fn enter(&mut self, container: &Container<'s>, attrs: &Attributes<'s>) -> std::fmt::Result {
+        self.writer.render_enter(container, attrs, &mut self.out)
+    }
+
+    /// Write the default end of a container.
+    pub fn exit(&mut self, container: &Container<'s>) -> std::fmt::Result {
+        self.writer.render_exit(container, &mut self.out)
+    }
+
+    /// Write the default output of a symbol.
+    pub fn symbol(&mut self, sym: &str) -> std::fmt::Result {
+        write!(self.out, ":{}:", sym)
+    }
+
+    /// Write the default output of a footnote reference.
+    pub fn footnote_reference(&mut self, _label: &str, number: usize) -> std::fmt::Result {
+        self.writer.render_footnote_reference(number, &mut self.out)
+    }
+
+    /// Write the default output of a thematic break.
+    pub fn thematic_break(&mut self, attrs: &Attributes<'s>) -> std::fmt::Result {
+        self.writer.render_thematic_break(attrs, &mut self.out)
+    }
+}
+
+impl<'a, 's> std::fmt::Write for Output<'a, 's> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.out.write_str(s)
+    }
+}
+
+impl<H: HtmlHandler> Render for Renderer<H> {
     fn push<'s, I, W>(&self, mut events: I, mut out: W) -> std::fmt::Result
     where
         I: Iterator<Item = Event<'s>>,
         W: std::fmt::Write,
     {
-        let mut w = Writer::default();
-        events.try_for_each(|e| w.render_event(&e, &mut out))?;
-        w.render_epilogue(&mut out)
+        if self.toc.is_some() || self.standalone.is_some() {
+            let events = events.collect::<Vec<_>>();
+            return self.push_borrowed(events.iter(), out);
+        }
+
+        let mut w = Writer::new(self.options);
+        events.try_for_each(|e| w.render_event(self.hooks(), &e, &mut out))?;
+        w.render_epilogue(self.hooks(), &mut out)
     }
 
-    fn push_borrowed<'s, E, I, W>(&self, mut events: I, mut out: W) -> std::fmt::Result
+    fn push_borrowed<'s, E, I, W>(&self, events: I, out: W) -> std::fmt::Result
     where
         E: AsRef<Event<'s>>,
         I: Iterator<Item = E>,
         W: std::fmt::Write,
     {
-        let mut w = Writer::default();
-        events.try_for_each(|e| w.render_event(e.as_ref(), &mut out))?;
-        w.render_epilogue(&mut out)
+        if let Some(standalone) = &self.standalone {
+            let events = events.collect::<Vec<_>>();
+            let events = events.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+            return self.push_document(standalone, &events, out);
+        }
+        self.push_fragment(events, out)
     }
 }
 
@@ -49,6 +983,12 @@ impl Default for Raw {
     }
 }
 
+/// A row group of a table, i.e. `<thead>` or `<tbody>`.
+enum RowGroup {
+    Head,
+    Body,
+}
+
 #[derive(Default)]
 struct Writer<'s> {
     raw: Raw,
@@ -57,15 +997,38 @@ struct Writer<'s> {
     not_first_line: bool,
     ignore: bool,
     footnotes: Footnotes<'s>,
+    /// Rendered table of contents, if enabled.
+    toc: Option<String>,
+    /// Place the table of contents at `toc` divs.
+    toc_marker: bool,
+    /// Nesting depth of divs within a `toc` div that is being replaced.
+    toc_marker_depth: usize,
+    options: Options,
+    /// Attributes of a section that is not rendered, to be placed on its heading.
+    section_attrs: Option<Attributes<'s>>,
+    /// Checkbox of a task list item that has not yet been rendered, and whether it is checked.
+    checkbox: Option<bool>,
+    /// Row group of the current table that is open.
+    row_group: Option<RowGroup>,
+    /// Attributes and buffered content of code that is to be highlighted or split into lines, or
+    /// of math that is to be converted to MathML.
+    code: Option<(Attributes<'s>, String)>,
 }
 
 impl<'s> Writer<'s> {
-    fn render_event<W>(&mut self, e: &Event<'s>, mut out: W) -> std::fmt::Result
+    fn new(options: Options) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    fn render_event<W>(&mut self, h: Hooks, e: &Event<'s>, mut out: W) -> std::fmt::Result
     where
         W: std::fmt::Write,
     {
         if let Event::Start(Container::Footnote { label }, ..) = e {
-            self.footnotes.start(label, Vec::new());
+            self.footnotes.start(label.clone(), Vec::new());
             return Ok(());
         } else if let Some(events) = self.footnotes.current() {
             if matches!(e, Event::End(Container::Footnote { .. })) {
@@ -90,273 +1053,162 @@ impl<'s> Writer<'s> {
             return Ok(());
         }
 
+        if self.toc_marker_depth > 0 {
+            match e {
+                Event::Start(Container::Div { .. }, ..) => self.toc_marker_depth += 1,
+                Event::End(Container::Div { .. }) => self.toc_marker_depth -= 1,
+                _ => {}
+            }
+            return Ok(());
+        } else if self.toc_marker {
+            if let Event::Start(Container::Div { class }, ..) = e {
+                if class == "toc" {
+                    self.toc_marker_depth = 1;
+                    return self.render_toc(out);
+                }
+            }
+        }
+
+        if !self.options.sections {
+            match e {
+                Event::Start(Container::Section { .. }, attrs) => {
+                    self.section_attrs = Some(attrs.clone());
+                    return Ok(());
+                }
+                Event::End(Container::Section { .. }) => return Ok(()),
+                _ => {}
+            }
+        }
+
+        if let Some(checked) = self.checkbox {
+            if !matches!(e, Event::Start(Container::Paragraph, ..)) {
+                self.checkbox = None;
+                self.render_checkbox(checked, &mut out)?;
+            }
+        }
+
         match e {
             Event::Start(c, attrs) => {
                 if c.is_block() && self.not_first_line {
                     out.write_char('\n')?;
                 }
+                let section_attrs;
+                let attrs = match self.section_attrs.take() {
+                    Some(mut sa) if matches!(c, Container::Heading { .. }) => {
+                        sa.union(attrs.clone());
+                        section_attrs = sa;
+                        &section_attrs
+                    }
+                    _ => attrs,
+                };
+                let sanitized;
+                let (c, attrs) = if let Some(s) = h.sanitizer {
+                    sanitized = (s.container(c), s.attributes_of(attrs));
+                    (&sanitized.0, &sanitized.1)
+                } else {
+                    (c, attrs)
+                };
                 if self.img_alt_text > 0 && !matches!(c, Container::Image(..)) {
                     return Ok(());
                 }
-                match &c {
-                    Container::Blockquote => out.write_str("<blockquote")?,
-                    Container::List { kind, tight } => {
-                        self.list_tightness.push(*tight);
-                        match kind {
-                            ListKind::Unordered | ListKind::Task => out.write_str("<ul")?,
-                            ListKind::Ordered {
-                                numbering, start, ..
-                            } => {
-                                out.write_str("<ol")?;
-                                if *start > 1 {
-                                    write!(out, r#" start="{}""#, start)?;
-                                }
-                                if let Some(ty) = match numbering {
-                                    Decimal => None,
-                                    AlphaLower => Some('a'),
-                                    AlphaUpper => Some('A'),
-                                    RomanLower => Some('i'),
-                                    RomanUpper => Some('I'),
-                                } {
-                                    write!(out, r#" type="{}""#, ty)?;
-                                }
-                            }
-                        }
-                    }
-                    Container::ListItem | Container::TaskListItem { .. } => {
-                        out.write_str("<li")?;
-                    }
-                    Container::DescriptionList => out.write_str("<dl")?,
-                    Container::DescriptionDetails => out.write_str("<dd")?,
-                    Container::Footnote { .. } => unreachable!(),
-                    Container::Table => out.write_str("<table")?,
-                    Container::TableRow { .. } => out.write_str("<tr")?,
-                    Container::Section { .. } => out.write_str("<section")?,
-                    Container::Div { .. } => out.write_str("<div")?,
-                    Container::Paragraph => {
-                        if matches!(self.list_tightness.last(), Some(true)) {
-                            return Ok(());
-                        }
-                        out.write_str("<p")?;
-                    }
-                    Container::Heading { level, .. } => write!(out, "<h{}", level)?,
-                    Container::TableCell { head: false, .. } => out.write_str("<td")?,
-                    Container::TableCell { head: true, .. } => out.write_str("<th")?,
-                    Container::Caption => out.write_str("<caption")?,
-                    Container::DescriptionTerm => out.write_str("<dt")?,
-                    Container::CodeBlock { .. } => out.write_str("<pre")?,
-                    Container::Span | Container::Math { .. } => out.write_str("<span")?,
-                    Container::Link(dst, ty) => {
-                        if matches!(ty, LinkType::Span(SpanLinkType::Unresolved)) {
-                            out.write_str("<a")?;
-                        } else {
-                            out.write_str(r#"<a href=""#)?;
-                            if matches!(ty, LinkType::Email) {
-                                out.write_str("mailto:")?;
-                            }
-                            write_attr(dst, &mut out)?;
-                            out.write_char('"')?;
-                        }
+                let code_block_lines =
+                    matches!(c, Container::CodeBlock { .. }) && self.options.code_block_lines;
+                let mathml = matches!(c, Container::Math { .. }) && self.options.mathml;
+                if code_block_lines
+                    || mathml
+                    || (h.highlighter.is_some()
+                        && matches!(c, Container::CodeBlock { .. } | Container::Verbatim))
+                {
+                    self.code = Some((attrs.clone(), String::new()));
+                }
+                let code_attrs;
+                let attrs = if code_block_lines && attrs.get("highlight").is_some() {
+                    let mut a = attrs.clone();
+                    a.remove("highlight");
+                    code_attrs = a;
+                    &code_attrs
+                } else {
+                    attrs
+                };
+                match c {
+                    Container::List { tight, .. } => self.list_tightness.push(*tight),
+                    Container::TaskListItem { checked } if self.options.task_list_checkboxes => {
+                        self.checkbox = Some(*checked);
                     }
                     Container::Image(..) => {
                         self.img_alt_text += 1;
-                        if self.img_alt_text == 1 {
-                            out.write_str("<img")?;
-                        } else {
+                        if self.img_alt_text > 1 {
                             return Ok(());
                         }
                     }
-                    Container::Verbatim => out.write_str("<code")?,
                     Container::RawBlock { format } | Container::RawInline { format } => {
-                        self.raw = if format == &"html" {
-                            Raw::Html
-                        } else {
-                            Raw::Other
+                        self.raw = match h.sanitizer {
+                            _ if format != "html" => Raw::Other,
+                            None => Raw::Html,
+                            Some(s) if s.raw_html == RawHtml::Escape => Raw::None,
+                            Some(_) => Raw::Other,
                         };
                         return Ok(());
                     }
-                    Container::Subscript => out.write_str("<sub")?,
-                    Container::Superscript => out.write_str("<sup")?,
-                    Container::Insert => out.write_str("<ins")?,
-                    Container::Delete => out.write_str("<del")?,
-                    Container::Strong => out.write_str("<strong")?,
-                    Container::Emphasis => out.write_str("<em")?,
-                    Container::Mark => out.write_str("<mark")?,
-                    Container::LinkDefinition { .. } => return Ok(()),
-                }
-
-                for (a, v) in attrs.iter().filter(|(a, _)| *a != "class") {
-                    write!(out, r#" {}=""#, a)?;
-                    v.parts().try_for_each(|part| write_attr(part, &mut out))?;
-                    out.write_char('"')?;
-                }
-
-                if let Container::Heading {
-                    id,
-                    has_section: false,
-                    ..
-                }
-                | Container::Section { id } = &c
-                {
-                    if !attrs.iter().any(|(a, _)| a == "id") {
-                        out.write_str(r#" id=""#)?;
-                        write_attr(id, &mut out)?;
-                        out.write_char('"')?;
-                    }
+                    _ => {}
                 }
-
-                if attrs.iter().any(|(a, _)| a == "class")
-                    || matches!(
-                        c,
-                        Container::Div { class } if !class.is_empty())
-                    || matches!(c, |Container::Math { .. }| Container::List {
-                        kind: ListKind::Task,
-                        ..
-                    } | Container::TaskListItem { .. })
-                {
-                    out.write_str(r#" class=""#)?;
-                    let mut first_written = false;
-                    if let Some(cls) = match c {
-                        Container::List {
-                            kind: ListKind::Task,
-                            ..
-                        } => Some("task-list"),
-                        Container::TaskListItem { checked: false } => Some("unchecked"),
-                        Container::TaskListItem { checked: true } => Some("checked"),
-                        Container::Math { display: false } => Some("math inline"),
-                        Container::Math { display: true } => Some("math display"),
-                        _ => None,
-                    } {
-                        first_written = true;
-                        out.write_str(cls)?;
-                    }
-                    for cls in attrs
-                        .iter()
-                        .filter(|(a, _)| a == &"class")
-                        .map(|(_, cls)| cls)
-                    {
-                        if first_written {
-                            out.write_char(' ')?;
-                        }
-                        first_written = true;
-                        cls.parts()
-                            .try_for_each(|part| write_attr(part, &mut out))?;
-                    }
-                    // div class goes after classes from attrs
-                    if let Container::Div { class } = c {
-                        if !class.is_empty() {
-                            if first_written {
-                                out.write_char(' ')?;
-                            }
-                            out.write_str(class)?;
-                        }
-                    }
-                    out.write_char('"')?;
+                if mathml {
+                    return Ok(());
                 }
+                h.handler
+                    .enter(&mut Output::new(self, &mut out), c, attrs)?;
 
-                match c {
-                    Container::TableCell { alignment, .. }
-                        if !matches!(alignment, Alignment::Unspecified) =>
-                    {
-                        let a = match alignment {
-                            Alignment::Unspecified => unreachable!(),
-                            Alignment::Left => "left",
-                            Alignment::Center => "center",
-                            Alignment::Right => "right",
-                        };
-                        write!(out, r#" style="text-align: {};">"#, a)?;
+                if let Some(checked) = self.checkbox {
+                    if matches!(c, Container::Paragraph) {
+                        self.checkbox = None;
+                        self.render_checkbox(checked, &mut out)?;
+                        out.write_char(' ')?;
                     }
-                    Container::CodeBlock { language } => {
-                        if language.is_empty() {
-                            out.write_str("><code>")?;
-                        } else {
-                            out.write_str(r#"><code class="language-"#)?;
-                            write_attr(language, &mut out)?;
-                            out.write_str(r#"">"#)?;
-                        }
-                    }
-                    Container::Image(..) => {
-                        if self.img_alt_text == 1 {
-                            out.write_str(r#" alt=""#)?;
-                        }
-                    }
-                    Container::Math { display } => {
-                        out.write_str(if *display { r#">\["# } else { r#">\("# })?;
-                    }
-                    _ => out.write_char('>')?,
                 }
             }
             Event::End(c) => {
                 if c.is_block_container() {
                     out.write_char('\n')?;
                 }
+                let sanitized;
+                let c = if let Some(s) = h.sanitizer {
+                    sanitized = s.container(c);
+                    &sanitized
+                } else {
+                    c
+                };
                 if self.img_alt_text > 0 && !matches!(c, Container::Image(..)) {
                     return Ok(());
                 }
                 match c {
-                    Container::Blockquote => out.write_str("</blockquote>")?,
-                    Container::List { kind, .. } => {
-                        self.list_tightness.pop();
-                        match kind {
-                            ListKind::Unordered | ListKind::Task => out.write_str("</ul>")?,
-                            ListKind::Ordered { .. } => out.write_str("</ol>")?,
-                        }
-                    }
-                    Container::ListItem | Container::TaskListItem { .. } => {
-                        out.write_str("</li>")?;
-                    }
-                    Container::DescriptionList => out.write_str("</dl>")?,
-                    Container::DescriptionDetails => out.write_str("</dd>")?,
-                    Container::Footnote { .. } => unreachable!(),
-                    Container::Table => out.write_str("</table>")?,
-                    Container::TableRow { .. } => out.write_str("</tr>")?,
-                    Container::Section { .. } => out.write_str("</section>")?,
-                    Container::Div { .. } => out.write_str("</div>")?,
-                    Container::Paragraph => {
-                        if matches!(self.list_tightness.last(), Some(true)) {
-                            return Ok(());
-                        }
-                        if !self.footnotes.in_epilogue() {
-                            out.write_str("</p>")?;
-                        }
-                    }
-                    Container::Heading { level, .. } => write!(out, "</h{}>", level)?,
-                    Container::TableCell { head: false, .. } => out.write_str("</td>")?,
-                    Container::TableCell { head: true, .. } => out.write_str("</th>")?,
-                    Container::Caption => out.write_str("</caption>")?,
-                    Container::DescriptionTerm => out.write_str("</dt>")?,
-                    Container::CodeBlock { .. } => out.write_str("</code></pre>")?,
-                    Container::Span => out.write_str("</span>")?,
-                    Container::Link(..) => out.write_str("</a>")?,
-                    Container::Image(src, ..) => {
-                        if self.img_alt_text == 1 {
-                            if !src.is_empty() {
-                                out.write_str(r#"" src=""#)?;
-                                write_attr(src, &mut out)?;
-                            }
-                            out.write_str(r#"">"#)?;
-                        }
+                    Container::Image(..) if self.img_alt_text > 1 => {
                         self.img_alt_text -= 1;
-                    }
-                    Container::Verbatim => out.write_str("</code>")?,
-                    Container::Math { display } => {
-                        out.write_str(if *display {
-                            r#"\]</span>"#
-                        } else {
-                            r#"\)</span>"#
-                        })?;
+                        return Ok(());
                     }
                     Container::RawBlock { .. } | Container::RawInline { .. } => {
                         self.raw = Raw::None;
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+                if let Some((attrs, code)) = self.code.take() {
+                    if let Container::Math { display } = c {
+                        return self.render_math(h, *display, &attrs, &code, &mut out);
+                    }
+                    self.render_code(h, c, &attrs, &code, &mut out)?;
+                }
+                h.handler.exit(&mut Output::new(self, &mut out), c)?;
+                match c {
+                    Container::List { .. } => {
+                        self.list_tightness.pop();
                     }
-                    Container::Subscript => out.write_str("</sub>")?,
-                    Container::Superscript => out.write_str("</sup>")?,
-                    Container::Insert => out.write_str("</ins>")?,
-                    Container::Delete => out.write_str("</del>")?,
-                    Container::Strong => out.write_str("</strong>")?,
-                    Container::Emphasis => out.write_str("</em>")?,
-                    Container::Mark => out.write_str("</mark>")?,
-                    Container::LinkDefinition { .. } => unreachable!(),
+                    Container::Image(..) => self.img_alt_text -= 1,
+                    _ => {}
+                }
+            }
+            Event::Str(s) if self.code.is_some() => {
+                if let Some((_, code)) = &mut self.code {
+                    code.push_str(s);
                 }
             }
             Event::Str(s) => match self.raw {
@@ -368,14 +1220,24 @@ impl<'s> Writer<'s> {
             Event::FootnoteReference(label) => {
                 let number = self.footnotes.reference(label);
                 if self.img_alt_text == 0 {
-                    write!(
-                        out,
-                        r##"<a id="fnref{}" href="#fn{}" role="doc-noteref"><sup>{}</sup></a>"##,
-                        number, number, number
+                    h.handler.footnote_reference(
+                        &mut Output::new(self, &mut out),
+                        label,
+                        number,
                     )?;
                 }
             }
-            Event::Symbol(sym) => write!(out, ":{}:", sym)?,
+            Event::Symbol(sym) => h.handler.symbol(&mut Output::new(self, &mut out), sym)?,
+            Event::LeftSingleQuote | Event::RightSingleQuote if self.options.ascii_quotes => {
+                out.write_char('\'')?;
+            }
+            Event::LeftDoubleQuote | Event::RightDoubleQuote if self.options.ascii_quotes => {
+                out.write_str(if self.img_alt_text > 0 {
+                    "&quot;"
+                } else {
+                    "\""
+                })?;
+            }
             Event::LeftSingleQuote => out.write_str("&lsquo;")?,
             Event::RightSingleQuote => out.write_str("&rsquo;")?,
             Event::LeftDoubleQuote => out.write_str("&ldquo;")?,
@@ -384,33 +1246,478 @@ impl<'s> Writer<'s> {
             Event::EnDash => out.write_str("&ndash;")?,
             Event::EmDash => out.write_str("&mdash;")?,
             Event::NonBreakingSpace => out.write_str("&nbsp;")?,
-            Event::Hardbreak => out.write_str("<br>\n")?,
-            Event::Softbreak => out.write_char('\n')?,
+            Event::Hardbreak => {
+                out.write_str("<br")?;
+                self.render_tag_end(&mut out)?;
+                out.write_char('\n')?;
+            }
+            Event::Softbreak => match self.options.softbreak {
+                SoftbreakStyle::Space => out.write_char(' ')?,
+                SoftbreakStyle::Break if self.img_alt_text == 0 => {
+                    out.write_str("<br")?;
+                    self.render_tag_end(&mut out)?;
+                    out.write_char('\n')?;
+                }
+                SoftbreakStyle::Newline | SoftbreakStyle::Break => out.write_char('\n')?,
+            },
             Event::Escape | Event::Blankline => {}
             Event::ThematicBreak(attrs) => {
                 if self.not_first_line {
                     out.write_char('\n')?;
                 }
-                out.write_str("<hr")?;
-                for (a, v) in attrs.iter() {
-                    write!(out, r#" {}=""#, a)?;
-                    v.parts().try_for_each(|part| write_attr(part, &mut out))?;
+                let sanitized;
+                let attrs = if let Some(s) = h.sanitizer {
+                    sanitized = s.attributes_of(attrs);
+                    &sanitized
+                } else {
+                    attrs
+                };
+                h.handler
+                    .thematic_break(&mut Output::new(self, &mut out), attrs)?;
+            }
+        }
+        self.not_first_line = true;
+
+        Ok(())
+    }
+
+    /// Write the start tag of a container.
+    fn render_enter<W>(
+        &mut self,
+        c: &Container<'s>,
+        attrs: &Attributes<'s>,
+        mut out: W,
+    ) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        match c {
+            Container::Blockquote => out.write_str("<blockquote")?,
+            Container::List { kind, .. } => match kind {
+                ListKind::Unordered | ListKind::Task => out.write_str("<ul")?,
+                ListKind::Ordered {
+                    numbering, start, ..
+                } => {
+                    out.write_str("<ol")?;
+                    if *start > 1 {
+                        write!(out, r#" start="{}""#, start)?;
+                    }
+                    if let Some(ty) = match numbering {
+                        Decimal => None,
+                        AlphaLower => Some('a'),
+                        AlphaUpper => Some('A'),
+                        RomanLower => Some('i'),
+                        RomanUpper => Some('I'),
+                    } {
+                        write!(out, r#" type="{}""#, ty)?;
+                    }
+                }
+            },
+            Container::ListItem | Container::TaskListItem { .. } => out.write_str("<li")?,
+            Container::DescriptionList => out.write_str("<dl")?,
+            Container::DescriptionDetails => out.write_str("<dd")?,
+            Container::Footnote { .. } => unreachable!(),
+            Container::Table => out.write_str("<table")?,
+            Container::TableRow { head } => {
+                if self.options.table_row_groups {
+                    match self.row_group {
+                        None if *head => {
+                            self.row_group = Some(RowGroup::Head);
+                            out.write_str("<thead>\n")?;
+                        }
+                        None => {
+                            self.row_group = Some(RowGroup::Body);
+                            out.write_str("<tbody>\n")?;
+                        }
+                        Some(RowGroup::Head) if !*head => {
+                            self.row_group = Some(RowGroup::Body);
+                            out.write_str("</thead>\n<tbody>\n")?;
+                        }
+                        Some(RowGroup::Head | RowGroup::Body) => {}
+                    }
+                }
+                out.write_str("<tr")?;
+            }
+            Container::Section { .. } => out.write_str("<section")?,
+            Container::Div { .. } => out.write_str("<div")?,
+            Container::Paragraph => {
+                if matches!(self.list_tightness.last(), Some(true)) {
+                    return Ok(());
+                }
+                out.write_str("<p")?;
+            }
+            Container::Heading { level, .. } => write!(out, "<h{}", level)?,
+            Container::TableCell { head: false, .. } => out.write_str("<td")?,
+            Container::TableCell { head: true, .. } => out.write_str("<th")?,
+            Container::Caption => out.write_str("<caption")?,
+            Container::DescriptionTerm => out.write_str("<dt")?,
+            Container::CodeBlock { .. } => out.write_str("<pre")?,
+            Container::Span | Container::Math { .. } => out.write_str("<span")?,
+            Container::Link(dst, ty) => {
+                if matches!(ty, LinkType::Span(SpanLinkType::Unresolved)) {
+                    out.write_str("<a")?;
+                } else {
+                    out.write_str(r#"<a href=""#)?;
+                    if matches!(ty, LinkType::Email) {
+                        out.write_str("mailto:")?;
+                    }
+                    write_attr(dst, &mut out)?;
                     out.write_char('"')?;
                 }
-                out.write_str(">")?;
             }
+            Container::Image(..) => out.write_str("<img")?,
+            Container::Verbatim => out.write_str("<code")?,
+            Container::RawBlock { .. } | Container::RawInline { .. } => return Ok(()),
+            Container::Subscript => out.write_str("<sub")?,
+            Container::Superscript => out.write_str("<sup")?,
+            Container::Insert => out.write_str("<ins")?,
+            Container::Delete => out.write_str("<del")?,
+            Container::Strong => out.write_str("<strong")?,
+            Container::Emphasis => out.write_str("<em")?,
+            Container::Mark => out.write_str("<mark")?,
+            Container::LinkDefinition { .. } => return Ok(()),
         }
-        self.not_first_line = true;
 
+        for (a, v) in attrs.iter().filter(|(a, _)| *a != "class") {
+            write!(out, r#" {}=""#, a)?;
+            v.parts().try_for_each(|part| write_attr(part, &mut out))?;
+            out.write_char('"')?;
+        }
+
+        if let Container::Heading {
+            id, has_section, ..
+        } = c
+        {
+            if !id.is_empty()
+                && (!has_section || !self.options.sections)
+                && !attrs.iter().any(|(a, _)| a == "id")
+            {
+                out.write_str(r#" id=""#)?;
+                write_attr(id, &mut out)?;
+                out.write_char('"')?;
+            }
+        }
+        if let Container::Section { id } = c {
+            if !id.is_empty() && !attrs.iter().any(|(a, _)| a == "id") {
+                out.write_str(r#" id=""#)?;
+                write_attr(id, &mut out)?;
+                out.write_char('"')?;
+            }
+        }
+
+        if attrs.iter().any(|(a, _)| a == "class")
+            || matches!(
+                c,
+                Container::Div { class } if !class.is_empty())
+            || matches!(c, |Container::Math { .. }| Container::List {
+                kind: ListKind::Task,
+                ..
+            } | Container::TaskListItem { .. })
+        {
+            out.write_str(r#" class=""#)?;
+            let mut first_written = false;
+            if let Some(cls) = match c {
+                Container::List {
+                    kind: ListKind::Task,
+                    ..
+                } => Some("task-list"),
+                Container::TaskListItem { checked: false } => Some("unchecked"),
+                Container::TaskListItem { checked: true } => Some("checked"),
+                Container::Math { display: false } => Some("math inline"),
+                Container::Math { display: true } => Some("math display"),
+                _ => None,
+            } {
+                first_written = true;
+                out.write_str(cls)?;
+            }
+            for cls in attrs
+                .iter()
+                .filter(|(a, _)| a == &"class")
+                .map(|(_, cls)| cls)
+            {
+                if first_written {
+                    out.write_char(' ')?;
+                }
+                first_written = true;
+                cls.parts()
+                    .try_for_each(|part| write_attr(part, &mut out))?;
+            }
+            // div class goes after classes from attrs
+            if let Container::Div { class } = c {
+                if !class.is_empty() {
+                    if first_written {
+                        out.write_char(' ')?;
+                    }
+                    out.write_str(class)?;
+                }
+            }
+            out.write_char('"')?;
+        }
+
+        match c {
+            Container::TableCell { alignment, .. }
+                if !matches!(alignment, Alignment::Unspecified) =>
+            {
+                let a = match alignment {
+                    Alignment::Unspecified => unreachable!(),
+                    Alignment::Left => "left",
+                    Alignment::Center => "center",
+                    Alignment::Right => "right",
+                };
+                write!(out, r#" style="text-align: {};">"#, a)?;
+            }
+            Container::CodeBlock { language } => {
+                if language.is_empty() {
+                    out.write_str("><code>")?;
+                } else {
+                    out.write_str(r#"><code class="language-"#)?;
+                    write_attr(language, &mut out)?;
+                    out.write_str(r#"">"#)?;
+                }
+            }
+            Container::Image(..) => out.write_str(r#" alt=""#)?,
+            Container::Math { display } => {
+                out.write_str(if *display { r#">\["# } else { r#">\("# })?;
+            }
+            _ => out.write_char('>')?,
+        }
+
+        Ok(())
+    }
+
+    /// Write the end tag of a container.
+    fn render_exit<W>(&mut self, c: &Container<'s>, mut out: W) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        match c {
+            Container::Blockquote => out.write_str("</blockquote>")?,
+            Container::List { kind, .. } => match kind {
+                ListKind::Unordered | ListKind::Task => out.write_str("</ul>")?,
+                ListKind::Ordered { .. } => out.write_str("</ol>")?,
+            },
+            Container::ListItem | Container::TaskListItem { .. } => {
+                out.write_str("</li>")?;
+            }
+            Container::DescriptionList => out.write_str("</dl>")?,
+            Container::DescriptionDetails => out.write_str("</dd>")?,
+            Container::Footnote { .. } => unreachable!(),
+            Container::Table => {
+                match self.row_group.take() {
+                    None => {}
+                    Some(RowGroup::Head) => out.write_str("</thead>\n")?,
+                    Some(RowGroup::Body) => out.write_str("</tbody>\n")?,
+                }
+                out.write_str("</table>")?;
+            }
+            Container::TableRow { .. } => out.write_str("</tr>")?,
+            Container::Section { .. } => out.write_str("</section>")?,
+            Container::Div { .. } => out.write_str("</div>")?,
+            Container::Paragraph => {
+                if matches!(self.list_tightness.last(), Some(true)) {
+                    return Ok(());
+                }
+                if !self.footnotes.in_epilogue() {
+                    out.write_str("</p>")?;
+                }
+            }
+            Container::Heading { level, .. } => write!(out, "</h{}>", level)?,
+            Container::TableCell { head: false, .. } => out.write_str("</td>")?,
+            Container::TableCell { head: true, .. } => out.write_str("</th>")?,
+            Container::Caption => out.write_str("</caption>")?,
+            Container::DescriptionTerm => out.write_str("</dt>")?,
+            Container::CodeBlock { .. } => out.write_str("</code></pre>")?,
+            Container::Span => out.write_str("</span>")?,
+            Container::Link(..) => out.write_str("</a>")?,
+            Container::Image(src, ..) => {
+                if !src.is_empty() {
+                    out.write_str(r#"" src=""#)?;
+                    write_attr(src, &mut out)?;
+                }
+                out.write_char('"')?;
+                self.render_tag_end(&mut out)?;
+            }
+            Container::Verbatim => out.write_str("</code>")?,
+            Container::Math { display } => {
+                out.write_str(if *display {
+                    r#"\]</span>"#
+                } else {
+                    r#"\)</span>"#
+                })?;
+            }
+            Container::RawBlock { .. } | Container::RawInline { .. } => {}
+            Container::Subscript => out.write_str("</sub>")?,
+            Container::Superscript => out.write_str("</sup>")?,
+            Container::Insert => out.write_str("</ins>")?,
+            Container::Delete => out.write_str("</del>")?,
+            Container::Strong => out.write_str("</strong>")?,
+            Container::Emphasis => out.write_str("</em>")?,
+            Container::Mark => out.write_str("</mark>")?,
+            Container::LinkDefinition { .. } => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Write buffered code, highlighted and split into lines if enabled.
+    fn render_code<W>(
+        &mut self,
+        h: Hooks,
+        c: &Container<'s>,
+        attrs: &Attributes<'s>,
+        code: &str,
+        mut out: W,
+    ) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        let language = match c {
+            Container::CodeBlock { language } => language,
+            _ => attrs.classes().next().unwrap_or(""),
+        };
+        let html = h
+            .highlighter
+            .and_then(|l| l.highlight(language, attrs, code));
+
+        let lines = matches!(c, Container::CodeBlock { .. }) && self.options.code_block_lines;
+        let numbers = lines && attrs.has_class("line-numbers");
+        let highlighted = match attrs.get("highlight") {
+            Some(v) if lines => line_ranges(&v.to_string()),
+            _ => Vec::new(),
+        };
+        if !numbers && highlighted.is_empty() {
+            return match html {
+                Some(html) => out.write_str(&html),
+                None => write_text(code, out),
+            };
+        }
+
+        let html = if let Some(html) = html {
+            html
+        } else {
+            let mut s = String::new();
+            write_text(code, &mut s)?;
+            s
+        };
+        for (i, line) in html.split_terminator('\n').enumerate() {
+            let n = i + 1;
+            if highlighted.iter().any(|r| r.contains(&n)) {
+                out.write_str(r#"<span class="line highlighted">"#)?;
+            } else {
+                out.write_str(r#"<span class="line">"#)?;
+            }
+            if numbers {
+                write!(out, r#"<span class="line-number">{}</span>"#, n)?;
+            }
+            out.write_str(line)?;
+            out.write_str("</span>\n")?;
+        }
+        Ok(())
+    }
+
+    /// Write buffered math as MathML, or as LaTeX if it can not be converted.
+    fn render_math<W>(
+        &mut self,
+        h: Hooks,
+        display: bool,
+        attrs: &Attributes<'s>,
+        latex: &str,
+        mut out: W,
+    ) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        #[cfg(feature = "math")]
+        if let Some(mathml) = crate::math::to_mathml(latex, display) {
+            out.write_str("<math")?;
+            Output::new(self, &mut out).write_attributes(attrs)?;
+            return out.write_str(&mathml["<math".len()..]);
+        }
+        let c = Container::Math { display };
+        h.handler
+            .enter(&mut Output::new(self, &mut out), &c, attrs)?;
+        write_text(latex, &mut out)?;
+        h.handler.exit(&mut Output::new(self, &mut out), &c)
+    }
+
+    fn render_footnote_reference<W>(&mut self, number: usize, mut out: W) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        write!(
+            out,
+            r##"<a id="fnref{}" href="#fn{}" role="doc-noteref"><sup>{}</sup></a>"##,
+            number, number, number
+        )
+    }
+
+    fn render_thematic_break<W>(&mut self, attrs: &Attributes<'s>, mut out: W) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        out.write_str("<hr")?;
+        for (a, v) in attrs.iter() {
+            write!(out, r#" {}=""#, a)?;
+            v.parts().try_for_each(|part| write_attr(part, &mut out))?;
+            out.write_char('"')?;
+        }
+        self.render_tag_end(&mut out)
+    }
+
+    /// Close the start tag of a void element, e.g. `<br>` or `<br />`.
+    fn render_tag_end<W>(&self, mut out: W) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        out.write_str(if self.options.self_closing_tags {
+            " />"
+        } else {
+            ">"
+        })
+    }
+
+    fn render_checkbox<W>(&self, checked: bool, mut out: W) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        out.write_str(r#"<input type="checkbox""#)?;
+        let attrs: &[&str] = if checked {
+            &["disabled", "checked"]
+        } else {
+            &["disabled"]
+        };
+        for a in attrs {
+            if self.options.self_closing_tags {
+                write!(out, r#" {}="{}""#, a, a)?;
+            } else {
+                write!(out, " {}", a)?;
+            }
+        }
+        self.render_tag_end(&mut out)
+    }
+
+    fn render_toc<W>(&mut self, mut out: W) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        if let Some(toc) = &self.toc {
+            if self.not_first_line {
+                out.write_char('\n')?;
+            }
+            out.write_str(toc)?;
+            self.not_first_line = true;
+        }
         Ok(())
     }
 
-    fn render_epilogue<W>(&mut self, mut out: W) -> std::fmt::Result
+    fn render_epilogue<W>(&mut self, h: Hooks, mut out: W) -> std::fmt::Result
     where
         W: std::fmt::Write,
     {
         if self.footnotes.reference_encountered() {
-            out.write_str("\n<section role=\"doc-endnotes\">\n<hr>\n<ol>")?;
+            out.write_str("\n<section role=\"doc-endnotes\">\n<hr")?;
+            self.render_tag_end(&mut out)?;
+            out.write_str("\n<ol>")?;
 
             while let Some((number, events)) = self.footnotes.next() {
                 write!(out, "\n<li id=\"fn{}\">", number)?;
@@ -424,7 +1731,7 @@ impl<'s> Writer<'s> {
                         // not a footnote, so no need to add href before para close
                         out.write_str("</p>")?;
                     }
-                    self.render_event(e, &mut out)?;
+                    self.render_event(h, e, &mut out)?;
                     unclosed_para = matches!(e, Event::End(Container::Paragraph { .. }))
                         && !matches!(self.list_tightness.last(), Some(true));
                 }
@@ -450,7 +1757,100 @@ impl<'s> Writer<'s> {
     }
 }
 
-fn write_text<W>(s: &str, out: W) -> std::fmt::Result
+/// Write a table of contents with the headings up to the given level, excluding headings within
+/// footnotes. Nothing is written if there are no such headings.
+///
+/// Links and footnote references are removed from the content of the headings, as they may not
+/// appear within the links to the headings.
+fn write_toc<'a, 's: 'a, I, W>(
+    events: I,
+    depth: u16,
+    options: Options,
+    h: Hooks,
+    mut out: W,
+) -> std::fmt::Result
+where
+    I: Iterator<Item = &'a Event<'s>>,
+    W: std::fmt::Write,
+{
+    // levels of the first items of the currently open lists
+    let mut lists: Vec<u16> = Vec::new();
+    let mut footnote_depth = 0;
+    let mut heading: Option<Writer> = None;
+    for e in events {
+        match e {
+            Event::Start(Container::Footnote { .. }, ..) => footnote_depth += 1,
+            Event::End(Container::Footnote { .. }) => footnote_depth -= 1,
+            Event::Start(Container::Heading { level, id, .. }, ..)
+                if footnote_depth == 0 && *level <= depth =>
+            {
+                while lists.len() > 1 && matches!(lists.last(), Some(l) if *l > *level) {
+                    out.write_str("</li>\n</ul>\n")?;
+                    lists.pop();
+                }
+                match lists.last_mut() {
+                    Some(l) if *l >= *level => {
+                        out.write_str("</li>\n")?;
+                        *l = *level;
+                    }
+                    Some(_) => {
+                        out.write_str("\n<ul>\n")?;
+                        lists.push(*level);
+                    }
+                    None => {
+                        out.write_str("<nav class=\"toc\">\n<ul>\n")?;
+                        lists.push(*level);
+                    }
+                }
+                out.write_str("<li>")?;
+                if !id.is_empty() {
+                    out.write_str(r##"<a href="#"##)?;
+                    write_attr(id, &mut out)?;
+                    out.write_str(r#"">"#)?;
+                }
+                heading = Some(Writer::new(options));
+            }
+            Event::End(Container::Heading { id, .. }) if heading.is_some() => {
+                heading = None;
+                if !id.is_empty() {
+                    out.write_str("</a>")?;
+                }
+            }
+            Event::Start(Container::Link(..), ..)
+            | Event::End(Container::Link(..))
+            | Event::FootnoteReference(..) => {}
+            e => {
+                if let Some(w) = &mut heading {
+                    w.render_event(h, e, &mut out)?;
+                }
+            }
+        }
+    }
+    if lists.is_empty() {
+        return Ok(());
+    }
+    for _ in lists {
+        out.write_str("</li>\n</ul>\n")?;
+    }
+    out.write_str("</nav>")
+}
+
+/// Parse line numbers and ranges of line numbers, e.g. `1,3-5`. Invalid parts are ignored.
+fn line_ranges(s: &str) -> Vec<std::ops::RangeInclusive<usize>> {
+    s.split(',')
+        .filter_map(|part| {
+            let mut bounds = part.splitn(2, '-').map(|n| n.trim().parse::<usize>());
+            let start = bounds.next()?.ok()?;
+            let end = match bounds.next() {
+                Some(end) => end.ok()?,
+                None => start,
+            };
+            Some(start..=end)
+        })
+        .collect()
+}
+
+pub(crate) fn write_text<W>(s: &str, out: W) -> std::fmt::Result
 where
     W: std::fmt::Write,
 {
@@ -497,11 +1897,11 @@ where
 #[derive(Default)]
 struct Footnotes<'s> {
     /// Stack of current open footnotes, with label and staging buffer.
-    open: Vec<(&'s str, Vec<Event<'s>>)>,
+    open: Vec<(CowStr<'s>, Vec<Event<'s>>)>,
     /// Footnote references in the order they were first encountered.
-    references: Vec<&'s str>,
+    references: Vec<CowStr<'s>>,
     /// Events for each footnote.
-    events: Map<&'s str, Vec<Event<'s>>>,
+    events: Map<CowStr<'s>, Vec<Event<'s>>>,
     /// Number of last footnote that was emitted.
     number: usize,
 }
@@ -518,21 +1918,18 @@ impl<'s> Footnotes<'s> {
     }
 
     /// Add a footnote reference.
-    fn reference(&mut self, label: &'s str) -> usize {
-        self.references
-            .iter()
-            .position(|t| *t == label)
-            .map_or_else(
-                || {
-                    self.references.push(label);
-                    self.references.len()
-                },
-                |i| i + 1,
-            )
+    fn reference(&mut self, label: &CowStr<'s>) -> usize {
+        self.references.iter().position(|t| t == label).map_or_else(
+            || {
+                self.references.push(label.clone());
+                self.references.len()
+            },
+            |i| i + 1,
+        )
     }
 
     /// Start aggregating a footnote.
-    fn start(&mut self, label: &'s str, events: Vec<Event<'s>>) {
+    fn start(&mut self, label: CowStr<'s>, events: Vec<Event<'s>>) {
         self.open.push((label, events));
     }
 
@@ -558,3 +1955,499 @@ impl<'s> Iterator for Footnotes<'s> {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::HtmlHandler;
+    use super::Output;
+    use super::RawHtml;
+    use super::Renderer;
+    use super::Sanitizer;
+    use super::SoftbreakStyle;
+    use super::Standalone;
+    use super::TableOfContents;
+    use super::TocPlacement;
+    use crate::Attributes;
+    use crate::Container;
+    use crate::Parser;
+    use crate::Render;
+    use std::fmt::Write;
+
+    fn render<H: HtmlHandler>(renderer: &Renderer<H>, src: &str) -> String {
+        let mut html = String::new();
+        renderer.push(Parser::new(src), &mut html).unwrap();
+        html
+    }
+
+    #[test]
+    fn toc_start() {
+        let src = concat!(
+            "### a\n",
+            "\n",
+            "# b [link](url)[^n] _c_\n",
+            "\n",
+            "#### d\n",
+            "\n",
+            "## e\n",
+            "\n",
+            "[^n]: # f\n",
+        );
+        let renderer = Renderer::default().table_of_contents(TableOfContents {
+            depth: 3,
+            placement: TocPlacement::Start,
+        });
+        let html = render(&renderer, src);
+        assert_eq!(
+            &html[..html.find("<section").unwrap()],
+            concat!(
+                "<nav class=\"toc\">\n",
+                "<ul>\n",
+                "<li><a href=\"#a\">a</a></li>\n",
+                "<li><a href=\"#b-link-c\">b link <em>c</em></a>\n",
+                "<ul>\n",
+                "<li><a href=\"#e\">e</a></li>\n",
+                "</ul>\n",
+                "</li>\n",
+                "</ul>\n",
+                "</nav>\n",
+            ),
+        );
+        assert_eq!(html.matches("<nav").count(), 1);
+    }
+
+    #[test]
+    fn toc_marker() {
+        let src = concat!(
+            "para\n",
+            "\n",
+            "::::: toc\n",
+            "::: inner\n",
+            "replaced\n",
+            ":::\n",
+            ":::::\n",
+            "\n",
+            "# a\n",
+        );
+        let renderer = Renderer::default().table_of_contents(TableOfContents {
+            depth: 1,
+            placement: TocPlacement::Marker,
+        });
+        assert_eq!(
+            render(&renderer, src),
+            concat!(
+                "<p>para</p>\n",
+                "<nav class=\"toc\">\n",
+                "<ul>\n",
+                "<li><a href=\"#a\">a</a></li>\n",
+                "</ul>\n",
+                "</nav>\n",
+                "<section id=\"a\">\n",
+                "<h1>a</h1>\n",
+                "</section>\n",
+            ),
+        );
+        assert_eq!(
+            render(&Renderer::default(), "::: toc\n:::\n"),
+            "<div class=\"toc\">\n</div>\n",
+        );
+    }
+
+    #[test]
+    fn toc_empty() {
+        let toc = |placement| {
+            Renderer::default().table_of_contents(TableOfContents {
+                depth: 1,
+                placement,
+            })
+        };
+        assert_eq!(
+            render(&toc(TocPlacement::Start), "para\n\n## deep\n"),
+            concat!(
+                "<p>para</p>\n",
+                "<section id=\"deep\">\n",
+                "<h2>deep</h2>\n",
+                "</section>\n",
+            ),
+        );
+        assert_eq!(
+            render(&toc(TocPlacement::Marker), "para\n\n::: toc\n:::\n"),
+            "<p>para</p>\n",
+        );
+    }
+
+    #[test]
+    fn self_closing_tags() {
+        let renderer = Renderer::default().self_closing_tags(true);
+        assert_eq!(
+            render(&renderer, "a\\\nb ![c](d)[^e]\n\n***\n\n[^e]: f\n"),
+            concat!(
+                "<p>a<br />\n",
+                "b <img alt=\"c\" src=\"d\" />",
+                "<a id=\"fnref1\" href=\"#fn1\" role=\"doc-noteref\"><sup>1</sup></a></p>\n",
+                "<hr />\n",
+                "<section role=\"doc-endnotes\">\n",
+                "<hr />\n",
+                "<ol>\n",
+                "<li id=\"fn1\">\n",
+                "<p>f<a href=\"#fnref1\" role=\"doc-backlink\">↩︎︎</a></p>\n",
+                "</li>\n",
+                "</ol>\n",
+                "</section>\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn softbreak() {
+        let src = "a\nb ![c\nd](e)\n";
+        let softbreak = |style| render(&Renderer::default().softbreak(style), src);
+        assert_eq!(
+            softbreak(SoftbreakStyle::Newline),
+            "<p>a\nb <img alt=\"c\nd\" src=\"e\"></p>\n",
+        );
+        assert_eq!(
+            softbreak(SoftbreakStyle::Space),
+            "<p>a b <img alt=\"c d\" src=\"e\"></p>\n",
+        );
+        assert_eq!(
+            softbreak(SoftbreakStyle::Break),
+            "<p>a<br>\nb <img alt=\"c\nd\" src=\"e\"></p>\n",
+        );
+    }
+
+    #[test]
+    fn ascii_quotes() {
+        let renderer = Renderer::default().ascii_quotes(true);
+        assert_eq!(
+            render(&renderer, "'a' \"b\" ![\"c\"](d)\n"),
+            "<p>'a' \"b\" <img alt=\"&quot;c&quot;\" src=\"d\"></p>\n",
+        );
+    }
+
+    #[test]
+    fn no_sections() {
+        let renderer = Renderer::default().sections(false);
+        assert_eq!(
+            render(&renderer, "{.a}\n# b\n\nc\n\n{#e}\n## d\n"),
+            concat!(
+                "<h1 id=\"b\" class=\"a\">b</h1>\n",
+                "<p>c</p>\n",
+                "<h2 id=\"e\">d</h2>\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn task_list_checkboxes() {
+        let renderer = Renderer::default().task_list_checkboxes(true);
+        assert_eq!(
+            render(&renderer, "- [ ] a\n\n- [x] b\n\n  c\n"),
+            concat!(
+                "<ul class=\"task-list\">\n",
+                "<li class=\"unchecked\">\n",
+                "<p><input type=\"checkbox\" disabled> a</p>\n",
+                "</li>\n",
+                "<li class=\"checked\">\n",
+                "<p><input type=\"checkbox\" disabled checked> b</p>\n",
+                "<p>c</p>\n",
+                "</li>\n",
+                "</ul>\n",
+            ),
+        );
+        let renderer = renderer.self_closing_tags(true);
+        assert_eq!(
+            render(&renderer, "- [x] ```\n  a\n  ```\n"),
+            concat!(
+                "<ul class=\"task-list\">\n",
+                "<li class=\"checked\"><input type=\"checkbox\" disabled=\"disabled\" ",
+                "checked=\"checked\" />\n",
+                "<pre><code>a\n",
+                "</code></pre>\n",
+                "</li>\n",
+                "</ul>\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn table_row_groups() {
+        let renderer = Renderer::default().table_row_groups(true);
+        assert_eq!(
+            render(&renderer, "|a|\n|-|\n|b|\n|c|\n|-|\n\n^ d\n"),
+            concat!(
+                "<table>\n",
+                "<caption>d</caption>\n",
+                "<thead>\n",
+                "<tr>\n",
+                "<th>a</th>\n",
+                "</tr>\n",
+                "</thead>\n",
+                "<tbody>\n",
+                "<tr>\n",
+                "<td>b</td>\n",
+                "</tr>\n",
+                "<tr>\n",
+                "<th>c</th>\n",
+                "</tr>\n",
+                "</tbody>\n",
+                "</table>\n",
+            ),
+        );
+        assert_eq!(
+            render(&renderer, "|a|\n|-|\n"),
+            "<table>\n<thead>\n<tr>\n<th>a</th>\n</tr>\n</thead>\n</table>\n",
+        );
+    }
+
+    #[test]
+    fn handler() {
+        struct Handler;
+
+        impl HtmlHandler for Handler {
+            fn enter<'s>(
+                &self,
+                out: &mut Output<'_, 's>,
+                container: &Container<'s>,
+                attrs: &Attributes<'s>,
+            ) -> std::fmt::Result {
+                match container {
+                    Container::Image(..) => out.write_str("<figure><img alt=\""),
+                    c => out.enter(c, attrs),
+                }
+            }
+
+            fn exit<'s>(
+                &self,
+                out: &mut Output<'_, 's>,
+                container: &Container<'s>,
+            ) -> std::fmt::Result {
+                match container {
+                    Container::Image(src, ..) => {
+                        out.write_str("\" src=\"")?;
+                        out.write_attr(src)?;
+                        out.write_str("\"></figure>")
+                    }
+                    c => out.exit(c),
+                }
+            }
+
+            fn symbol<'s>(&self, out: &mut Output<'_, 's>, sym: &str) -> std::fmt::Result {
+                out.write_str("<i>")?;
+                out.write_text(sym)?;
+                out.write_str("</i>")
+            }
+
+            fn footnote_reference<'s>(
+                &self,
+                out: &mut Output<'_, 's>,
+                label: &str,
+                number: usize,
+            ) -> std::fmt::Result {
+                write!(out, "[{}:{}]", number, label)
+            }
+        }
+
+        let renderer = Renderer::default().handler(Handler);
+        assert_eq!(
+            render(&renderer, "![a *b*](\"c\") :d:[^e]\n\n[^e]: :f:[^g]\n"),
+            concat!(
+                "<p><figure><img alt=\"a b\" src=\"&quot;c&quot;\"></figure> <i>d</i>[1:e]</p>\n",
+                "<section role=\"doc-endnotes\">\n",
+                "<hr>\n",
+                "<ol>\n",
+                "<li id=\"fn1\">\n",
+                "<p><i>f</i>[2:g]<a href=\"#fnref1\" role=\"doc-backlink\">↩︎︎</a></p>\n",
+                "</li>\n",
+                "<li id=\"fn2\">\n",
+                "<p><a href=\"#fnref2\" role=\"doc-backlink\">↩︎︎</a></p>\n",
+                "</li>\n",
+                "</ol>\n",
+                "</section>\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn highlighter() {
+        let renderer =
+            Renderer::default().highlighter(|language: &str, attrs: &Attributes, code: &str| {
+                match language {
+                    "x" => Some(format!(
+                        "[{}|{}]",
+                        attrs.classes().count(),
+                        code.replace('\n', ";")
+                    )),
+                    _ => None,
+                }
+            });
+        assert_eq!(
+            render(&renderer, "`a`{.x .y} `<`{.z} `b`\n\n``` x\nc\nd\n```\n"),
+            concat!(
+                "<p><code class=\"x y\">[2|a]</code> <code class=\"z\">&lt;</code> ",
+                "<code>b</code></p>\n",
+                "<pre><code class=\"language-x\">[0|c;d;]</code></pre>\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn code_block_lines() {
+        let src = "{highlight=\"1,3-4,x\"}\n```\na\n<\nc\nd\n```\n";
+        assert_eq!(
+            render(&Renderer::default(), src),
+            "<pre highlight=\"1,3-4,x\"><code>a\n&lt;\nc\nd\n</code></pre>\n",
+        );
+        let renderer = Renderer::default().code_block_lines(true);
+        assert_eq!(
+            render(&renderer, src),
+            concat!(
+                "<pre><code>",
+                "<span class=\"line highlighted\">a</span>\n",
+                "<span class=\"line\">&lt;</span>\n",
+                "<span class=\"line highlighted\">c</span>\n",
+                "<span class=\"line highlighted\">d</span>\n",
+                "</code></pre>\n",
+            ),
+        );
+        assert_eq!(
+            render(&renderer, "```\na\n```\n"),
+            "<pre><code>a\n</code></pre>\n",
+        );
+        let renderer = renderer
+            .highlighter(|_: &str, _: &Attributes, code: &str| Some(code.replace('a', "<b>a</b>")));
+        assert_eq!(
+            render(&renderer, "{.line-numbers}\n```\na\n\nb\n```\n"),
+            concat!(
+                "<pre class=\"line-numbers\"><code>",
+                "<span class=\"line\"><span class=\"line-number\">1</span><b>a</b></span>\n",
+                "<span class=\"line\"><span class=\"line-number\">2</span></span>\n",
+                "<span class=\"line\"><span class=\"line-number\">3</span>b</span>\n",
+                "</code></pre>\n",
+            ),
+        );
+    }
+
+    #[cfg(feature = "math")]
+    #[test]
+    fn mathml() {
+        let src = "$$`x_1`{.a} $`a < b` $`\\x`{#m} *$`1`*\n";
+        assert_eq!(
+            render(&Renderer::default(), src),
+            concat!(
+                "<p><span class=\"math display a\">\\[x_1\\]</span> ",
+                "<span class=\"math inline\">\\(a &lt; b\\)</span> ",
+                "<span id=\"m\" class=\"math inline\">\\(\\x\\)</span> ",
+                "<strong><span class=\"math inline\">\\(1\\)</span></strong></p>\n",
+            ),
+        );
+        assert_eq!(
+            render(&Renderer::default().mathml(true), src),
+            concat!(
+                "<p><math class=\"a\" display=\"block\"><msub><mi>x</mi><mn>1</mn></msub></math> ",
+                "<math><mrow><mi>a</mi><mo>&lt;</mo><mi>b</mi></mrow></math> ",
+                "<span id=\"m\" class=\"math inline\">\\(\\x\\)</span> ",
+                "<strong><math><mn>1</mn></math></strong></p>\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn sanitizer() {
+        let src = concat!(
+            "[a](javascript:x){onclick=x title=t} [b]( JaVa\tscript:x) <a@b.c> <http://c>\n",
+            "![d](data:x){.e #f style=g} [h](/h) [i](?i:j) [k](#k:l)\n",
+            "\n",
+            "``` =html\n",
+            "<script>x</script>\n",
+            "```\n",
+            "\n",
+            "{onmouseover=x}\n",
+            "----\n",
+        );
+        let sanitizer = Sanitizer::default();
+        assert_eq!(
+            render(&Renderer::default().sanitizer(sanitizer.clone()), src),
+            concat!(
+                "<p><a title=\"t\">a</a> <a>b</a> ",
+                "<a href=\"mailto:a@b.c\">a@b.c</a> <a href=\"http://c\">http://c</a>\n",
+                "<img id=\"f\" class=\"e\" alt=\"d\"> <a href=\"/h\">h</a> ",
+                "<a href=\"?i:j\">i</a> <a href=\"#k:l\">k</a></p>\n",
+                "\n",
+                "<hr>\n",
+            ),
+        );
+        let sanitizer = sanitizer
+            .raw_html(RawHtml::Escape)
+            .url_schemes(["data"])
+            .attributes(["style"]);
+        assert_eq!(
+            render(&Renderer::default().sanitizer(sanitizer), src),
+            concat!(
+                "<p><a>a</a> <a>b</a> <a>a@b.c</a> <a>http://c</a>\n",
+                "<img style=\"g\" alt=\"d\" src=\"data:x\"> <a href=\"/h\">h</a> ",
+                "<a href=\"?i:j\">i</a> <a href=\"#k:l\">k</a></p>\n",
+                "&lt;script&gt;x&lt;/script&gt;\n",
+                "<hr>\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn standalone() {
+        let src = "text[^a]\n\n[^a]: # note\n\n# A 'b' `<i>`{=html}\n\n## C\n";
+        let renderer = Renderer::default().sections(false).standalone(
+            Standalone::default()
+                .stylesheet("a.css")
+                .script("b&c.js")
+                .template("<title>{{ title }}</title>\n{{head}}{{toc}}{{body}}{{x}}{{"),
+        );
+        assert_eq!(
+            render(&renderer, src),
+            concat!(
+                "<title>A ‘b’</title>\n",
+                "<link rel=\"stylesheet\" href=\"a.css\">\n",
+                "<script src=\"b&amp;c.js\"></script>\n",
+                "<nav class=\"toc\">\n",
+                "<ul>\n",
+                "<li><a href=\"#A-b-i\">A &lsquo;b&rsquo; <i></a>\n",
+                "<ul>\n",
+                "<li><a href=\"#C\">C</a></li>\n",
+                "</ul>\n",
+                "</li>\n",
+                "</ul>\n",
+                "</nav>",
+                "<p>text<a id=\"fnref1\" href=\"#fn1\" role=\"doc-noteref\"><sup>1</sup></a></p>\n",
+                "<h1 id=\"A-b-i\">A &lsquo;b&rsquo; <i></h1>\n",
+                "<h2 id=\"C\">C</h2>\n",
+                "<section role=\"doc-endnotes\">\n",
+                "<hr>\n",
+                "<ol>\n",
+                "<li id=\"fn1\">\n",
+                "<h1 id=\"note\">note</h1>\n",
+                "<p><a href=\"#fnref1\" role=\"doc-backlink\">↩︎︎</a></p>\n",
+                "</li>\n",
+                "</ol>\n",
+                "</section>\n",
+                "{{x}}{{",
+            ),
+        );
+        let renderer = Renderer::default().standalone(Standalone::default().title("<T>"));
+        assert_eq!(
+            render(&renderer, "# A\n"),
+            concat!(
+                "<!DOCTYPE html>\n",
+                "<html>\n",
+                "<head>\n",
+                "<meta charset=\"utf-8\">\n",
+                "<title>&lt;T&gt;</title>\n",
+                "</head>\n",
+                "<body>\n",
+                "<section id=\"A\">\n",
+                "<h1>A</h1>\n",
+                "</section>\n",
+                "</body>\n",
+                "</html>\n",
+            ),
+        );
+    }
+}
@@ -268,6 +268,14 @@ impl<'s> Parser<'s> {
         self.store_attributes.clear();
     }
 
+    /// End offset of the exit event of a container whose events have been buffered.
+    pub fn container_end(&self, container: Container<'s>) -> Option<usize> {
+        self.events
+            .iter()
+            .find(|e| e.kind == EventKind::Exit(container))
+            .map(|e| e.span.end)
+    }
+
     fn push_sp(&mut self, kind: EventKind<'s>, span: Range<usize>) -> Option<ControlFlow> {
         self.events.push_back(Event { kind, span });
         Some(Continue)
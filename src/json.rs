@@ -0,0 +1,1484 @@
+//! Conversion between [`Event`]s and the JSON AST of [djot.js](https://github.com/jgm/djot.js).
+//!
+//! The [`Renderer`] writes a document in the same JSON format as `djot -t ast --json`, and
+//! [`parse`] reads such a document back into a sequence of events. This makes it possible to
+//! exchange documents with djot.js, or to compare the output of the two implementations
+//! structurally.
+//!
+//! Some information is not part of the JSON AST and will not survive a roundtrip, e.g. the
+//! locations of link definitions and footnotes, which djot.js stores in maps at the document
+//! level, or [`Event::Escape`] and [`Event::Blankline`] events.
+//!
+//! # Examples
+//!
+//! ```
+//! use jotdown::Render;
+//! let events = jotdown::Parser::new("_hello_");
+//! let mut json = String::new();
+//! jotdown::json::Renderer::default().push(events, &mut json);
+//! assert_eq!(
+//!     json,
+//!     concat!(
+//!         r#"{"tag":"doc","references":{},"autoReferences":{},"footnotes":{},"children":["#,
+//!         r#"{"tag":"para","children":[{"tag":"emph","#,
+//!         r#""children":[{"tag":"str","text":"hello"}]}]}"#,
+//!         "]}\n",
+//!     ),
+//! );
+//! let events = jotdown::json::parse(&json).unwrap();
+//! assert_eq!(events, jotdown::Parser::new("_hello_").collect::<Vec<_>>());
+//! ```
+
+use std::fmt;
+
+use crate::ast::Document;
+use crate::ast::Node;
+use crate::ast::NodeKind;
+use crate::Alignment;
+use crate::AttributeValue;
+use crate::Attributes;
+use crate::Container;
+use crate::Event;
+use crate::LinkType;
+use crate::ListKind;
+use crate::OrderedListNumbering::{self, *};
+use crate::OrderedListStyle::{self, *};
+use crate::Render;
+use crate::SpanLinkType;
+
+/// [`Render`] implementor that writes the JSON AST of djot.js.
+///
+/// All events are buffered before any output is written, as link definitions and footnotes are
+/// written at the start of the document.
+#[derive(Default)]
+pub struct Renderer {}
+
+impl Render for Renderer {
+    fn push<'s, I, W>(&self, events: I, mut out: W) -> fmt::Result
+    where
+        I: Iterator<Item = Event<'s>>,
+        W: fmt::Write,
+    {
+        let doc = events.collect::<Document>();
+        Writer::new(&doc).write(&mut out)
+    }
+
+    fn push_borrowed<'s, E, I, W>(&self, events: I, mut out: W) -> fmt::Result
+    where
+        E: AsRef<Event<'s>>,
+        I: Iterator<Item = E>,
+        W: fmt::Write,
+    {
+        let doc = events.map(|e| e.as_ref().clone()).collect::<Document>();
+        Writer::new(&doc).write(&mut out)
+    }
+}
+
+struct Writer<'a, 's> {
+    doc: &'a Document<'s>,
+    /// Link definitions with their label, destination and attributes.
+    link_definitions: Vec<(&'a str, String, &'a Attributes<'s>)>,
+    /// Heading ids and their text, for implicit heading references.
+    headings: Vec<(&'a str, String)>,
+    /// Footnotes with their label.
+    footnotes: Vec<(&'a str, &'a Node<'s>)>,
+}
+
+impl<'a, 's> Writer<'a, 's> {
+    fn new(doc: &'a Document<'s>) -> Self {
+        let mut link_definitions = Vec::new();
+        let mut headings = Vec::new();
+        let mut footnotes = Vec::new();
+        for node in doc.descendants() {
+            match node.container() {
+                Some(Container::LinkDefinition { label }) => {
+                    let attrs = node.attributes().unwrap();
+                    link_definitions.push((label.as_ref(), text(node), attrs));
+                }
+                Some(Container::Heading { id, .. })
+                    if !id.is_empty() && !headings.iter().any(|(i, _)| i == id) =>
+                {
+                    headings.push((id.as_ref(), text(node)));
+                }
+                Some(Container::Footnote { label }) => footnotes.push((label.as_ref(), node)),
+                _ => {}
+            }
+        }
+        Self {
+            doc,
+            link_definitions,
+            headings,
+            footnotes,
+        }
+    }
+
+    fn write<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        out.write_str(r#"{"tag":"doc","references":{"#)?;
+        for (i, (label, url, attrs)) in self.link_definitions.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            write_str(label, out)?;
+            out.write_char(':')?;
+            write_reference(label, url, attrs, out)?;
+        }
+        out.write_str(r#"},"autoReferences":{"#)?;
+        let mut first = true;
+        for (id, text) in &self.headings {
+            if self.link_definitions.iter().any(|(l, ..)| l == text) {
+                continue;
+            }
+            if !first {
+                out.write_char(',')?;
+            }
+            first = false;
+            write_str(text, out)?;
+            out.write_char(':')?;
+            write_reference(text, &format!("#{}", id), &Attributes::new(), out)?;
+        }
+        out.write_str(r#"},"footnotes":{"#)?;
+        for (i, (label, node)) in self.footnotes.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            write_str(label, out)?;
+            out.write_str(r#":{"tag":"footnote","label":"#)?;
+            write_str(label, out)?;
+            self.write_attributes(node.attributes().unwrap(), out)?;
+            self.write_children(node.children(), out)?;
+            out.write_char('}')?;
+        }
+        out.write_char('}')?;
+        self.write_children(&self.doc.children, out)?;
+        out.write_str("}\n")
+    }
+
+    /// Write the `children` field of a node.
+    fn write_children<W: fmt::Write>(&self, children: &[Node<'s>], out: &mut W) -> fmt::Result {
+        out.write_str(r#","children":["#)?;
+        let mut first = true;
+        let mut i = 0;
+        while i < children.len() {
+            let node = &children[i];
+            i += 1;
+            match &node.kind {
+                NodeKind::Atom(Event::Escape | Event::Blankline)
+                | NodeKind::Container {
+                    container: Container::LinkDefinition { .. } | Container::Footnote { .. },
+                    ..
+                } => continue,
+                _ => {}
+            }
+            if !first {
+                out.write_char(',')?;
+            }
+            first = false;
+            match &node.kind {
+                NodeKind::Atom(Event::Str(s)) => {
+                    // adjacent strings are merged in djot.js
+                    let mut s = s.to_string();
+                    while let Some(NodeKind::Atom(e @ (Event::Str(..) | Event::Escape))) =
+                        children.get(i).map(|n| &n.kind)
+                    {
+                        if let Event::Str(t) = e {
+                            s.push_str(t);
+                        }
+                        i += 1;
+                    }
+                    out.write_str(r#"{"tag":"str","text":"#)?;
+                    write_str(&s, out)?;
+                    out.write_char('}')?;
+                }
+                NodeKind::Atom(e @ (Event::LeftSingleQuote | Event::LeftDoubleQuote)) => {
+                    // djot.js represents matched quotes as containers
+                    if let Some(j) = closing_quote(e, &children[i..]) {
+                        if let Event::LeftSingleQuote = e {
+                            out.write_str(r#"{"tag":"single_quoted""#)?;
+                        } else {
+                            out.write_str(r#"{"tag":"double_quoted""#)?;
+                        }
+                        self.write_children(&children[i..i + j], out)?;
+                        out.write_char('}')?;
+                        i += j + 1;
+                    } else {
+                        self.write_atom(e, out)?;
+                    }
+                }
+                NodeKind::Atom(e) => self.write_atom(e, out)?,
+                NodeKind::Container {
+                    container,
+                    attributes,
+                    children,
+                } => self.write_container(container, attributes, children, out)?,
+            }
+        }
+        out.write_char(']')
+    }
+
+    fn write_atom<W: fmt::Write>(&self, e: &Event<'s>, out: &mut W) -> fmt::Result {
+        let smart = |ty: &str, text: &str, out: &mut W| {
+            write!(
+                out,
+                r#"{{"tag":"smart_punctuation","type":"{}","text":"{}"}}"#,
+                ty, text
+            )
+        };
+        match e {
+            Event::FootnoteReference(label) => {
+                out.write_str(r#"{"tag":"footnote_reference","text":"#)?;
+                write_str(label, out)?;
+                out.write_char('}')
+            }
+            Event::Symbol(sym) => {
+                out.write_str(r#"{"tag":"symb","alias":"#)?;
+                write_str(sym, out)?;
+                out.write_char('}')
+            }
+            Event::LeftSingleQuote => smart("left_single_quote", "'", out),
+            Event::RightSingleQuote => smart("right_single_quote", "'", out),
+            Event::LeftDoubleQuote => smart("left_double_quote", "\\\"", out),
+            Event::RightDoubleQuote => smart("right_double_quote", "\\\"", out),
+            Event::Ellipsis => smart("ellipses", "...", out),
+            Event::EnDash => smart("en_dash", "--", out),
+            Event::EmDash => smart("em_dash", "---", out),
+            Event::NonBreakingSpace => out.write_str(r#"{"tag":"non_breaking_space"}"#),
+            Event::Softbreak => out.write_str(r#"{"tag":"soft_break"}"#),
+            Event::Hardbreak => out.write_str(r#"{"tag":"hard_break"}"#),
+            Event::ThematicBreak(attrs) => {
+                out.write_str(r#"{"tag":"thematic_break""#)?;
+                self.write_attributes(attrs, out)?;
+                out.write_char('}')
+            }
+            Event::Str(..)
+            | Event::Escape
+            | Event::Blankline
+            | Event::Start(..)
+            | Event::End(..) => unreachable!(),
+        }
+    }
+
+    fn write_container<W: fmt::Write>(
+        &self,
+        c: &Container<'s>,
+        attrs: &Attributes<'s>,
+        children: &[Node<'s>],
+        out: &mut W,
+    ) -> fmt::Result {
+        let tag = match c {
+            Container::Blockquote => "block_quote",
+            Container::List { kind, .. } => match kind {
+                ListKind::Unordered => "bullet_list",
+                ListKind::Ordered { .. } => "ordered_list",
+                ListKind::Task => "task_list",
+            },
+            Container::ListItem => "list_item",
+            Container::TaskListItem { .. } => "task_list_item",
+            Container::DescriptionList => "definition_list",
+            Container::DescriptionDetails => "definition",
+            Container::Table => "table",
+            Container::TableRow { .. } => "row",
+            Container::Section { .. } => "section",
+            Container::Div { .. } => "div",
+            Container::Paragraph => "para",
+            Container::Heading { .. } => "heading",
+            Container::TableCell { .. } => "cell",
+            Container::Caption => "caption",
+            Container::DescriptionTerm => "term",
+            Container::RawBlock { .. } => "raw_block",
+            Container::CodeBlock { .. } => "code_block",
+            Container::Span => "span",
+            Container::Link(_, LinkType::AutoLink) => "url",
+            Container::Link(_, LinkType::Email) => "email",
+            Container::Link(..) => "link",
+            Container::Image(..) => "image",
+            Container::Verbatim => "verbatim",
+            Container::Math { display: false } => "inline_math",
+            Container::Math { display: true } => "display_math",
+            Container::RawInline { .. } => "raw_inline",
+            Container::Subscript => "subscript",
+            Container::Superscript => "superscript",
+            Container::Insert => "insert",
+            Container::Delete => "delete",
+            Container::Strong => "strong",
+            Container::Emphasis => "emph",
+            Container::Mark => "mark",
+            Container::Footnote { .. } | Container::LinkDefinition { .. } => unreachable!(),
+        };
+        write!(out, r#"{{"tag":"{}""#, tag)?;
+
+        let mut attrs = std::borrow::Cow::Borrowed(attrs);
+        let mut auto_id = None;
+        match c {
+            Container::List { kind, tight } => {
+                match kind {
+                    ListKind::Unordered => out.write_str(r#","style":"-""#)?,
+                    ListKind::Ordered {
+                        numbering,
+                        style,
+                        start,
+                    } => {
+                        let n = match numbering {
+                            Decimal => '1',
+                            AlphaLower => 'a',
+                            AlphaUpper => 'A',
+                            RomanLower => 'i',
+                            RomanUpper => 'I',
+                        };
+                        match style {
+                            Period => write!(out, r#","style":"{}.""#, n)?,
+                            Paren => write!(out, r#","style":"{})""#, n)?,
+                            ParenParen => write!(out, r#","style":"({})""#, n)?,
+                        }
+                        if *start != 1 {
+                            write!(out, r#","start":{}"#, start)?;
+                        }
+                    }
+                    ListKind::Task => {}
+                }
+                write!(out, r#","tight":{}"#, tight)?;
+            }
+            Container::TaskListItem { checked } => {
+                let checkbox = if *checked { "checked" } else { "unchecked" };
+                write!(out, r#","checkbox":"{}""#, checkbox)?;
+            }
+            Container::DescriptionList => out.write_str(r#","tight":false"#)?,
+            Container::TableRow { head } => write!(out, r#","head":{}"#, head)?,
+            Container::TableCell { alignment, head } => {
+                let align = match alignment {
+                    Alignment::Unspecified => "default",
+                    Alignment::Left => "left",
+                    Alignment::Center => "center",
+                    Alignment::Right => "right",
+                };
+                write!(out, r#","head":{},"align":"{}""#, head, align)?;
+            }
+            Container::Section { id } | Container::Heading { id, .. } => {
+                if let Container::Heading { level, .. } = c {
+                    write!(out, r#","level":{}"#, level)?;
+                }
+                let in_section = matches!(
+                    c,
+                    Container::Heading {
+                        has_section: true,
+                        ..
+                    }
+                );
+                if !in_section && !id.is_empty() && attrs.get("id").is_none() {
+                    auto_id = Some(id);
+                }
+            }
+            Container::Div { class } if !class.is_empty() => {
+                attrs.to_mut().insert("class", class.clone().into());
+            }
+            Container::RawBlock { format } | Container::RawInline { format } => {
+                out.write_str(r#","format":"#)?;
+                write_str(format, out)?;
+            }
+            Container::CodeBlock { language } if !language.is_empty() => {
+                out.write_str(r#","lang":"#)?;
+                write_str(language, out)?;
+            }
+            Container::Link(dst, LinkType::Span(ty)) | Container::Image(dst, ty) => {
+                let reference = match ty {
+                    SpanLinkType::Inline => None,
+                    SpanLinkType::Reference => self.reference(dst, &attrs),
+                    SpanLinkType::Unresolved => Some((dst.to_string(), Attributes::new())),
+                };
+                if let Some((label, attrs_own)) = reference {
+                    out.write_str(r#","reference":"#)?;
+                    write_str(&label, out)?;
+                    attrs = std::borrow::Cow::Owned(attrs_own);
+                } else {
+                    out.write_str(r#","destination":"#)?;
+                    write_str(dst, out)?;
+                }
+            }
+            _ => {}
+        }
+
+        if matches!(
+            c,
+            Container::RawBlock { .. }
+                | Container::CodeBlock { .. }
+                | Container::Verbatim
+                | Container::Math { .. }
+                | Container::RawInline { .. }
+                | Container::Link(_, LinkType::AutoLink | LinkType::Email)
+        ) {
+            out.write_str(r#","text":"#)?;
+            write_str(&text_nodes(children), out)?;
+            self.write_attributes(&attrs, out)?;
+        } else {
+            self.write_attributes(&attrs, out)?;
+            if let Some(id) = auto_id {
+                out.write_str(r#","autoAttributes":{"id":"#)?;
+                write_str(id, out)?;
+                out.write_char('}')?;
+            }
+            if let Container::DescriptionList = c {
+                self.write_definition_items(children, out)?;
+            } else {
+                self.write_children(children, out)?;
+            }
+        }
+        out.write_char('}')
+    }
+
+    /// Write the children of a description list, djot.js groups each term and its definition
+    /// into an item.
+    fn write_definition_items<W: fmt::Write>(
+        &self,
+        children: &[Node<'s>],
+        out: &mut W,
+    ) -> fmt::Result {
+        out.write_str(r#","children":["#)?;
+        let mut first = true;
+        let mut i = 0;
+        while i < children.len() {
+            if !matches!(children[i].container(), Some(Container::DescriptionTerm)) {
+                i += 1;
+                continue;
+            }
+            let next = children[i + 1..]
+                .iter()
+                .position(|n| n.container().is_some())
+                .map_or(children.len(), |j| i + 1 + j);
+            let end = if matches!(
+                children.get(next).and_then(Node::container),
+                Some(Container::DescriptionDetails)
+            ) {
+                next + 1
+            } else {
+                i + 1
+            };
+            if !first {
+                out.write_char(',')?;
+            }
+            first = false;
+            out.write_str(r#"{"tag":"definition_list_item""#)?;
+            self.write_children(&children[i..end], out)?;
+            out.write_char('}')?;
+            i = end;
+        }
+        out.write_char(']')
+    }
+
+    /// Find the label of a reference link and the attributes of the link that do not originate
+    /// from the link definition.
+    fn reference(&self, dst: &str, attrs: &Attributes<'s>) -> Option<(String, Attributes<'s>)> {
+        self.link_definitions
+            .iter()
+            .filter(|(_, url, _)| url == dst)
+            .find_map(|(label, _, attrs_def)| {
+                let attrs_own = attrs.difference(attrs_def)?;
+                Some((label.to_string(), attrs_own))
+            })
+            .or_else(|| {
+                let id = dst.strip_prefix('#')?;
+                self.headings
+                    .iter()
+                    .find(|(i, _)| *i == id)
+                    .map(|(_, text)| (text.clone(), attrs.clone()))
+            })
+    }
+
+    fn write_attributes<W: fmt::Write>(&self, attrs: &Attributes, out: &mut W) -> fmt::Result {
+        if attrs.is_empty() {
+            return Ok(());
+        }
+        out.write_str(r#","attributes":{"#)?;
+        for (i, (k, v)) in attrs.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            write_str(k, out)?;
+            out.write_char(':')?;
+            write_str(&v.to_string(), out)?;
+        }
+        out.write_char('}')
+    }
+}
+
+fn write_reference<W: fmt::Write>(
+    label: &str,
+    url: &str,
+    attrs: &Attributes,
+    out: &mut W,
+) -> fmt::Result {
+    out.write_str(r#"{"tag":"reference","label":"#)?;
+    write_str(label, out)?;
+    out.write_str(r#","destination":"#)?;
+    write_str(url, out)?;
+    if !attrs.is_empty() {
+        out.write_str(r#","attributes":{"#)?;
+        for (i, (k, v)) in attrs.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            write_str(k, out)?;
+            out.write_char(':')?;
+            write_str(&v.to_string(), out)?;
+        }
+        out.write_char('}')?;
+    }
+    out.write_char('}')
+}
+
+/// Find the index of the quote that closes the left quote `left` among the following siblings.
+fn closing_quote(left: &Event, siblings: &[Node]) -> Option<usize> {
+    let right = match left {
+        Event::LeftSingleQuote => Event::RightSingleQuote,
+        _ => Event::RightDoubleQuote,
+    };
+    let mut depth = 0;
+    siblings.iter().position(|n| match &n.kind {
+        NodeKind::Atom(e) if e == left => {
+            depth += 1;
+            false
+        }
+        NodeKind::Atom(e) if *e == right => {
+            if depth == 0 {
+                true
+            } else {
+                depth -= 1;
+                false
+            }
+        }
+        _ => false,
+    })
+}
+
+/// Write a JSON string literal.
+fn write_str<W: fmt::Write>(s: &str, out: &mut W) -> fmt::Result {
+    out.write_char('"')?;
+    let mut ahead = s;
+    while let Some(i) = ahead.find(|c: char| c < ' ' || c == '"' || c == '\\') {
+        out.write_str(&ahead[..i])?;
+        let c = ahead[i..].chars().next().unwrap();
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            c => write!(out, "\\u{:04x}", c as u32)?,
+        }
+        ahead = &ahead[i + 1..];
+    }
+    out.write_str(ahead)?;
+    out.write_char('"')
+}
+
+/// Concatenated text of a node, with softbreaks as spaces.
+fn text(node: &Node) -> String {
+    text_nodes(node.children())
+}
+
+fn text_nodes(nodes: &[Node]) -> String {
+    let mut s = String::new();
+    for n in nodes.iter().flat_map(Node::descendants) {
+        match &n.kind {
+            NodeKind::Atom(Event::Str(t)) => s.push_str(t),
+            NodeKind::Atom(Event::Softbreak) => s.push(' '),
+            _ => {}
+        }
+    }
+    s
+}
+
+/// An error that occurred while reading a JSON AST.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    /// Byte offset in the input where the error was detected.
+    pub offset: usize,
+    /// Description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Read a djot.js JSON AST into a sequence of events.
+///
+/// Link definitions and footnotes, which are stored at the document level, are emitted after the
+/// content of the document. Reference links are resolved using the link definitions and heading
+/// references of the document, just like when parsing Djot.
+///
+/// # Errors
+///
+/// If the input is not valid JSON, or does not describe a valid djot.js document, an [`Error`]
+/// with the byte offset of the problem is returned. Arrays and objects may be nested at most 128
+/// levels deep.
+pub fn parse(json: &str) -> Result<Vec<Event<'static>>, Error> {
+    let mut lexer = Lexer {
+        src: json,
+        pos: 0,
+        depth: 0,
+    };
+    let value = lexer.value()?;
+    lexer.whitespace();
+    if lexer.pos < json.len() {
+        return Err(lexer.error("trailing characters"));
+    }
+    let mut reader = Reader {
+        references: Vec::new(),
+        auto_references: Vec::new(),
+        events: Vec::new(),
+    };
+    reader.doc(&value)?;
+    Ok(reader.events)
+}
+
+/// Maximum nesting depth of arrays and objects accepted by [`parse`].
+const MAX_DEPTH: usize = 128;
+
+/// A parsed JSON value.
+#[derive(Debug)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Fields in order of appearance, along with the offset of the object.
+    Object(Vec<(String, Value)>, usize),
+}
+
+struct Lexer<'s> {
+    src: &'s str,
+    pos: usize,
+    /// Number of arrays and objects that are currently open.
+    depth: usize,
+}
+
+impl<'s> Lexer<'s> {
+    fn error(&self, message: &str) -> Error {
+        Error {
+            offset: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn whitespace(&mut self) {
+        let rest = &self.src[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> Result<(), Error> {
+        self.whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", char::from(c))))
+        }
+    }
+
+    /// Enter an array or object, the input is read recursively so the depth must be limited.
+    fn open(&mut self) -> Result<(), Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("maximum nesting depth exceeded"));
+        }
+        self.depth += 1;
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'{') => {
+                let start = self.pos;
+                self.open()?;
+                let mut fields = Vec::new();
+                self.whitespace();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        self.whitespace();
+                        let key = self.string()?;
+                        self.eat(b':')?;
+                        fields.push((key, self.value()?));
+                        self.whitespace();
+                        match self.peek() {
+                            Some(b',') => self.pos += 1,
+                            Some(b'}') => {
+                                self.pos += 1;
+                                break;
+                            }
+                            _ => return Err(self.error("expected ',' or '}'")),
+                        }
+                    }
+                }
+                self.depth -= 1;
+                Ok(Value::Object(fields, start))
+            }
+            Some(b'[') => {
+                self.open()?;
+                let mut elems = Vec::new();
+                self.whitespace();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        elems.push(self.value()?);
+                        self.whitespace();
+                        match self.peek() {
+                            Some(b',') => self.pos += 1,
+                            Some(b']') => {
+                                self.pos += 1;
+                                break;
+                            }
+                            _ => return Err(self.error("expected ',' or ']'")),
+                        }
+                    }
+                }
+                self.depth -= 1;
+                Ok(Value::Array(elems))
+            }
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') => self.keyword("true", Value::Bool(true)),
+            Some(b'f') => self.keyword("false", Value::Bool(false)),
+            Some(b'n') => self.keyword("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.pos;
+                let len = self.src[start..]
+                    .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                    .unwrap_or(self.src.len() - start);
+                self.src[start..start + len]
+                    .parse()
+                    .map(|n| {
+                        self.pos += len;
+                        Value::Number(n)
+                    })
+                    .map_err(|_| self.error("invalid number"))
+            }
+            _ => Err(self.error("expected value")),
+        }
+    }
+
+    fn keyword(&mut self, kw: &str, value: Value) -> Result<Value, Error> {
+        if self.src[self.pos..].starts_with(kw) {
+            self.pos += kw.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected value"))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        if self.peek() != Some(b'"') {
+            return Err(self.error("expected string"));
+        }
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let rest = &self.src[self.pos..];
+            let i = rest
+                .find(|c: char| c == '"' || c == '\\' || c < ' ')
+                .ok_or_else(|| self.error("unterminated string"))?;
+            s.push_str(&rest[..i]);
+            self.pos += i;
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let c = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.pos += 1;
+                            let mut c = self.hex()?;
+                            if (0xd800..0xdc00).contains(&c) {
+                                // a high surrogate must be followed by an escaped low surrogate
+                                if !self.src[self.pos..].starts_with("\\u") {
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                self.pos += 2;
+                                let low = self.hex()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    self.pos -= 6;
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                c = 0x10000 + ((c - 0xd800) << 10) + (low - 0xdc00);
+                            } else if (0xdc00..0xe000).contains(&c) {
+                                self.pos -= 6;
+                                return Err(self.error("unpaired surrogate"));
+                            }
+                            self.pos -= 1;
+                            char::from_u32(c).ok_or_else(|| self.error("invalid code point"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    self.pos += 1;
+                    s.push(c);
+                }
+                _ => return Err(self.error("control character in string")),
+            }
+        }
+    }
+
+    fn hex(&mut self) -> Result<u32, Error> {
+        let digits = self
+            .src
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.error("invalid unicode escape"));
+        }
+        let c =
+            u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(c)
+    }
+}
+
+/// A JSON object that describes a djot.js node.
+struct Object<'a> {
+    fields: &'a [(String, Value)],
+    offset: usize,
+}
+
+impl<'a> Object<'a> {
+    fn new(value: &'a Value) -> Result<Self, Error> {
+        if let Value::Object(fields, offset) = value {
+            Ok(Self {
+                fields,
+                offset: *offset,
+            })
+        } else {
+            Err(Error {
+                offset: 0,
+                message: "expected object".to_string(),
+            })
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error {
+            offset: self.offset,
+            message: message.to_string(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn str(&self, key: &str) -> Result<&'a str, Error> {
+        self.str_opt(key)?
+            .ok_or_else(|| self.error(&format!("missing field '{}'", key)))
+    }
+
+    fn str_opt(&self, key: &str) -> Result<Option<&'a str>, Error> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(_) => Err(self.error(&format!("field '{}' is not a string", key))),
+        }
+    }
+
+    fn bool(&self, key: &str) -> Result<bool, Error> {
+        match self.get(key) {
+            None => Ok(false),
+            Some(Value::Bool(b)) => Ok(*b),
+            Some(_) => Err(self.error(&format!("field '{}' is not a boolean", key))),
+        }
+    }
+
+    fn number(&self, key: &str) -> Result<Option<u64>, Error> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => Ok(Some(*n as u64)),
+            Some(_) => Err(self.error(&format!("field '{}' is not an integer", key))),
+        }
+    }
+
+    fn object(&self, key: &str) -> Result<&'a [(String, Value)], Error> {
+        match self.get(key) {
+            None => Ok(&[]),
+            Some(Value::Object(fields, _)) => Ok(fields),
+            Some(_) => Err(self.error(&format!("field '{}' is not an object", key))),
+        }
+    }
+
+    fn children(&self) -> Result<&'a [Value], Error> {
+        match self.get("children") {
+            None => Ok(&[]),
+            Some(Value::Array(elems)) => Ok(elems),
+            Some(_) => Err(self.error("field 'children' is not an array")),
+        }
+    }
+
+    fn attributes(&self) -> Result<Attributes<'static>, Error> {
+        let mut attrs = Attributes::new();
+        for (k, v) in self.object("attributes")? {
+            if let Value::String(v) = v {
+                attrs.insert(k.clone(), attribute_value(v));
+            } else {
+                return Err(self.error("attribute value is not a string"));
+            }
+        }
+        Ok(attrs)
+    }
+
+    fn id(&self) -> Result<Option<&'a str>, Error> {
+        for key in ["attributes", "autoAttributes"] {
+            if let Some((_, Value::String(id))) = self.object(key)?.iter().find(|(k, _)| k == "id")
+            {
+                return Ok(Some(id));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Create an attribute value that is displayed as the provided text.
+fn attribute_value(text: &str) -> AttributeValue<'static> {
+    let mut raw = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        // quotes can only appear escaped within a value in djot
+        if c == '"' || c == '\\' && matches!(chars.peek(), Some(c) if c.is_ascii_punctuation()) {
+            raw.push('\\');
+        }
+        raw.push(c);
+    }
+    raw.into()
+}
+
+struct Reader {
+    /// Link definitions with their label, destination and attributes.
+    references: Vec<(String, String, Attributes<'static>)>,
+    /// Implicit heading references with their label and destination.
+    auto_references: Vec<(String, String)>,
+    events: Vec<Event<'static>>,
+}
+
+impl Reader {
+    fn doc(&mut self, value: &Value) -> Result<(), Error> {
+        let doc = Object::new(value)?;
+        if doc.str("tag")? != "doc" {
+            return Err(doc.error("expected doc"));
+        }
+        for (label, r) in doc.object("references")? {
+            let r = Object::new(r)?;
+            let url = r.str("destination")?.to_string();
+            self.references.push((label.clone(), url, r.attributes()?));
+        }
+        for (label, r) in doc.object("autoReferences")? {
+            let url = Object::new(r)?.str("destination")?.to_string();
+            self.auto_references.push((label.clone(), url));
+        }
+        self.children(&doc, None)?;
+        for (label, url, attrs) in std::mem::take(&mut self.references) {
+            let c = Container::LinkDefinition {
+                label: label.clone().into(),
+            };
+            self.events.push(Event::Start(c.clone(), attrs.clone()));
+            self.events.push(Event::Str(url.clone().into()));
+            self.events.push(Event::End(c));
+            self.references.push((label, url, attrs));
+        }
+        for (_, footnote) in doc.object("footnotes")? {
+            self.node(footnote, None)?;
+        }
+        Ok(())
+    }
+
+    fn children(&mut self, obj: &Object, section: Option<&str>) -> Result<(), Error> {
+        for child in obj.children()? {
+            self.node(child, section)?;
+        }
+        Ok(())
+    }
+
+    /// Read a node, `section` is the id of the parent section, if any.
+    fn node(&mut self, value: &Value, section: Option<&str>) -> Result<(), Error> {
+        let obj = Object::new(value)?;
+        let atom = match obj.str("tag")? {
+            "str" => Some(Event::Str(obj.str("text")?.to_string().into())),
+            "footnote_reference" => Some(Event::FootnoteReference(
+                obj.str("text")?.to_string().into(),
+            )),
+            "symb" => Some(Event::Symbol(obj.str("alias")?.to_string().into())),
+            "soft_break" => Some(Event::Softbreak),
+            "hard_break" => Some(Event::Hardbreak),
+            "non_breaking_space" => Some(Event::NonBreakingSpace),
+            "thematic_break" => Some(Event::ThematicBreak(obj.attributes()?)),
+            "smart_punctuation" => Some(match obj.str("type")? {
+                "left_single_quote" => Event::LeftSingleQuote,
+                "right_single_quote" => Event::RightSingleQuote,
+                "left_double_quote" => Event::LeftDoubleQuote,
+                "right_double_quote" => Event::RightDoubleQuote,
+                "ellipses" => Event::Ellipsis,
+                "em_dash" => Event::EmDash,
+                "en_dash" => Event::EnDash,
+                _ => return Err(obj.error("unknown smart punctuation type")),
+            }),
+            _ => None,
+        };
+        if let Some(atom) = atom {
+            self.events.push(atom);
+            return Ok(());
+        }
+
+        let tag = obj.str("tag")?;
+        match tag {
+            "single_quoted" | "double_quoted" => {
+                let (left, right) = if tag == "single_quoted" {
+                    (Event::LeftSingleQuote, Event::RightSingleQuote)
+                } else {
+                    (Event::LeftDoubleQuote, Event::RightDoubleQuote)
+                };
+                self.events.push(left);
+                self.children(&obj, None)?;
+                self.events.push(right);
+                return Ok(());
+            }
+            "definition_list_item" => return self.children(&obj, None),
+            _ => {}
+        }
+
+        let mut attrs = obj.attributes()?;
+        let owned = |s: &str| -> crate::CowStr<'static> { s.to_string().into() };
+        let c = match tag {
+            "para" => Container::Paragraph,
+            "heading" => {
+                let level = obj
+                    .number("level")?
+                    .ok_or_else(|| obj.error("missing field 'level'"))?;
+                Container::Heading {
+                    level: u16::try_from(level).map_err(|_| obj.error("invalid level"))?,
+                    has_section: section.is_some(),
+                    id: owned(
+                        section
+                            .map_or_else(|| obj.id(), |id| Ok(Some(id)))?
+                            .unwrap_or(""),
+                    ),
+                }
+            }
+            "section" => Container::Section {
+                id: owned(obj.id()?.unwrap_or("")),
+            },
+            "div" => {
+                // djot.js stores the class of the div along with the other attributes
+                let class = attrs.get("class").map(ToString::to_string);
+                if class.is_some() {
+                    attrs = attrs
+                        .iter()
+                        .filter(|(k, _)| *k != "class")
+                        .map(|(k, v)| (k.to_string(), v.clone()))
+                        .fold(Attributes::new(), |mut a, (k, v)| {
+                            a.insert(k, v);
+                            a
+                        });
+                }
+                Container::Div {
+                    class: class.unwrap_or_default().into(),
+                }
+            }
+            "block_quote" => Container::Blockquote,
+            "code_block" => Container::CodeBlock {
+                language: owned(obj.str_opt("lang")?.unwrap_or("")),
+            },
+            "raw_block" => Container::RawBlock {
+                format: owned(obj.str("format")?),
+            },
+            "bullet_list" | "ordered_list" | "task_list" => {
+                let kind = match tag {
+                    "bullet_list" => ListKind::Unordered,
+                    "task_list" => ListKind::Task,
+                    _ => {
+                        let (numbering, style) = list_style(obj.str("style")?)
+                            .ok_or_else(|| obj.error("invalid list style"))?;
+                        ListKind::Ordered {
+                            numbering,
+                            style,
+                            start: obj.number("start")?.unwrap_or(1),
+                        }
+                    }
+                };
+                Container::List {
+                    kind,
+                    tight: obj.bool("tight")?,
+                }
+            }
+            "list_item" => Container::ListItem,
+            "task_list_item" => Container::TaskListItem {
+                checked: obj.str("checkbox")? == "checked",
+            },
+            "definition_list" => Container::DescriptionList,
+            "term" => Container::DescriptionTerm,
+            "definition" => Container::DescriptionDetails,
+            "table" => Container::Table,
+            "caption" => Container::Caption,
+            "row" => Container::TableRow {
+                head: obj.bool("head")?,
+            },
+            "cell" => Container::TableCell {
+                alignment: match obj.str_opt("align")?.unwrap_or("default") {
+                    "default" => Alignment::Unspecified,
+                    "left" => Alignment::Left,
+                    "center" => Alignment::Center,
+                    "right" => Alignment::Right,
+                    _ => return Err(obj.error("invalid alignment")),
+                },
+                head: obj.bool("head")?,
+            },
+            "footnote" => Container::Footnote {
+                label: owned(obj.str("label")?),
+            },
+            "reference" => Container::LinkDefinition {
+                label: owned(obj.str("label")?),
+            },
+            "span" => Container::Span,
+            "emph" => Container::Emphasis,
+            "strong" => Container::Strong,
+            "mark" => Container::Mark,
+            "superscript" => Container::Superscript,
+            "subscript" => Container::Subscript,
+            "insert" => Container::Insert,
+            "delete" => Container::Delete,
+            "verbatim" => Container::Verbatim,
+            "inline_math" => Container::Math { display: false },
+            "display_math" => Container::Math { display: true },
+            "raw_inline" => Container::RawInline {
+                format: owned(obj.str("format")?),
+            },
+            "url" => Container::Link(owned(obj.str("text")?), LinkType::AutoLink),
+            "email" => Container::Link(owned(obj.str("text")?), LinkType::Email),
+            "link" | "image" => {
+                let (dst, ty) = if let Some(dst) = obj.str_opt("destination")? {
+                    (dst.to_string(), SpanLinkType::Inline)
+                } else {
+                    let label = obj.str("reference")?;
+                    if let Some((_, url, attrs_def)) =
+                        self.references.iter().find(|(l, ..)| l == label)
+                    {
+                        attrs.union(attrs_def.clone());
+                        (url.clone(), SpanLinkType::Reference)
+                    } else if let Some((_, url)) =
+                        self.auto_references.iter().find(|(l, _)| l == label)
+                    {
+                        (url.clone(), SpanLinkType::Reference)
+                    } else {
+                        (label.to_string(), SpanLinkType::Unresolved)
+                    }
+                };
+                if tag == "link" {
+                    Container::Link(dst.into(), LinkType::Span(ty))
+                } else {
+                    Container::Image(dst.into(), ty)
+                }
+            }
+            _ => return Err(obj.error(&format!("unknown tag '{}'", tag))),
+        };
+
+        self.events.push(Event::Start(c.clone(), attrs));
+        match &c {
+            Container::CodeBlock { .. }
+            | Container::RawBlock { .. }
+            | Container::Verbatim
+            | Container::Math { .. }
+            | Container::RawInline { .. }
+            | Container::Link(_, LinkType::AutoLink | LinkType::Email) => {
+                let text = obj.str("text")?;
+                if !text.is_empty() {
+                    self.events.push(Event::Str(text.to_string().into()));
+                }
+            }
+            Container::LinkDefinition { .. } => {
+                self.events
+                    .push(Event::Str(obj.str("destination")?.to_string().into()));
+            }
+            Container::Section { id } => self.children(&obj, Some(id))?,
+            _ => self.children(&obj, None)?,
+        }
+        self.events.push(Event::End(c));
+        Ok(())
+    }
+}
+
+/// Parse the style of an ordered list, e.g. `1.` or `(a)`.
+fn list_style(s: &str) -> Option<(OrderedListNumbering, OrderedListStyle)> {
+    let (n, style) = if let Some(n) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        (n, ParenParen)
+    } else if let Some(n) = s.strip_suffix(')') {
+        (n, Paren)
+    } else {
+        (s.strip_suffix('.')?, Period)
+    };
+    let numbering = match n {
+        "1" => Decimal,
+        "a" => AlphaLower,
+        "A" => AlphaUpper,
+        "i" => RomanLower,
+        "I" => RomanUpper,
+        _ => return None,
+    };
+    Some((numbering, style))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use super::Renderer;
+    use crate::Event;
+    use crate::Event::*;
+    use crate::Parser;
+    use crate::Render;
+
+    fn render(src: &str) -> String {
+        let mut out = String::new();
+        Renderer::default()
+            .push(Parser::new(src), &mut out)
+            .unwrap();
+        out
+    }
+
+    /// Events without blanklines and escapes, with adjacent strings merged.
+    fn normalize<'s>(events: impl Iterator<Item = Event<'s>>) -> Vec<Event<'s>> {
+        let mut normalized: Vec<Event> = Vec::new();
+        for e in events {
+            match (e, normalized.last_mut()) {
+                (Blankline | Escape, _) => {}
+                (Str(s), Some(Str(prev))) => *prev = format!("{}{}", prev, s).into(),
+                (e, _) => normalized.push(e),
+            }
+        }
+        normalized
+    }
+
+    macro_rules! test_json {
+        ($src:expr, $expected:expr $(,)?) => {
+            let src = $src;
+            let json = render(src);
+            assert_eq!(json, $expected, "\n\nsrc:\n{}\n", src);
+            test_roundtrip!(src);
+        };
+    }
+
+    macro_rules! test_roundtrip {
+        ($src:expr $(,)?) => {
+            let src = $src;
+            let json = render(src);
+            let events = parse(&json).unwrap();
+            assert_eq!(
+                normalize(events.into_iter()),
+                normalize(Parser::new(src)),
+                "\n\njson:\n{}\n",
+                json,
+            );
+        };
+    }
+
+    #[test]
+    fn heading() {
+        test_json!(
+            "# Heading",
+            concat!(
+                r#"{"tag":"doc","references":{},"#,
+                r##""autoReferences":{"Heading":{"tag":"reference","##,
+                r##""label":"Heading","destination":"#Heading"}},"##,
+                r#""footnotes":{},"children":[{"tag":"section","autoAttributes":{"id":"Heading"},"#,
+                r#""children":[{"tag":"heading","level":1,"#,
+                r#""children":[{"tag":"str","text":"Heading"}]}]}]}"#,
+                "\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn lists() {
+        test_json!(
+            "1. a\n2. b",
+            concat!(
+                r#"{"tag":"doc","references":{},"autoReferences":{},"footnotes":{},"children":["#,
+                r#"{"tag":"ordered_list","style":"1.","tight":true,"children":["#,
+                r#"{"tag":"list_item","children":[{"tag":"para","#,
+                r#""children":[{"tag":"str","text":"a"}]}]},"#,
+                r#"{"tag":"list_item","children":[{"tag":"para","#,
+                r#""children":[{"tag":"str","text":"b"}]}]}"#,
+                "]}]}\n",
+            ),
+        );
+        test_json!(
+            "- [x] done",
+            concat!(
+                r#"{"tag":"doc","references":{},"autoReferences":{},"footnotes":{},"children":["#,
+                r#"{"tag":"task_list","tight":true,"#,
+                r#""children":[{"tag":"task_list_item","checkbox":"checked","#,
+                r#""children":[{"tag":"para","children":[{"tag":"str","text":"done"}]}]}]}]}"#,
+                "\n",
+            ),
+        );
+        test_roundtrip!("b) a\nb) b\n\n  c\n\n- x\n\n+ y");
+        test_roundtrip!(": term\n\n  details\n\n: term2\n\n  more");
+    }
+
+    #[test]
+    fn table() {
+        test_json!(
+            "|a|\n|:-|\n|b|",
+            concat!(
+                r#"{"tag":"doc","references":{},"autoReferences":{},"footnotes":{},"children":["#,
+                r#"{"tag":"table","children":["#,
+                r#"{"tag":"row","head":true,"children":[{"tag":"cell","head":true,"align":"left","#,
+                r#""children":[{"tag":"str","text":"a"}]}]},"#,
+                r#"{"tag":"row","head":false,"children":[{"tag":"cell","#,
+                r#""head":false,"align":"left","#,
+                r#""children":[{"tag":"str","text":"b"}]}]}"#,
+                "]}]}\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn reference() {
+        test_json!(
+            "[link][r]\n\n[r]: /url",
+            concat!(
+                r#"{"tag":"doc","#,
+                r#""references":{"r":{"tag":"reference","label":"r","destination":"/url"}},"#,
+                r#""autoReferences":{},"footnotes":{},"children":["#,
+                r#"{"tag":"para","children":[{"tag":"link","reference":"r","#,
+                r#""children":[{"tag":"str","text":"link"}]}]}]}"#,
+                "\n",
+            ),
+        );
+        test_roundtrip!("[a][r]{.own} [b][missing]\n\n[r]: /u\n");
+        test_roundtrip!("# Heading\n\n[c][Heading]");
+    }
+
+    #[test]
+    fn footnote() {
+        test_json!(
+            "a[^n]\n\n[^n]: note",
+            concat!(
+                r#"{"tag":"doc","references":{},"autoReferences":{},"#,
+                r#""footnotes":{"n":{"tag":"footnote","label":"n","#,
+                r#""children":[{"tag":"para","children":[{"tag":"str","text":"note"}]}]}},"#,
+                r#""children":[{"tag":"para","children":[{"tag":"str","text":"a"},"#,
+                r#"{"tag":"footnote_reference","text":"n"}]}]}"#,
+                "\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn inline() {
+        test_json!(
+            "`code`{=html}",
+            concat!(
+                r#"{"tag":"doc","references":{},"autoReferences":{},"footnotes":{},"children":["#,
+                r#"{"tag":"para","children":[{"tag":"raw_inline","#,
+                r#""format":"html","text":"code"}]}]}"#,
+                "\n",
+            ),
+        );
+        test_roundtrip!("_a_ *b* {+c+} {-d-} {=e=} ^f^ ~g~ $`h` $$`i` 'j' \"k\" l--m...\\\n n");
+        test_json!(
+            "'a' don't \"b 'c'\"",
+            concat!(
+                r#"{"tag":"doc","references":{},"autoReferences":{},"footnotes":{},"children":["#,
+                r#"{"tag":"para","children":["#,
+                r#"{"tag":"single_quoted","children":[{"tag":"str","text":"a"}]},"#,
+                r#"{"tag":"str","text":" don"},"#,
+                r#"{"tag":"smart_punctuation","type":"right_single_quote","text":"'"},"#,
+                r#"{"tag":"str","text":"t "},"#,
+                r#"{"tag":"double_quoted","children":[{"tag":"str","text":"b "},"#,
+                r#"{"tag":"single_quoted","children":[{"tag":"str","text":"c"}]}]}"#,
+                "]}]}\n",
+            ),
+        );
+        test_roundtrip!("![img](src.png){#id .class key=\"a \\\"val\\\"\"} <https://x.y> :smile:");
+    }
+
+    #[test]
+    fn blocks() {
+        test_roundtrip!(concat!(
+            "> quote\n",
+            "\n",
+            "* * *\n",
+            "\n",
+            "``` rust\n",
+            "fn main() {}\n",
+            "```\n",
+            "\n",
+            "::: warning\n",
+            "div\n",
+            ":::\n",
+            "\n",
+            "``` =html\n",
+            "<br>\n",
+            "```\n",
+            "\n",
+            "^ caption\n",
+        ));
+    }
+
+    #[test]
+    fn parse_error() {
+        let err = parse(r#"{"tag":"doc","children":[}"#).unwrap_err();
+        assert_eq!(err.offset, 25);
+        let err = parse(r#"{"tag":"doc","children":[{"tag":"unknown"}]}"#).unwrap_err();
+        assert_eq!(err.offset, 25);
+    }
+
+    #[test]
+    fn parse_nested() {
+        let err = parse(&"[".repeat(100_000)).unwrap_err();
+        assert_eq!(err.offset, super::MAX_DEPTH);
+        let mut json = r#"{"tag":"doc","children":["#.to_string();
+        for _ in 0..100_000 {
+            json.push_str(r#"{"tag":"block_quote","children":["#);
+        }
+        let err = parse(&json).unwrap_err();
+        assert_eq!(err.message, "maximum nesting depth exceeded");
+    }
+
+    #[test]
+    fn parse_surrogates() {
+        for (src, offset) in [
+            (r#""\ud800\ud800""#, 7),
+            (r#""\ud800x""#, 7),
+            (r#""\udc00""#, 1),
+            (r#""\ud800""#, 7),
+        ] {
+            let err = parse(src).unwrap_err();
+            assert_eq!(
+                (err.message.as_str(), err.offset),
+                ("unpaired surrogate", offset),
+                "{}",
+                src,
+            );
+        }
+        let err = parse(r#""\u+abc""#).unwrap_err();
+        assert_eq!(err.message, "invalid unicode escape");
+        let json = concat!(
+            r#"{"tag":"doc","children":[{"tag":"para","children":["#,
+            r#"{"tag":"str","text":"\ud834\udd1e"}]}]}"#,
+        );
+        assert_eq!(parse(json).unwrap()[1], Event::Str("\u{1d11e}".into()));
+    }
+}
@@ -3,11 +3,19 @@
 //! The main entry is through [`Parser`] which implements an [`Iterator`] of [`Event`]s. The events
 //! can then be used to traverse the document structure in order to e.g. construct an AST or
 //! directly generate to some output format. This crate provides an [`html`] module that can be
-//! used to render the events to HTML.
+//! used to render the events to HTML, a [`djot`] module that can be used to render them back to
+//! Djot, and an [`ast`] module that can be used to build a tree of the events. The [`filter`]
+//! module provides adapters that may be used to modify the events before they are rendered.
 //!
 //! # Feature flags
 //!
 //! - `html` (default): build the html module and a binary that converts djot to HTML.
+//! - `djot` (default): build the djot module.
+//! - `highlight`: build the highlight module, a syntax highlighter for the html renderer.
+//! - `math`: build the math module, for rendering LaTeX math as MathML with the html renderer.
+//! - `json`: build the json module, for exchanging documents with djot.js.
+//! - `serde`: implement `Serialize` and `Deserialize` from [serde](https://serde.rs) for
+//!   [`Event`] and the types it contains.
 //!
 //! # Examples
 //!
@@ -49,19 +57,39 @@
 #![allow(clippy::blocks_in_if_conditions)]
 
 use std::fmt;
-use std::fmt::Write as FmtWrite;
 use std::io;
 use std::ops::Range;
 
+pub mod ast;
+#[cfg(feature = "djot")]
+pub mod djot;
+pub mod filter;
+#[cfg(feature = "highlight")]
+pub mod highlight;
 #[cfg(feature = "html")]
 pub mod html;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "math")]
+pub mod math;
 
 mod attr;
 mod block;
+mod diagnostics;
 mod inline;
 mod lex;
-
-pub use attr::{AttributeValue, AttributeValueParts, Attributes};
+mod line_index;
+mod references;
+mod slug;
+
+pub use attr::{
+    AttributeComment, AttributeError, AttributeErrorKind, AttributeValue, AttributeValueParts,
+    Attributes,
+};
+pub use diagnostics::{Diagnostic, DiagnosticKind, Severity};
+pub use line_index::{LineColumn, LineIndex};
+pub use references::{FootnoteDefinition, LinkDefinition, Reference, ReferenceKind, Resolution};
+pub use slug::{DefaultSlugger, Slugger};
 
 type CowStr<'s> = std::borrow::Cow<'s, str>;
 
@@ -198,6 +226,7 @@ impl<'s> AsRef<Event<'s>> for &Event<'s> {
 /// events representing its content, and finally a [`Event::End`]. Atomic elements without any
 /// inside elements are represented by a single event.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event<'s> {
     /// Start of a container.
     Start(Container<'s>, Attributes<'s>),
@@ -206,7 +235,7 @@ pub enum Event<'s> {
     /// A string object, text only.
     Str(CowStr<'s>),
     /// A footnote reference.
-    FootnoteReference(&'s str),
+    FootnoteReference(CowStr<'s>),
     /// A symbol, by default rendered literally but may be treated specially.
     Symbol(CowStr<'s>),
     /// Left single quotation mark.
@@ -237,6 +266,34 @@ pub enum Event<'s> {
     ThematicBreak(Attributes<'s>),
 }
 
+impl<'s> Event<'s> {
+    /// Convert the event into one that owns all of its data, i.e. that does not borrow from the
+    /// input.
+    #[must_use]
+    pub fn into_owned(self) -> Event<'static> {
+        match self {
+            Self::Start(c, attrs) => Event::Start(c.into_owned(), attrs.into_owned()),
+            Self::End(c) => Event::End(c.into_owned()),
+            Self::Str(s) => Event::Str(s.into_owned().into()),
+            Self::FootnoteReference(s) => Event::FootnoteReference(s.into_owned().into()),
+            Self::Symbol(s) => Event::Symbol(s.into_owned().into()),
+            Self::LeftSingleQuote => Event::LeftSingleQuote,
+            Self::RightSingleQuote => Event::RightSingleQuote,
+            Self::LeftDoubleQuote => Event::LeftDoubleQuote,
+            Self::RightDoubleQuote => Event::RightDoubleQuote,
+            Self::Ellipsis => Event::Ellipsis,
+            Self::EnDash => Event::EnDash,
+            Self::EmDash => Event::EmDash,
+            Self::NonBreakingSpace => Event::NonBreakingSpace,
+            Self::Softbreak => Event::Softbreak,
+            Self::Hardbreak => Event::Hardbreak,
+            Self::Escape => Event::Escape,
+            Self::Blankline => Event::Blankline,
+            Self::ThematicBreak(attrs) => Event::ThematicBreak(attrs.into_owned()),
+        }
+    }
+}
+
 /// A container that may contain other elements.
 ///
 /// There are three types of containers:
@@ -245,6 +302,7 @@ pub enum Event<'s> {
 /// - block leaf, may only contain inline elements,
 /// - block container, may contain any block-level elements.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Container<'s> {
     /// A blockquote element.
     Blockquote,
@@ -259,7 +317,7 @@ pub enum Container<'s> {
     /// Details describing a term within a description list.
     DescriptionDetails,
     /// A footnote definition.
-    Footnote { label: &'s str },
+    Footnote { label: CowStr<'s> },
     /// A table element.
     Table,
     /// A row element of a table.
@@ -267,7 +325,7 @@ pub enum Container<'s> {
     /// A section belonging to a top level heading.
     Section { id: CowStr<'s> },
     /// A block-level divider element.
-    Div { class: &'s str },
+    Div { class: CowStr<'s> },
     /// A paragraph.
     Paragraph,
     /// A heading.
@@ -283,11 +341,11 @@ pub enum Container<'s> {
     /// A term within a description list.
     DescriptionTerm,
     /// A link definition.
-    LinkDefinition { label: &'s str },
+    LinkDefinition { label: CowStr<'s> },
     /// A block with raw markup for a specific output format.
-    RawBlock { format: &'s str },
+    RawBlock { format: CowStr<'s> },
     /// A block with code in a specific language.
-    CodeBlock { language: &'s str },
+    CodeBlock { language: CowStr<'s> },
     /// An inline divider element.
     Span,
     /// An inline link, the first field is either a destination URL or an unresolved tag.
@@ -300,7 +358,7 @@ pub enum Container<'s> {
     /// An inline or display math element.
     Math { display: bool },
     /// Inline raw markup for a specific output format.
-    RawInline { format: &'s str },
+    RawInline { format: CowStr<'s> },
     /// A subscripted element.
     Subscript,
     /// A superscripted element.
@@ -395,10 +453,73 @@ impl<'s> Container<'s> {
             | Self::Mark => false,
         }
     }
+
+    /// Convert the container into one that owns all of its data, i.e. that does not borrow from
+    /// the input.
+    #[must_use]
+    pub fn into_owned(self) -> Container<'static> {
+        match self {
+            Self::Blockquote => Container::Blockquote,
+            Self::List { kind, tight } => Container::List { kind, tight },
+            Self::ListItem => Container::ListItem,
+            Self::TaskListItem { checked } => Container::TaskListItem { checked },
+            Self::DescriptionList => Container::DescriptionList,
+            Self::DescriptionDetails => Container::DescriptionDetails,
+            Self::Footnote { label } => Container::Footnote {
+                label: label.into_owned().into(),
+            },
+            Self::Table => Container::Table,
+            Self::TableRow { head } => Container::TableRow { head },
+            Self::Section { id } => Container::Section {
+                id: id.into_owned().into(),
+            },
+            Self::Div { class } => Container::Div {
+                class: class.into_owned().into(),
+            },
+            Self::Paragraph => Container::Paragraph,
+            Self::Heading {
+                level,
+                has_section,
+                id,
+            } => Container::Heading {
+                level,
+                has_section,
+                id: id.into_owned().into(),
+            },
+            Self::TableCell { alignment, head } => Container::TableCell { alignment, head },
+            Self::Caption => Container::Caption,
+            Self::DescriptionTerm => Container::DescriptionTerm,
+            Self::LinkDefinition { label } => Container::LinkDefinition {
+                label: label.into_owned().into(),
+            },
+            Self::RawBlock { format } => Container::RawBlock {
+                format: format.into_owned().into(),
+            },
+            Self::CodeBlock { language } => Container::CodeBlock {
+                language: language.into_owned().into(),
+            },
+            Self::Span => Container::Span,
+            Self::Link(dst, ty) => Container::Link(dst.into_owned().into(), ty),
+            Self::Image(src, ty) => Container::Image(src.into_owned().into(), ty),
+            Self::Verbatim => Container::Verbatim,
+            Self::Math { display } => Container::Math { display },
+            Self::RawInline { format } => Container::RawInline {
+                format: format.into_owned().into(),
+            },
+            Self::Subscript => Container::Subscript,
+            Self::Superscript => Container::Superscript,
+            Self::Insert => Container::Insert,
+            Self::Delete => Container::Delete,
+            Self::Strong => Container::Strong,
+            Self::Emphasis => Container::Emphasis,
+            Self::Mark => Container::Mark,
+        }
+    }
 }
 
 /// Alignment of a table column.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Alignment {
     Unspecified,
     Left,
@@ -408,6 +529,7 @@ pub enum Alignment {
 
 /// The type of an inline span link.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SpanLinkType {
     /// E.g. `[text](url)`
     Inline,
@@ -419,6 +541,7 @@ pub enum SpanLinkType {
 
 /// The type of an inline link.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LinkType {
     /// E.g. `[text](url)`.
     Span(SpanLinkType),
@@ -430,6 +553,7 @@ pub enum LinkType {
 
 /// The type of a list.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ListKind {
     /// A bullet list.
     Unordered,
@@ -445,6 +569,7 @@ pub enum ListKind {
 
 /// Numbering type of an ordered list.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OrderedListNumbering {
     /// Decimal numbering, e.g. `1)`.
     Decimal,
@@ -460,6 +585,7 @@ pub enum OrderedListNumbering {
 
 /// Style of an ordered list.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OrderedListStyle {
     /// Number is followed by a period, e.g. `1.`.
     Period,
@@ -569,12 +695,45 @@ pub struct Parser<'s> {
 
     /// Inline parser.
     inline_parser: inline::Parser<'s>,
+
+    /// Options that configure the parsing.
+    options: ParserOptions,
+
+    /// Callback to resolve references that have no definition.
+    broken_reference_callback: Option<BrokenReferenceCallback<'s>>,
+    /// Destinations given by the callback for currently open references.
+    broken_references: Vec<Option<CowStr<'s>>>,
+
+    /// References to links and images that have been resolved, if they are being tracked.
+    references: Option<Vec<Reference<'s>>>,
 }
 
-#[derive(Clone)]
-struct Heading {
+type BrokenReferenceCallback<'s> = std::sync::Arc<
+    dyn Fn(BrokenReference) -> Option<(CowStr<'s>, Attributes<'s>)> + Send + Sync + 's,
+>;
+
+/// A reference link or image whose tag matches neither a link definition nor a heading.
+///
+/// See [`Parser::broken_reference_callback`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenReference<'a> {
+    /// The tag of the reference, e.g. `Some Page` in `[Some Page][]`.
+    pub tag: &'a str,
+    /// The location of the whole reference within the input.
+    pub span: Range<usize>,
+    /// The reference is an image rather than a link.
+    pub image: bool,
+}
+
+/// A heading within a document, see [`Parser::headings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Level of the heading, from 1 and up.
+    level: u16,
     /// Location of heading in src.
     location: u32,
+    /// End of heading in src.
+    end: u32,
     /// Automatically generated id from heading text.
     id_auto: String,
     /// Text of heading, formatting stripped.
@@ -583,11 +742,108 @@ struct Heading {
     id_override: Option<String>,
 }
 
+impl Heading {
+    /// The level of the heading, i.e. the number of `#` characters.
+    #[must_use]
+    pub fn level(&self) -> u16 {
+        self.level
+    }
+
+    /// The id of the heading, as used by [`Container::Heading`] and [`Container::Section`].
+    ///
+    /// This is the explicit id of the heading, if any, otherwise the generated one. It is empty if
+    /// the heading has no explicit id and [`ParserOptions::heading_ids`] is disabled.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        self.id_override.as_ref().unwrap_or(&self.id_auto)
+    }
+
+    /// The text of the heading, with formatting removed and soft breaks replaced by spaces.
+    ///
+    /// This is also the tag that references the heading, e.g. `[Some Heading][]`.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The location of the heading within the input, as given by [`Parser::into_offset_iter`] from
+    /// the start of its [`Event::Start`] to the end of its [`Event::End`].
+    #[must_use]
+    pub fn span(&self) -> Range<usize> {
+        self.location as usize..self.end as usize
+    }
+}
+
+/// Options that configure the behavior of a [`Parser`].
+///
+/// All options are enabled by default.
+///
+/// # Examples
+///
+/// Disable sections and smart punctuation:
+///
+/// ```
+/// # use jotdown::*;
+/// let options = ParserOptions {
+///     sections: false,
+///     smart_punctuation: false,
+///     ..ParserOptions::default()
+/// };
+/// assert_eq!(
+///     Parser::with_options("# 'a'", options).collect::<Vec<_>>(),
+///     &[
+///         Event::Start(
+///             Container::Heading {
+///                 level: 1,
+///                 has_section: false,
+///                 id: "a".into(),
+///             },
+///             Attributes::new(),
+///         ),
+///         Event::Str("'".into()),
+///         Event::Str("a".into()),
+///         Event::Str("'".into()),
+///         Event::End(Container::Heading {
+///             level: 1,
+///             has_section: false,
+///             id: "a".into(),
+///         }),
+///     ],
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Wrap top-level headings and their content in [`Container::Section`] elements.
+    pub sections: bool,
+    /// Generate ids from the text of headings that have no explicit id.
+    pub heading_ids: bool,
+    /// Turn quotes, ellipses and dashes into [`Event::LeftDoubleQuote`], [`Event::Ellipsis`],
+    /// [`Event::EnDash`] etc. If disabled, they are emitted as [`Event::Str`] instead.
+    pub smart_punctuation: bool,
+    /// Resolve references without a link definition to headings with the same text.
+    pub heading_references: bool,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            sections: true,
+            heading_ids: true,
+            smart_punctuation: true,
+            heading_references: true,
+        }
+    }
+}
+
 /// Because of potential future references, an initial pass is required to obtain all definitions.
 #[derive(Clone)]
 struct PrePass<'s> {
     /// Link definitions and their attributes.
-    link_definitions: Map<&'s str, (CowStr<'s>, attr::Attributes<'s>)>,
+    link_definitions: Map<CowStr<'s>, (CowStr<'s>, attr::Attributes<'s>)>,
+    /// All link definitions within the document, in the order they appear.
+    link_definitions_doc: Vec<LinkDefinition<'s>>,
+    /// All footnotes within the document, in the order they appear.
+    footnotes: Vec<FootnoteDefinition<'s>>,
     /// Cache of all heading ids.
     headings: Vec<Heading>,
     /// Indices to headings sorted lexicographically.
@@ -600,10 +856,14 @@ impl<'s> PrePass<'s> {
         src: &'s str,
         mut blocks: std::slice::Iter<block::Event<'s>>,
         inline_parser: &mut inline::Parser<'s>,
+        options: &ParserOptions,
+        slugger: &mut dyn Slugger,
     ) -> Self {
         let mut link_definitions = Map::new();
+        let mut link_definitions_doc = Vec::new();
+        let mut footnotes = Vec::new();
+        let mut footnotes_open = Vec::new();
         let mut headings: Vec<Heading> = Vec::new();
-        let mut used_ids: Set<String> = Set::new();
 
         let mut attr_prev: Option<Range<usize>> = None;
         while let Some(e) = blocks.next() {
@@ -616,42 +876,49 @@ impl<'s> PrePass<'s> {
                     let attrs = attr_prev
                         .as_ref()
                         .map_or_else(Attributes::new, |sp| attr::parse(&src[sp.clone()]));
-                    let url = if let Some(block::Event {
-                        kind: block::EventKind::Inline,
-                        span,
-                    }) = blocks.next()
-                    {
-                        let start =
-                            src[span.clone()].trim_matches(|c: char| c.is_ascii_whitespace());
-                        if let Some(block::Event {
-                            kind: block::EventKind::Inline,
-                            span,
-                        }) = blocks.next()
-                        {
-                            let mut url = start.to_string();
-                            url.push_str(
-                                src[span.clone()].trim_matches(|c: char| c.is_ascii_whitespace()),
-                            );
-                            while let Some(block::Event {
-                                kind: block::EventKind::Inline,
-                                span,
-                            }) = blocks.next()
-                            {
-                                url.push_str(
-                                    src[span.clone()]
-                                        .trim_matches(|c: char| c.is_ascii_whitespace()),
-                                );
-                            }
-                            url.into() // owned
+                    let mut url: CowStr = "".into();
+                    let mut end = e.span.end;
+                    for e in blocks.by_ref() {
+                        end = e.span.end;
+                        if !matches!(e.kind, block::EventKind::Inline) {
+                            break;
+                        }
+                        let line =
+                            src[e.span.clone()].trim_matches(|c: char| c.is_ascii_whitespace());
+                        if url.is_empty() {
+                            url = line.into(); // borrowed
                         } else {
-                            start.into() // borrowed
+                            url.to_mut().push_str(line); // owned
                         }
-                    } else {
-                        "".into() // static
-                    };
-                    link_definitions.insert(label, (url, attrs));
+                    }
+                    link_definitions_doc.push(LinkDefinition {
+                        label: (*label).into(),
+                        url: url.clone(),
+                        attrs: attrs.clone(),
+                        span: e.span.start..end,
+                    });
+                    link_definitions.insert(label.into(), (url, attrs));
+                }
+                block::EventKind::Enter(block::Node::Container(block::Container::Footnote {
+                    label,
+                })) => {
+                    footnotes_open.push(footnotes.len());
+                    footnotes.push(FootnoteDefinition {
+                        label: (*label).into(),
+                        span: e.span.clone(),
+                    });
+                }
+                block::EventKind::Exit(block::Node::Container(block::Container::Footnote {
+                    ..
+                })) => {
+                    if let Some(i) = footnotes_open.pop() {
+                        footnotes[i].span.end = e.span.end;
+                    }
+                    attr_prev = None;
                 }
-                block::EventKind::Enter(block::Node::Leaf(block::Leaf::Heading { .. })) => {
+                block::EventKind::Enter(block::Node::Leaf(block::Leaf::Heading {
+                    level, ..
+                })) => {
                     // All headings ids have to be obtained initially, as references can appear
                     // before the heading. Additionally, determining the id requires inline parsing
                     // as formatting must be removed.
@@ -663,13 +930,15 @@ impl<'s> PrePass<'s> {
                         .and_then(|attrs| attrs.get("id"))
                         .map(ToString::to_string);
 
-                    let mut id_auto = String::new();
                     let mut text = String::new();
-                    let mut last_whitespace = true;
+                    // text given to the slugger, where soft breaks are the only newlines
+                    let mut text_slug = String::new();
                     inline_parser.reset();
                     let mut last_end = 0;
+                    let mut end = e.span.end;
                     loop {
                         let span_inline = blocks.next().and_then(|e| {
+                            end = e.span.end;
                             if matches!(e.kind, block::EventKind::Inline) {
                                 last_end = e.span.end;
                                 Some(e.span.clone())
@@ -683,29 +952,14 @@ impl<'s> PrePass<'s> {
                         );
                         inline_parser.for_each(|ev| match ev.kind {
                             inline::EventKind::Str => {
-                                text.push_str(&src[ev.span.clone()]);
-                                let mut chars = src[ev.span].chars().peekable();
-                                while let Some(c) = chars.next() {
-                                    if c.is_ascii_whitespace() {
-                                        while chars
-                                            .peek()
-                                            .map_or(false, |c| c.is_ascii_whitespace())
-                                        {
-                                            chars.next();
-                                        }
-                                        if !last_whitespace {
-                                            last_whitespace = true;
-                                            id_auto.push('-');
-                                        }
-                                    } else if !c.is_ascii_punctuation() || matches!(c, '-' | '_') {
-                                        id_auto.push(c);
-                                        last_whitespace = false;
-                                    }
-                                }
+                                let s = &src[ev.span];
+                                text.push_str(s);
+                                text_slug
+                                    .extend(s.chars().map(|c| if c == '\n' { ' ' } else { c }));
                             }
                             inline::EventKind::Atom(inline::Atom::Softbreak) => {
                                 text.push(' ');
-                                id_auto.push('-');
+                                text_slug.push('\n');
                             }
                             _ => {}
                         });
@@ -713,27 +967,17 @@ impl<'s> PrePass<'s> {
                             break;
                         }
                     }
-                    id_auto.drain(id_auto.trim_end_matches('-').len()..);
-
-                    // ensure id unique
-                    if used_ids.contains::<str>(&id_auto) || id_auto.is_empty() {
-                        if id_auto.is_empty() {
-                            id_auto.push('s');
-                        }
-                        let mut num = 1;
-                        id_auto.push('-');
-                        let i_num = id_auto.len();
-                        write!(id_auto, "{}", num).unwrap();
-                        while used_ids.contains::<str>(&id_auto) {
-                            num += 1;
-                            id_auto.drain(i_num..);
-                            write!(id_auto, "{}", num).unwrap();
-                        }
-                    }
 
-                    used_ids.insert(id_auto.clone());
+                    let id_auto = if options.heading_ids {
+                        slugger.slug(&text_slug)
+                    } else {
+                        // only explicit ids are used
+                        String::new()
+                    };
                     headings.push(Heading {
+                        level,
                         location: e.span.start as u32,
+                        end: end as u32,
                         id_auto,
                         text,
                         id_override,
@@ -757,14 +1001,15 @@ impl<'s> PrePass<'s> {
 
         Self {
             link_definitions,
+            link_definitions_doc,
+            footnotes,
             headings,
             headings_lex,
         }
     }
 
     fn heading_id(&self, i: usize) -> &str {
-        let h = &self.headings[i];
-        h.id_override.as_ref().unwrap_or(&h.id_auto)
+        self.headings[i].id()
     }
 
     fn heading_id_by_location(&self, location: u32) -> Option<&str> {
@@ -779,15 +1024,34 @@ impl<'s> PrePass<'s> {
             .binary_search_by_key(&tag, |i| &self.headings[*i].text)
             .ok()
             .map(|i| self.heading_id(self.headings_lex[i]))
+            .filter(|id| !id.is_empty())
     }
 }
 
 impl<'s> Parser<'s> {
     #[must_use]
     pub fn new(src: &'s str) -> Self {
+        Self::with_options(src, ParserOptions::default())
+    }
+
+    /// Create a parser whose behavior is configured by the given [`ParserOptions`].
+    #[must_use]
+    pub fn with_options(src: &'s str, options: ParserOptions) -> Self {
+        Self::with_slugger(src, options, DefaultSlugger::default())
+    }
+
+    /// Create a parser that uses the given [`Slugger`] to generate ids for headings.
+    #[must_use]
+    pub fn with_slugger<S: Slugger>(src: &'s str, options: ParserOptions, mut slugger: S) -> Self {
         let blocks = block::parse(src);
         let mut inline_parser = inline::Parser::new(src);
-        let pre_pass = PrePass::new(src, blocks.iter(), &mut inline_parser);
+        let pre_pass = PrePass::new(
+            src,
+            blocks.iter(),
+            &mut inline_parser,
+            &options,
+            &mut slugger,
+        );
 
         Self {
             src,
@@ -798,9 +1062,199 @@ impl<'s> Parser<'s> {
             table_head_row: false,
             verbatim: false,
             inline_parser,
+            options,
+            broken_reference_callback: None,
+            broken_references: Vec::new(),
+            references: None,
         }
     }
 
+    /// Set a callback that is called for each reference link or image whose tag matches neither a
+    /// link definition nor a heading.
+    ///
+    /// If the callback returns a destination, the reference is emitted as a
+    /// [`SpanLinkType::Reference`] to it, along with the returned attributes, as if a matching
+    /// link definition existed. Otherwise, it is emitted as [`SpanLinkType::Unresolved`].
+    ///
+    /// The callback is shared by all clones of the parser, e.g. the ones used by
+    /// [`Parser::references`] and [`Parser::diagnostics`], so it may be called several times for
+    /// the same reference and has to be [`Fn`]. It also has to be [`Send`] and [`Sync`] so that the
+    /// parser remains so. Use e.g. a [`std::sync::Mutex`] if the callback needs mutable state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let events = Parser::new("[Some Page][]")
+    ///     .broken_reference_callback(|r| {
+    ///         if r.tag == "Some Page" {
+    ///             Some(("/wiki/some-page".into(), Attributes::new()))
+    ///         } else {
+    ///             None
+    ///         }
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     events[1],
+    ///     Event::Start(
+    ///         Container::Link(
+    ///             "/wiki/some-page".into(),
+    ///             LinkType::Span(SpanLinkType::Reference),
+    ///         ),
+    ///         Attributes::new(),
+    ///     ),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn broken_reference_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(BrokenReference) -> Option<(CowStr<'s>, Attributes<'s>)> + Send + Sync + 's,
+    {
+        self.broken_reference_callback = Some(std::sync::Arc::new(callback));
+        self
+    }
+
+    /// Add link definitions that are defined outside of the document, e.g. definitions that are
+    /// shared between multiple documents.
+    ///
+    /// The definitions are used as a fallback, definitions within the document take precedence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let shared = [("jira", ("https://jira.example.com", Attributes::new()))];
+    /// let events = Parser::new("[JIRA-123][jira]")
+    ///     .external_link_definitions(shared)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     events[1],
+    ///     Event::Start(
+    ///         Container::Link(
+    ///             "https://jira.example.com".into(),
+    ///             LinkType::Span(SpanLinkType::Reference),
+    ///         ),
+    ///         Attributes::new(),
+    ///     ),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn external_link_definitions<I, K, U>(mut self, definitions: I) -> Self
+    where
+        I: IntoIterator<Item = (K, (U, Attributes<'s>))>,
+        K: Into<CowStr<'s>>,
+        U: Into<CowStr<'s>>,
+    {
+        for (label, (url, attrs)) in definitions {
+            self.pre_pass
+                .link_definitions
+                .entry(label.into())
+                .or_insert_with(|| (url.into(), attrs));
+        }
+        self
+    }
+
+    /// All headings within the document, in the order they appear.
+    ///
+    /// The headings are obtained when the parser is created, so they are available before any
+    /// events have been consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let src = "# Some *heading*\n\n{#explicit}\n## Another heading\n";
+    /// let parser = Parser::new(src);
+    /// let headings = parser.headings();
+    /// assert_eq!(headings.len(), 2);
+    /// assert_eq!(headings[0].level(), 1);
+    /// assert_eq!(headings[0].id(), "Some-heading");
+    /// assert_eq!(headings[0].text(), "Some heading");
+    /// assert_eq!(&src[headings[0].span()], "# Some *heading*\n");
+    /// assert_eq!(headings[1].level(), 2);
+    /// assert_eq!(headings[1].id(), "explicit");
+    /// assert_eq!(headings[1].text(), "Another heading");
+    /// ```
+    #[must_use]
+    pub fn headings(&self) -> &[Heading] {
+        &self.pre_pass.headings
+    }
+
+    /// All link definitions within the document, in the order they appear.
+    ///
+    /// Definitions given by [`Parser::external_link_definitions`] are not included. If several
+    /// definitions have the same label, the last one is used by references.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let src = "[text][tag]\n\n[tag]: https://example.com\n";
+    /// let parser = Parser::new(src);
+    /// let defs = parser.link_definitions();
+    /// assert_eq!(defs.len(), 1);
+    /// assert_eq!(defs[0].label, "tag");
+    /// assert_eq!(defs[0].url, "https://example.com");
+    /// assert_eq!(&src[defs[0].span.clone()], "[tag]: https://example.com\n");
+    /// ```
+    #[must_use]
+    pub fn link_definitions(&self) -> &[LinkDefinition<'s>] {
+        &self.pre_pass.link_definitions_doc
+    }
+
+    /// All footnotes within the document, in the order they appear.
+    #[must_use]
+    pub fn footnote_definitions(&self) -> &[FootnoteDefinition<'s>] {
+        &self.pre_pass.footnotes
+    }
+
+    /// All reference links, reference images and footnote references within the document, in the
+    /// order they appear, along with what they have been resolved to.
+    ///
+    /// The remaining input is parsed to find the references, so this should typically be called
+    /// before any events have been consumed.
+    ///
+    /// # Examples
+    ///
+    /// Find unused footnotes:
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let parser = Parser::new("text[^a]\n\n[^a]: a\n\n[^b]: b\n");
+    /// let references = parser.references();
+    /// let unused = parser
+    ///     .footnote_definitions()
+    ///     .iter()
+    ///     .filter(|f| !references.iter().any(|r| r.tag == f.label))
+    ///     .map(|f| f.label.as_ref())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(unused, &["b"]);
+    /// ```
+    #[must_use]
+    pub fn references(&self) -> Vec<Reference<'s>> {
+        references::collect(self.clone())
+    }
+
+    /// Find potential problems within the document, e.g. references that cannot be resolved.
+    ///
+    /// The remaining input is parsed to find the problems, so this should typically be called
+    /// before any events have been consumed. The diagnostics are sorted by their location.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let diagnostics = Parser::new("[text][tag]").diagnostics();
+    /// assert_eq!(diagnostics.len(), 1);
+    /// assert_eq!(diagnostics[0].severity, Severity::Warning);
+    /// assert_eq!(diagnostics[0].span, 0..11);
+    /// assert_eq!(diagnostics[0].to_string(), "unresolved reference 'tag'");
+    /// ```
+    #[must_use]
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        diagnostics::collect(self.clone())
+    }
+
     /// Turn the [`Parser`] into an iterator of tuples, each with an [`Event`] and a start/end byte
     /// offset for its corresponding input (as a [`Range<usize>`]).
     ///
@@ -934,7 +1388,9 @@ impl<'s> Parser<'s> {
                         inline::Container::Verbatim => Container::Verbatim,
                         inline::Container::InlineMath => Container::Math { display: false },
                         inline::Container::DisplayMath => Container::Math { display: true },
-                        inline::Container::RawFormat { format } => Container::RawInline { format },
+                        inline::Container::RawFormat { format } => Container::RawInline {
+                            format: format.into(),
+                        },
                         inline::Container::Subscript => Container::Subscript,
                         inline::Container::Superscript => Container::Superscript,
                         inline::Container::Insert => Container::Insert,
@@ -959,16 +1415,73 @@ impl<'s> Parser<'s> {
                                 .get::<str>(tag.as_ref())
                                 .cloned();
 
-                            let (url_or_tag, ty) = if let Some((url, attrs_def)) = link_def {
+                            let (url_or_tag, ty, resolution) = if let Some((url, attrs_def)) =
+                                link_def
+                            {
                                 attributes.union(attrs_def);
-                                (url, SpanLinkType::Reference)
+                                (url, SpanLinkType::Reference, Resolution::Definition)
+                            } else if let Some(id) = self
+                                .pre_pass
+                                .heading_id_by_tag(tag.as_ref())
+                                .filter(|_| self.options.heading_references)
+                            {
+                                (
+                                    format!("#{}", id).into(),
+                                    SpanLinkType::Reference,
+                                    Resolution::Heading {
+                                        id: id.to_string().into(),
+                                    },
+                                )
+                            } else if let Some(callback) = &self.broken_reference_callback {
+                                let url = if enter {
+                                    let image = matches!(c, inline::Container::ReferenceImage(..));
+                                    let end = self.inline_parser.container_end(c);
+                                    let url = callback(BrokenReference {
+                                        tag: tag.as_ref(),
+                                        span: inline.span.start..end.unwrap_or(inline.span.end),
+                                        image,
+                                    })
+                                    .map(|(url, attrs)| {
+                                        attributes.union(attrs);
+                                        url
+                                    });
+                                    self.broken_references.push(url.clone());
+                                    url
+                                } else {
+                                    self.broken_references.pop().flatten()
+                                };
+                                url.map_or_else(
+                                    || {
+                                        (
+                                            tag.clone(),
+                                            SpanLinkType::Unresolved,
+                                            Resolution::Unresolved,
+                                        )
+                                    },
+                                    |url| (url, SpanLinkType::Reference, Resolution::Callback),
+                                )
                             } else {
-                                self.pre_pass.heading_id_by_tag(tag.as_ref()).map_or_else(
-                                    || (tag.clone(), SpanLinkType::Unresolved),
-                                    |id| (format!("#{}", id).into(), SpanLinkType::Reference),
+                                (
+                                    tag.clone(),
+                                    SpanLinkType::Unresolved,
+                                    Resolution::Unresolved,
                                 )
                             };
 
+                            if let (true, Some(references)) = (enter, &mut self.references) {
+                                let end = self.inline_parser.container_end(c);
+                                references.push(Reference {
+                                    kind: if matches!(c, inline::Container::ReferenceLink(..)) {
+                                        ReferenceKind::Link
+                                    } else {
+                                        ReferenceKind::Image
+                                    },
+                                    tag: tag.clone(),
+                                    span: inline.span.start..end.unwrap_or(inline.span.end),
+                                    resolution,
+                                });
+                            }
+
                             if matches!(c, inline::Container::ReferenceLink(..)) {
                                 Container::Link(url_or_tag, LinkType::Span(ty))
                             } else {
@@ -991,8 +1504,21 @@ impl<'s> Parser<'s> {
                     }
                 }
                 inline::EventKind::Atom(a) => match a {
-                    inline::Atom::FootnoteReference { label } => Event::FootnoteReference(label),
+                    inline::Atom::FootnoteReference { label } => {
+                        Event::FootnoteReference(label.into())
+                    }
                     inline::Atom::Symbol(sym) => Event::Symbol(sym.into()),
+                    inline::Atom::Quote { ty, .. } if !self.options.smart_punctuation => {
+                        Event::Str(match ty {
+                            inline::QuoteType::Single => "'".into(),
+                            inline::QuoteType::Double => "\"".into(),
+                        })
+                    }
+                    inline::Atom::Ellipsis | inline::Atom::EnDash | inline::Atom::EmDash
+                        if !self.options.smart_punctuation =>
+                    {
+                        Event::Str(self.src[inline.span.clone()].into())
+                    }
                     inline::Atom::Quote { ty, left } => match (ty, left) {
                         (inline::QuoteType::Single, true) => Event::LeftSingleQuote,
                         (inline::QuoteType::Single, false) => Event::RightSingleQuote,
@@ -1035,6 +1561,12 @@ impl<'s> Parser<'s> {
                         continue;
                     }
                 },
+                block::EventKind::Enter(block::Node::Container(block::Container::Section {
+                    ..
+                }))
+                | block::EventKind::Exit(block::Node::Container(block::Container::Section {
+                    ..
+                })) if !self.options.sections => continue,
                 block::EventKind::Enter(c) | block::EventKind::Exit(c) => {
                     let enter = matches!(ev.kind, block::EventKind::Enter(..));
                     let cont = match c {
@@ -1048,7 +1580,7 @@ impl<'s> Parser<'s> {
                                     pos,
                                 } => Container::Heading {
                                     level,
-                                    has_section,
+                                    has_section: has_section && self.options.sections,
                                     id: self
                                         .pre_pass
                                         .heading_id_by_location(pos)
@@ -1060,9 +1592,13 @@ impl<'s> Parser<'s> {
                                 block::Leaf::CodeBlock { language } => {
                                     self.verbatim = enter;
                                     if let Some(format) = language.strip_prefix('=') {
-                                        Container::RawBlock { format }
+                                        Container::RawBlock {
+                                            format: format.into(),
+                                        }
                                     } else {
-                                        Container::CodeBlock { language }
+                                        Container::CodeBlock {
+                                            language: language.into(),
+                                        }
                                     }
                                 }
                                 block::Leaf::TableCell(alignment) => Container::TableCell {
@@ -1072,14 +1608,20 @@ impl<'s> Parser<'s> {
                                 block::Leaf::Caption => Container::Caption,
                                 block::Leaf::LinkDefinition { label } => {
                                     self.verbatim = enter;
-                                    Container::LinkDefinition { label }
+                                    Container::LinkDefinition {
+                                        label: label.into(),
+                                    }
                                 }
                             }
                         }
                         block::Node::Container(c) => match c {
                             block::Container::Blockquote => Container::Blockquote,
-                            block::Container::Div { class } => Container::Div { class },
-                            block::Container::Footnote { label } => Container::Footnote { label },
+                            block::Container::Div { class } => Container::Div {
+                                class: class.into(),
+                            },
+                            block::Container::Footnote { label } => Container::Footnote {
+                                label: label.into(),
+                            },
                             block::Container::List {
                                 kind: block::ListKind { ty, tight },
                                 marker,
@@ -1189,6 +1731,129 @@ impl<'s> Iterator for OffsetIter<'s> {
     }
 }
 
+/// A parser that owns its input, and emits [`Event`]s that do not borrow from it.
+///
+/// It is identical to a [`Parser`], except that it can be stored along with its input, e.g. in a
+/// cache or an editor session, without being tied to the lifetime of a borrowed string. The input
+/// is parsed when the parser is created, so iterating or cloning the parser is cheap.
+///
+/// # Examples
+///
+/// ```
+/// # use jotdown::*;
+/// let parser = OwnedParser::new("_hello_".to_string());
+/// assert_eq!(parser.src(), "_hello_");
+/// assert_eq!(
+///     parser.collect::<Vec<Event<'static>>>(),
+///     Parser::new("_hello_").collect::<Vec<_>>(),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct OwnedParser {
+    src: String,
+    events: std::vec::IntoIter<(Event<'static>, Range<usize>)>,
+}
+
+impl OwnedParser {
+    /// Create a parser with the default [`ParserOptions`] that owns the given input.
+    #[must_use]
+    pub fn new(src: String) -> Self {
+        Self::with_parser(src, |src| Parser::new(src))
+    }
+
+    /// Create a parser whose behavior is configured by the given [`ParserOptions`].
+    #[must_use]
+    pub fn with_options(src: String, options: ParserOptions) -> Self {
+        Self::with_parser(src, |src| Parser::with_options(src, options))
+    }
+
+    /// Create a parser from a [`Parser`] that is set up by the given function, e.g. to use a
+    /// [`Slugger`] or a broken reference callback. The function is given the input and is called
+    /// once, before this function returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use jotdown::*;
+    /// let parser = OwnedParser::with_parser("[a][]".to_string(), |src| {
+    ///     Parser::new(src).external_link_definitions([("a", ("/a", Attributes::new()))])
+    /// });
+    /// assert_eq!(
+    ///     parser.collect::<Vec<_>>()[1],
+    ///     Event::Start(
+    ///         Container::Link("/a".into(), LinkType::Span(SpanLinkType::Reference)),
+    ///         Attributes::new(),
+    ///     ),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_parser<F>(src: String, parser: F) -> Self
+    where
+        F: for<'a> FnOnce(&'a str) -> Parser<'a>,
+    {
+        let events = parser(&src)
+            .into_offset_iter()
+            .map(|(e, span)| (e.into_owned(), span))
+            .collect::<Vec<_>>()
+            .into_iter();
+        Self { src, events }
+    }
+
+    /// The input that is parsed.
+    #[must_use]
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// Turn the [`OwnedParser`] into an iterator of tuples, each with an [`Event`] and a
+    /// start/end byte offset for its corresponding input.
+    ///
+    /// See the documentation of [`Parser::into_offset_iter`] for more information.
+    #[must_use]
+    pub fn into_offset_iter(self) -> OwnedOffsetIter {
+        OwnedOffsetIter { parser: self }
+    }
+}
+
+impl From<String> for OwnedParser {
+    fn from(src: String) -> Self {
+        Self::new(src)
+    }
+}
+
+impl Iterator for OwnedParser {
+    type Item = Event<'static>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next().map(|(e, _)| e)
+    }
+}
+
+/// An iterator that is identical to an [`OwnedParser`], except that it also emits the location
+/// of each event within the input.
+///
+/// See the documentation of [`Parser::into_offset_iter`] for more information.
+#[derive(Clone)]
+pub struct OwnedOffsetIter {
+    parser: OwnedParser,
+}
+
+impl OwnedOffsetIter {
+    /// The input that is parsed.
+    #[must_use]
+    pub fn src(&self) -> &str {
+        self.parser.src()
+    }
+}
+
+impl Iterator for OwnedOffsetIter {
+    type Item = (Event<'static>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parser.events.next()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Attributes;
@@ -1345,6 +2010,20 @@ mod test {
         );
     }
 
+    #[test]
+    fn heading_id_whitespace() {
+        for (src, id) in [
+            ("# a b", "a-b"),
+            ("# a \t b", "a-b"),
+            ("# a\nb", "a-b"),
+            ("# a  \nb", "a--b"),
+            ("# a\n\\ b", "a-b"),
+            ("# `a  \n  b`", "a-b"),
+        ] {
+            assert_eq!(super::Parser::new(src).headings()[0].id(), id, "{:?}", src);
+        }
+    }
+
     #[test]
     fn heading_ref() {
         test_parse!(
@@ -1522,9 +2201,16 @@ mod test {
         test_parse!(
             "``raw\nraw``{=format}",
             Start(Paragraph, Attributes::new()),
-            Start(RawInline { format: "format" }, Attributes::new()),
+            Start(
+                RawInline {
+                    format: "format".into()
+                },
+                Attributes::new()
+            ),
             Str("raw\nraw".into()),
-            End(RawInline { format: "format" }),
+            End(RawInline {
+                format: "format".into()
+            }),
             End(Paragraph),
         );
     }
@@ -1533,9 +2219,16 @@ mod test {
     fn raw_block() {
         test_parse!(
             "``` =html\n<table>\n```",
-            Start(RawBlock { format: "html" }, Attributes::new()),
+            Start(
+                RawBlock {
+                    format: "html".into()
+                },
+                Attributes::new()
+            ),
             Str("<table>".into()),
-            End(RawBlock { format: "html" }),
+            End(RawBlock {
+                format: "html".into()
+            }),
         );
     }
 
@@ -1555,19 +2248,33 @@ mod test {
                 "</tag1>\n",   //
                 "```\n",       //
             ),
-            Start(RawBlock { format: "html" }, Attributes::new()),
+            Start(
+                RawBlock {
+                    format: "html".into()
+                },
+                Attributes::new()
+            ),
             Str("<tag1>\n".into()),
             Str("<tag2>".into()),
-            End(RawBlock { format: "html" }),
+            End(RawBlock {
+                format: "html".into()
+            }),
             Blankline,
             Start(Paragraph, Attributes::new()),
             Str("paragraph".into()),
             End(Paragraph),
             Blankline,
-            Start(RawBlock { format: "html" }, Attributes::new()),
+            Start(
+                RawBlock {
+                    format: "html".into()
+                },
+                Attributes::new()
+            ),
             Str("</tag2>\n".into()),
             Str("</tag1>".into()),
-            End(RawBlock { format: "html" }),
+            End(RawBlock {
+                format: "html".into()
+            }),
         );
     }
 
@@ -1652,9 +2359,16 @@ mod test {
             End(Link("url".into(), LinkType::Span(SpanLinkType::Reference))),
             End(Paragraph),
             Blankline,
-            Start(LinkDefinition { label: "tag" }, Attributes::new()),
+            Start(
+                LinkDefinition {
+                    label: "tag".into()
+                },
+                Attributes::new()
+            ),
             Str("url".into()),
-            End(LinkDefinition { label: "tag" }),
+            End(LinkDefinition {
+                label: "tag".into()
+            }),
         );
         test_parse!(
             concat!(
@@ -1671,9 +2385,16 @@ mod test {
             End(Image("url".into(), SpanLinkType::Reference)),
             End(Paragraph),
             Blankline,
-            Start(LinkDefinition { label: "tag" }, Attributes::new()),
+            Start(
+                LinkDefinition {
+                    label: "tag".into()
+                },
+                Attributes::new()
+            ),
             Str("url".into()),
-            End(LinkDefinition { label: "tag" }),
+            End(LinkDefinition {
+                label: "tag".into()
+            }),
         );
     }
 
@@ -1723,9 +2444,16 @@ mod test {
             End(Paragraph),
             End(Blockquote),
             Blankline,
-            Start(LinkDefinition { label: "a b" }, Attributes::new()),
+            Start(
+                LinkDefinition {
+                    label: "a b".into()
+                },
+                Attributes::new()
+            ),
             Str("url".into()),
-            End(LinkDefinition { label: "a b" }),
+            End(LinkDefinition {
+                label: "a b".into()
+            }),
         );
     }
 
@@ -1763,9 +2491,16 @@ mod test {
             End(Paragraph),
             End(Blockquote),
             Blankline,
-            Start(LinkDefinition { label: "a b" }, Attributes::new()),
+            Start(
+                LinkDefinition {
+                    label: "a b".into()
+                },
+                Attributes::new()
+            ),
             Str("url".into()),
-            End(LinkDefinition { label: "a b" }),
+            End(LinkDefinition {
+                label: "a b".into()
+            }),
         );
     }
 
@@ -1787,10 +2522,17 @@ mod test {
             End(Link("url".into(), LinkType::Span(SpanLinkType::Reference))),
             End(Paragraph),
             Blankline,
-            Start(LinkDefinition { label: "tag" }, Attributes::new()),
+            Start(
+                LinkDefinition {
+                    label: "tag".into()
+                },
+                Attributes::new()
+            ),
             Str("u".into()),
             Str("rl".into()),
-            End(LinkDefinition { label: "tag" }),
+            End(LinkDefinition {
+                label: "tag".into()
+            }),
         );
         test_parse!(
             concat!(
@@ -1812,10 +2554,17 @@ mod test {
             )),
             End(Paragraph),
             Blankline,
-            Start(LinkDefinition { label: "tag" }, Attributes::new()),
+            Start(
+                LinkDefinition {
+                    label: "tag".into()
+                },
+                Attributes::new()
+            ),
             Str("url".into()),
             Str("cont".into()),
-            End(LinkDefinition { label: "tag" }),
+            End(LinkDefinition {
+                label: "tag".into()
+            }),
         );
     }
 
@@ -1839,11 +2588,15 @@ mod test {
             End(Paragraph),
             Blankline,
             Start(
-                LinkDefinition { label: "tag" },
+                LinkDefinition {
+                    label: "tag".into()
+                },
                 [("a", "b")].into_iter().collect()
             ),
             Str("url".into()),
-            End(LinkDefinition { label: "tag" }),
+            End(LinkDefinition {
+                label: "tag".into()
+            }),
             Start(Paragraph, Attributes::new()),
             Str("para".into()),
             End(Paragraph),
@@ -1870,11 +2623,15 @@ mod test {
             End(Paragraph),
             Blankline,
             Start(
-                LinkDefinition { label: "tag" },
+                LinkDefinition {
+                    label: "tag".into()
+                },
                 [("class", "def")].into_iter().collect()
             ),
             Str("url".into()),
-            End(LinkDefinition { label: "tag" }),
+            End(LinkDefinition {
+                label: "tag".into()
+            }),
             Start(Paragraph, Attributes::new()),
             Str("para".into()),
             End(Paragraph),
@@ -1916,9 +2673,9 @@ mod test {
         test_parse!(
             "[^a][^b][^c]",
             Start(Paragraph, Attributes::new()),
-            FootnoteReference("a"),
-            FootnoteReference("b"),
-            FootnoteReference("c"),
+            FootnoteReference("a".into()),
+            FootnoteReference("b".into()),
+            FootnoteReference("c".into()),
             End(Paragraph),
         );
     }
@@ -1928,14 +2685,14 @@ mod test {
         test_parse!(
             "[^a]\n\n[^a]: a\n",
             Start(Paragraph, Attributes::new()),
-            FootnoteReference("a"),
+            FootnoteReference("a".into()),
             End(Paragraph),
             Blankline,
-            Start(Footnote { label: "a" }, Attributes::new()),
+            Start(Footnote { label: "a".into() }, Attributes::new()),
             Start(Paragraph, Attributes::new()),
             Str("a".into()),
             End(Paragraph),
-            End(Footnote { label: "a" }),
+            End(Footnote { label: "a".into() }),
         );
     }
 
@@ -1950,10 +2707,10 @@ mod test {
                 " def", //
             ),
             Start(Paragraph, Attributes::new()),
-            FootnoteReference("a"),
+            FootnoteReference("a".into()),
             End(Paragraph),
             Blankline,
-            Start(Footnote { label: "a" }, Attributes::new()),
+            Start(Footnote { label: "a".into() }, Attributes::new()),
             Start(Paragraph, Attributes::new()),
             Str("abc".into()),
             End(Paragraph),
@@ -1961,7 +2718,7 @@ mod test {
             Start(Paragraph, Attributes::new()),
             Str("def".into()),
             End(Paragraph),
-            End(Footnote { label: "a" }),
+            End(Footnote { label: "a".into() }),
         );
     }
 
@@ -1977,17 +2734,17 @@ mod test {
                 "para\n", //
             ),
             Start(Paragraph, Attributes::new()),
-            FootnoteReference("a"),
+            FootnoteReference("a".into()),
             End(Paragraph),
             Blankline,
-            Start(Footnote { label: "a" }, Attributes::new()),
+            Start(Footnote { label: "a".into() }, Attributes::new()),
             Start(Paragraph, Attributes::new()),
             Str("note".into()),
             Softbreak,
             Str("cont".into()),
             End(Paragraph),
             Blankline,
-            End(Footnote { label: "a" }),
+            End(Footnote { label: "a".into() }),
             Start(Paragraph, Attributes::new()),
             Str("para".into()),
             End(Paragraph),
@@ -2000,16 +2757,16 @@ mod test {
                 ":::\n",        //
             ),
             Start(Paragraph, Attributes::new()),
-            FootnoteReference("a"),
+            FootnoteReference("a".into()),
             End(Paragraph),
             Blankline,
-            Start(Footnote { label: "a" }, Attributes::new()),
+            Start(Footnote { label: "a".into() }, Attributes::new()),
             Start(Paragraph, Attributes::new()),
             Str("note".into()),
             End(Paragraph),
-            End(Footnote { label: "a" }),
-            Start(Div { class: "" }, Attributes::new()),
-            End(Div { class: "" }),
+            End(Footnote { label: "a".into() }),
+            Start(Div { class: "".into() }, Attributes::new()),
+            End(Div { class: "".into() }),
         );
     }
 
@@ -2293,4 +3050,335 @@ mod test {
         assert_eq!(AlphaUpper.parse_number("Z"), 26);
         assert_eq!(AlphaLower.parse_number("aa"), 27);
     }
+
+    #[test]
+    fn options() {
+        let options = super::ParserOptions {
+            sections: false,
+            heading_ids: false,
+            smart_punctuation: false,
+            heading_references: false,
+        };
+        assert_eq!(
+            super::Parser::with_options("# a\n\n[a][] 'b'...", options).collect::<Vec<_>>(),
+            &[
+                Start(
+                    Heading {
+                        level: 1,
+                        has_section: false,
+                        id: "".into(),
+                    },
+                    Attributes::new(),
+                ),
+                Str("a".into()),
+                End(Heading {
+                    level: 1,
+                    has_section: false,
+                    id: "".into(),
+                }),
+                Blankline,
+                Start(Paragraph, Attributes::new()),
+                Start(
+                    Link("a".into(), LinkType::Span(SpanLinkType::Unresolved)),
+                    Attributes::new(),
+                ),
+                Str("a".into()),
+                End(Link("a".into(), LinkType::Span(SpanLinkType::Unresolved))),
+                Str(" ".into()),
+                Str("'".into()),
+                Str("b".into()),
+                Str("'".into()),
+                Str("...".into()),
+                End(Paragraph),
+            ],
+        );
+    }
+
+    #[test]
+    fn options_explicit_id() {
+        let options = super::ParserOptions {
+            sections: false,
+            heading_ids: false,
+            ..super::ParserOptions::default()
+        };
+        assert_eq!(
+            super::Parser::with_options("{#b}\n# a\n\n[a][]", options).collect::<Vec<_>>(),
+            &[
+                Start(
+                    Heading {
+                        level: 1,
+                        has_section: false,
+                        id: "b".into(),
+                    },
+                    [("id", "b")].into_iter().collect(),
+                ),
+                Str("a".into()),
+                End(Heading {
+                    level: 1,
+                    has_section: false,
+                    id: "b".into(),
+                }),
+                Blankline,
+                Start(Paragraph, Attributes::new()),
+                Start(
+                    Link("#b".into(), LinkType::Span(SpanLinkType::Reference)),
+                    Attributes::new(),
+                ),
+                Str("a".into()),
+                End(Link("#b".into(), LinkType::Span(SpanLinkType::Reference))),
+                End(Paragraph),
+            ],
+        );
+    }
+
+    #[test]
+    fn broken_reference_callback() {
+        let src = "[a][b]{.x} ![c][] [d][]";
+        let refs = std::sync::Mutex::new(Vec::new());
+        let events = super::Parser::new(src)
+            .broken_reference_callback(|r| {
+                refs.lock()
+                    .unwrap()
+                    .push((r.tag.to_string(), r.span.clone(), r.image));
+                if r.tag == "d" {
+                    None
+                } else {
+                    Some((
+                        format!("/{}", r.tag).into(),
+                        [("class", "y")].into_iter().collect(),
+                    ))
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            &[
+                Start(Paragraph, Attributes::new()),
+                Start(
+                    Link("/b".into(), LinkType::Span(SpanLinkType::Reference)),
+                    [("class", "x"), ("class", "y")].into_iter().collect(),
+                ),
+                Str("a".into()),
+                End(Link("/b".into(), LinkType::Span(SpanLinkType::Reference))),
+                Str(" ".into()),
+                Start(
+                    Image("/c".into(), SpanLinkType::Reference),
+                    [("class", "y")].into_iter().collect(),
+                ),
+                Str("c".into()),
+                End(Image("/c".into(), SpanLinkType::Reference)),
+                Str(" ".into()),
+                Start(
+                    Link("d".into(), LinkType::Span(SpanLinkType::Unresolved)),
+                    Attributes::new(),
+                ),
+                Str("d".into()),
+                End(Link("d".into(), LinkType::Span(SpanLinkType::Unresolved))),
+                End(Paragraph),
+            ],
+        );
+        assert_eq!(
+            refs.into_inner().unwrap(),
+            &[
+                ("b".to_string(), 0..10, false),
+                ("c".to_string(), 11..17, true),
+                ("d".to_string(), 18..23, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn broken_reference_callback_send_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        let parser = super::Parser::new("[a][]").broken_reference_callback(|_| None);
+        assert_send_sync(&parser);
+    }
+
+    #[test]
+    fn external_link_definitions() {
+        let src = "[a][x] [b][y] [c][]\n\n# c\n\n[y]: /doc";
+        let external = [
+            ("x", ("/x", Attributes::new())),
+            ("y", ("/y", Attributes::new())),
+            ("c", ("/c", Attributes::new())),
+        ];
+        let links = super::Parser::new(src)
+            .external_link_definitions(external)
+            .filter_map(|e| match e {
+                Start(Link(url, _), _) => Some(url),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(links, &["/x", "/doc", "/c"]);
+    }
+
+    #[test]
+    fn headings() {
+        let src = concat!(
+            "# a *b*\n",
+            "c\n",
+            "\n",
+            "> ## d\n",
+            "\n",
+            "{#e}\n",
+            "### f",
+        );
+        let p = super::Parser::new(src);
+        let headings = p
+            .headings()
+            .iter()
+            .map(|h| (h.level(), h.id(), h.text(), &src[h.span()]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headings,
+            &[
+                (1, "a-b-c", "a b c", "# a *b*\nc\n"),
+                (2, "d", "d", "## d\n"),
+                (3, "e", "f", "### f"),
+            ]
+        );
+        let p = super::Parser::with_options(
+            src,
+            super::ParserOptions {
+                heading_ids: false,
+                ..Default::default()
+            },
+        );
+        let ids = p.headings().iter().map(|h| h.id()).collect::<Vec<_>>();
+        assert_eq!(ids, &["", "", "e"]);
+    }
+
+    #[test]
+    fn into_owned() {
+        let src = concat!(
+            "# heading\n",
+            "\n",
+            "::: div\n",
+            "[link](url){.a #b} `raw`{=html} [^n] :sym:\n",
+            ":::\n",
+            "\n",
+            "``` rust\n",
+            "code\n",
+            "```\n",
+            "\n",
+            "[^n]: note\n",
+        )
+        .to_string();
+        let events = super::Parser::new(&src).collect::<Vec<_>>();
+        let owned: Vec<super::Event<'static>> = events
+            .iter()
+            .cloned()
+            .map(super::Event::into_owned)
+            .collect();
+        assert_eq!(owned, events);
+        drop(events);
+        drop(src);
+        assert!(matches!(owned[0], Start(Section { .. }, _)));
+    }
+
+    #[test]
+    fn owned_parser() {
+        let src = "# heading\n\n[link][] _a_\n\n[link]: url\n";
+        let parser = super::OwnedParser::new(src.to_string());
+        assert_eq!(
+            parser.clone().collect::<Vec<_>>(),
+            super::Parser::new(src).collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            parser.into_offset_iter().collect::<Vec<_>>(),
+            super::Parser::new(src)
+                .into_offset_iter()
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde_test::Token;
+        let events = super::Parser::new("[a](url){.b}[^n]").collect::<Vec<_>>();
+        serde_test::assert_tokens(
+            &events,
+            &[
+                Token::Seq { len: Some(6) },
+                Token::TupleVariant {
+                    name: "Event",
+                    variant: "Start",
+                    len: 2,
+                },
+                Token::UnitVariant {
+                    name: "Container",
+                    variant: "Paragraph",
+                },
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::TupleVariantEnd,
+                Token::TupleVariant {
+                    name: "Event",
+                    variant: "Start",
+                    len: 2,
+                },
+                Token::TupleVariant {
+                    name: "Container",
+                    variant: "Link",
+                    len: 2,
+                },
+                Token::BorrowedStr("url"),
+                Token::NewtypeVariant {
+                    name: "LinkType",
+                    variant: "Span",
+                },
+                Token::UnitVariant {
+                    name: "SpanLinkType",
+                    variant: "Inline",
+                },
+                Token::TupleVariantEnd,
+                Token::Seq { len: None },
+                Token::Tuple { len: 2 },
+                Token::BorrowedStr("class"),
+                Token::BorrowedStr("b"),
+                Token::TupleEnd,
+                Token::SeqEnd,
+                Token::TupleVariantEnd,
+                Token::NewtypeVariant {
+                    name: "Event",
+                    variant: "Str",
+                },
+                Token::BorrowedStr("a"),
+                Token::NewtypeVariant {
+                    name: "Event",
+                    variant: "End",
+                },
+                Token::TupleVariant {
+                    name: "Container",
+                    variant: "Link",
+                    len: 2,
+                },
+                Token::BorrowedStr("url"),
+                Token::NewtypeVariant {
+                    name: "LinkType",
+                    variant: "Span",
+                },
+                Token::UnitVariant {
+                    name: "SpanLinkType",
+                    variant: "Inline",
+                },
+                Token::TupleVariantEnd,
+                Token::NewtypeVariant {
+                    name: "Event",
+                    variant: "FootnoteReference",
+                },
+                Token::BorrowedStr("n"),
+                Token::NewtypeVariant {
+                    name: "Event",
+                    variant: "End",
+                },
+                Token::UnitVariant {
+                    name: "Container",
+                    variant: "Paragraph",
+                },
+                Token::SeqEnd,
+            ],
+        );
+    }
 }
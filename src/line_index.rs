@@ -0,0 +1,300 @@
+use std::ops::Range;
+
+use crate::Container;
+use crate::Event;
+
+/// A position within a document, as a line and a column.
+///
+/// Both the line and the column are zero-based. Depending on the method used to obtain it, the
+/// column is counted in either Unicode scalar values or UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// An index that converts byte offsets within a document to line/column positions and back.
+///
+/// When created from the events of an offset iterator, the index may also be used to find the
+/// innermost container at a specific offset, see [`LineIndex::container_at`].
+///
+/// # Examples
+///
+/// ```
+/// # use jotdown::*;
+/// let src = "para\n\n_émph_ 𝄞";
+/// let index = LineIndex::with_events(src, Parser::new(src).into_offset_iter());
+/// let offset = src.find('𝄞').unwrap();
+/// assert_eq!(index.line_column(offset), Some(LineColumn { line: 2, column: 7 }));
+/// assert_eq!(index.line_column_utf16(offset), Some(LineColumn { line: 2, column: 7 }));
+/// assert_eq!(index.line_column_utf16(src.len()), Some(LineColumn { line: 2, column: 9 }));
+/// assert_eq!(index.offset(LineColumn { line: 2, column: 7 }), Some(offset));
+///
+/// let (container, span) = index.container_at(src.find('m').unwrap()).unwrap();
+/// assert_eq!(container, &Container::Emphasis);
+/// assert_eq!(&src[span], "_émph_");
+/// ```
+#[derive(Debug, Clone)]
+pub struct LineIndex<'s> {
+    src: &'s str,
+    /// Byte offset of the start of each line.
+    line_starts: Vec<usize>,
+    /// Containers and their spans, sorted by the start of their spans.
+    containers: Vec<(Container<'s>, Range<usize>)>,
+}
+
+impl<'s> LineIndex<'s> {
+    /// Create an index of the lines of the document.
+    #[must_use]
+    pub fn new(src: &'s str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            src,
+            line_starts,
+            containers: Vec::new(),
+        }
+    }
+
+    /// Create an index of the lines of the document, as well as the containers emitted by an
+    /// offset iterator of the same document, e.g. [`crate::Parser::into_offset_iter`].
+    #[must_use]
+    pub fn with_events<I>(src: &'s str, events: I) -> Self
+    where
+        I: IntoIterator<Item = (Event<'s>, Range<usize>)>,
+    {
+        let mut index = Self::new(src);
+        let mut open = Vec::new();
+        // a caption is emitted at the start of its table, with an empty span
+        let mut caption: Option<usize> = None;
+        for (e, span) in events {
+            if let Some(i) = caption.take() {
+                index.containers[i].1.start = span.start;
+            }
+            match e {
+                Event::Start(c, _) => {
+                    if matches!(c, Container::Caption) {
+                        caption = Some(index.containers.len());
+                    }
+                    open.push(index.containers.len());
+                    index.containers.push((c, span));
+                }
+                Event::End(_) => {
+                    if let Some(i) = open.pop() {
+                        let range = &mut index.containers[i].1;
+                        range.end = range.end.max(span.end);
+                    }
+                }
+                _ => {}
+            }
+        }
+        index.containers.sort_by_key(|(_, span)| span.start);
+        index
+    }
+
+    /// The number of lines in the document.
+    ///
+    /// A trailing newline is considered to start an additional, empty line.
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The byte range of a line, including its line terminator.
+    #[must_use]
+    pub fn line_span(&self, line: usize) -> Option<Range<usize>> {
+        let start = *self.line_starts.get(line)?;
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .copied()
+            .unwrap_or(self.src.len());
+        Some(start..end)
+    }
+
+    /// The line that contains a byte offset.
+    ///
+    /// Returns `None` if the offset is outside of the document.
+    #[must_use]
+    pub fn line(&self, offset: usize) -> Option<usize> {
+        if offset > self.src.len() {
+            return None;
+        }
+        Some(self.line_starts.partition_point(|s| *s <= offset) - 1)
+    }
+
+    /// Convert a byte offset to a line and a column counted in Unicode scalar values.
+    ///
+    /// Returns `None` if the offset is outside of the document or not at a character boundary.
+    #[must_use]
+    pub fn line_column(&self, offset: usize) -> Option<LineColumn> {
+        self.to_line_column(offset, |s| s.chars().count())
+    }
+
+    /// Convert a byte offset to a line and a column counted in UTF-16 code units.
+    ///
+    /// Returns `None` if the offset is outside of the document or not at a character boundary.
+    #[must_use]
+    pub fn line_column_utf16(&self, offset: usize) -> Option<LineColumn> {
+        self.to_line_column(offset, |s| s.encode_utf16().count())
+    }
+
+    /// Convert a line and a column counted in Unicode scalar values to a byte offset.
+    ///
+    /// Returns `None` if the line does not exist or the column is beyond the end of the line.
+    #[must_use]
+    pub fn offset(&self, pos: LineColumn) -> Option<usize> {
+        self.to_offset(pos, |_| 1)
+    }
+
+    /// Convert a line and a column counted in UTF-16 code units to a byte offset.
+    ///
+    /// Returns `None` if the line does not exist, the column is beyond the end of the line or the
+    /// column is within a surrogate pair.
+    #[must_use]
+    pub fn offset_utf16(&self, pos: LineColumn) -> Option<usize> {
+        self.to_offset(pos, char::len_utf16)
+    }
+
+    /// Find the innermost container whose span includes the byte offset, along with its span.
+    ///
+    /// Only containers given to [`LineIndex::with_events`] are considered. The span of a
+    /// container starts at its [`Event::Start`] and ends at its [`Event::End`], except for table
+    /// captions, which start at their content as they are emitted before the rows of the table.
+    #[must_use]
+    pub fn container_at(&self, offset: usize) -> Option<(&Container<'s>, Range<usize>)> {
+        // containers are sorted by start and nested containers start after their parents, so the
+        // last container that contains the offset is the innermost one
+        let n = self
+            .containers
+            .partition_point(|(_, span)| span.start <= offset);
+        self.containers[..n]
+            .iter()
+            .rev()
+            .find(|(_, span)| offset < span.end)
+            .map(|(c, span)| (c, span.clone()))
+    }
+
+    fn to_line_column(&self, offset: usize, len: impl Fn(&str) -> usize) -> Option<LineColumn> {
+        let line = self.line(offset)?;
+        let prefix = self.src.get(self.line_starts[line]..offset)?;
+        Some(LineColumn {
+            line,
+            column: len(prefix),
+        })
+    }
+
+    fn to_offset(&self, pos: LineColumn, len: impl Fn(char) -> usize) -> Option<usize> {
+        let span = self.line_span(pos.line)?;
+        let mut column = 0;
+        for (i, c) in self.src[span.clone()].char_indices() {
+            if column == pos.column {
+                return Some(span.start + i);
+            } else if column > pos.column {
+                return None;
+            }
+            column += len(c);
+        }
+        if column == pos.column && span.end == self.src.len() {
+            Some(span.end)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LineColumn;
+    use super::LineIndex;
+    use crate::Container;
+    use crate::Parser;
+
+    fn lc(line: usize, column: usize) -> LineColumn {
+        LineColumn { line, column }
+    }
+
+    #[test]
+    fn lines() {
+        let index = LineIndex::new("ab\ncd\n");
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line_span(0), Some(0..3));
+        assert_eq!(index.line_span(1), Some(3..6));
+        assert_eq!(index.line_span(2), Some(6..6));
+        assert_eq!(index.line_span(3), None);
+        assert_eq!(index.line(2), Some(0));
+        assert_eq!(index.line(3), Some(1));
+        assert_eq!(index.line(6), Some(2));
+        assert_eq!(index.line(7), None);
+    }
+
+    #[test]
+    fn line_column() {
+        let src = "aé\n𝄞b\n";
+        let index = LineIndex::new(src);
+        assert_eq!(index.line_column(0), Some(lc(0, 0)));
+        assert_eq!(index.line_column(3), Some(lc(0, 2)));
+        assert_eq!(index.line_column(2), None);
+        assert_eq!(index.line_column(4), Some(lc(1, 0)));
+        assert_eq!(index.line_column(8), Some(lc(1, 1)));
+        assert_eq!(index.line_column_utf16(8), Some(lc(1, 2)));
+        assert_eq!(index.line_column(10), Some(lc(2, 0)));
+        assert_eq!(index.line_column(11), None);
+    }
+
+    #[test]
+    fn offset() {
+        let src = "aé\n𝄞b";
+        let index = LineIndex::new(src);
+        assert_eq!(index.offset(lc(0, 2)), Some(3));
+        assert_eq!(index.offset(lc(0, 3)), None);
+        assert_eq!(index.offset(lc(1, 1)), Some(8));
+        assert_eq!(index.offset(lc(1, 2)), Some(9));
+        assert_eq!(index.offset(lc(1, 3)), None);
+        assert_eq!(index.offset_utf16(lc(1, 1)), None);
+        assert_eq!(index.offset_utf16(lc(1, 2)), Some(8));
+        assert_eq!(index.offset_utf16(lc(1, 3)), Some(9));
+        assert_eq!(index.offset(lc(2, 0)), None);
+    }
+
+    #[test]
+    fn container_at() {
+        let src = "> a *b*\n\nc\n";
+        let index = LineIndex::with_events(src, Parser::new(src).into_offset_iter());
+        assert_eq!(
+            index.container_at(2).map(|(c, _)| c),
+            Some(&Container::Paragraph)
+        );
+        let (c, span) = index.container_at(5).unwrap();
+        assert_eq!((c, &src[span]), (&Container::Strong, "*b*"));
+        assert_eq!(
+            index.container_at(0).map(|(c, _)| c),
+            Some(&Container::Blockquote)
+        );
+        assert_eq!(index.container_at(8), None);
+        assert_eq!(
+            index.container_at(9).map(|(c, _)| c),
+            Some(&Container::Paragraph)
+        );
+    }
+
+    #[test]
+    fn container_at_caption() {
+        let src = "| a |\n^ cap _b_\n";
+        let index = LineIndex::with_events(src, Parser::new(src).into_offset_iter());
+        assert_eq!(
+            index.container_at(2).map(|(c, _)| c),
+            Some(&Container::TableCell {
+                alignment: crate::Alignment::Unspecified,
+                head: false,
+            })
+        );
+        let (c, span) = index.container_at(8).unwrap();
+        assert_eq!((c, &src[span]), (&Container::Caption, "cap _b_\n"));
+        assert_eq!(
+            index.container_at(12).map(|(c, _)| c),
+            Some(&Container::Emphasis)
+        );
+    }
+}
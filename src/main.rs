@@ -10,6 +10,8 @@ use jotdown::Render;
 struct App {
     input: Option<OsString>,
     output: Option<OsString>,
+    standalone: bool,
+    template: Option<OsString>,
 }
 
 fn parse_args() -> App {
@@ -37,6 +39,18 @@ fn parse_args() -> App {
                     exit(1);
                 }
             },
+            ("-s" | "--standalone", _) => app.standalone = true,
+            (flag @ ("-t" | "--template"), t) => match t {
+                Some(t) => {
+                    app.template = Some(t.into());
+                    app.standalone = true;
+                    args.next();
+                }
+                None => {
+                    eprintln!("please supply an argument to {}", flag);
+                    exit(1);
+                }
+            },
             ("-", _) => {}
             (file, _) if !file.starts_with('-') => {
                 if app.input.is_some() {
@@ -68,7 +82,14 @@ fn run() -> Result<(), std::io::Error> {
     };
 
     let parser = jotdown::Parser::new(&content);
-    let renderer = jotdown::html::Renderer::default();
+    let mut renderer = jotdown::html::Renderer::default();
+    if app.standalone {
+        let mut standalone = jotdown::html::Standalone::default();
+        if let Some(path) = app.template {
+            standalone = standalone.template(std::fs::read_to_string(path)?);
+        }
+        renderer = renderer.standalone(standalone);
+    }
 
     match app.output {
         Some(path) => renderer.write(parser, File::create(path)?)?,
@@ -0,0 +1,619 @@
+//! Conversion of LaTeX math to MathML.
+//!
+//! Only a common subset of LaTeX math is supported, e.g. fractions, roots, scripts, Greek
+//! letters, operators, accents and fonts. The [`html::Renderer`] uses the conversion when
+//! [`html::Renderer::mathml`] is enabled, and falls back to its usual output for math that can
+//! not be converted.
+//!
+//! # Examples
+//!
+//! ```
+//! # use jotdown::math::to_mathml;
+//! assert_eq!(
+//!     to_mathml(r"x^2 + \alpha", false).unwrap(),
+//!     "<math><mrow><msup><mi>x</mi><mn>2</mn></msup><mo>+</mo><mi>α</mi></mrow></math>",
+//! );
+//! assert_eq!(to_mathml(r"\begin{cases}x\end{cases}", true), None);
+//! ```
+//!
+//! [`html::Renderer`]: crate::html::Renderer
+//! [`html::Renderer::mathml`]: crate::html::Renderer::mathml
+
+/// Convert LaTeX math to a MathML `<math>` element, or `None` if it contains constructs that are
+/// not supported.
+///
+/// If `display` is true, the element is given the `display="block"` attribute and limits of
+/// large operators such as `\sum` are placed under and over the operator.
+#[must_use]
+pub fn to_mathml(latex: &str, display: bool) -> Option<String> {
+    let mut p = Parser {
+        src: latex,
+        pos: 0,
+        display,
+    };
+    let row = p.row(Terminator::End)?;
+    let mut out = String::from(if display {
+        r#"<math display="block">"#
+    } else {
+        "<math>"
+    });
+    out.push_str(&row);
+    out.push_str("</math>");
+    Some(out)
+}
+
+/// What ends a sequence of elements.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Terminator {
+    End,
+    Brace,
+    Bracket,
+    Right,
+}
+
+/// A single element, and whether its scripts are placed under and over it.
+struct Atom {
+    mathml: String,
+    limits: bool,
+}
+
+impl Atom {
+    fn new(mathml: String) -> Self {
+        Self {
+            mathml,
+            limits: false,
+        }
+    }
+}
+
+struct Parser<'s> {
+    src: &'s str,
+    pos: usize,
+    display: bool,
+}
+
+impl<'s> Parser<'s> {
+    fn rest(&self) -> &'s str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Parse elements until the terminator, and combine them into a single element.
+    fn row(&mut self, term: Terminator) -> Option<String> {
+        let mut elems = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match (self.peek(), term) {
+                (None, Terminator::End) => break,
+                (None, _) => return None,
+                (Some('}'), Terminator::Brace) | (Some(']'), Terminator::Bracket) => {
+                    self.pos += 1;
+                    break;
+                }
+                (Some('}'), _) => return None,
+                _ => {}
+            }
+            if term == Terminator::Right && is_right(self.rest()) {
+                break;
+            }
+            let atom = self.atom()?;
+            elems.push(self.scripts(atom)?);
+        }
+        Some(if elems.len() == 1 {
+            elems.pop().unwrap()
+        } else {
+            format!("<mrow>{}</mrow>", elems.concat())
+        })
+    }
+
+    /// Attach any subscript and superscript to an element.
+    fn scripts(&mut self, atom: Atom) -> Option<String> {
+        let mut sub = None;
+        let mut sup = None;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('_') if sub.is_none() => {
+                    self.pos += 1;
+                    sub = Some(self.argument()?);
+                }
+                Some('^') if sup.is_none() => {
+                    self.pos += 1;
+                    sup = Some(self.argument()?);
+                }
+                Some('_' | '^') => return None,
+                _ => break,
+            }
+        }
+        let base = atom.mathml;
+        let under = atom.limits && self.display;
+        Some(match (sub, sup) {
+            (None, None) => base,
+            (Some(b), None) if under => format!("<munder>{}{}</munder>", base, b),
+            (None, Some(p)) if under => format!("<mover>{}{}</mover>", base, p),
+            (Some(b), Some(p)) if under => {
+                format!("<munderover>{}{}{}</munderover>", base, b, p)
+            }
+            (Some(b), None) => format!("<msub>{}{}</msub>", base, b),
+            (None, Some(p)) => format!("<msup>{}{}</msup>", base, p),
+            (Some(b), Some(p)) => format!("<msubsup>{}{}{}</msubsup>", base, b, p),
+        })
+    }
+
+    /// Parse the argument of a command or script, i.e. a group or a single token.
+    fn argument(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        match self.peek()? {
+            '{' => {
+                self.pos += 1;
+                self.row(Terminator::Brace)
+            }
+            c if c.is_ascii_digit() => {
+                self.pos += 1;
+                Some(format!("<mn>{}</mn>", c))
+            }
+            _ => self.atom().map(|a| a.mathml),
+        }
+    }
+
+    /// Parse the raw text of a group, e.g. the argument of `\text`.
+    fn text_argument(&mut self) -> Option<&'s str> {
+        self.skip_whitespace();
+        let rest = self.rest().strip_prefix('{')?;
+        let mut depth = 0;
+        for (i, c) in rest.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    self.pos += 1 + i + 1;
+                    return Some(&rest[..i]);
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn atom(&mut self) -> Option<Atom> {
+        let c = self.peek()?;
+        if c.is_ascii_digit()
+            || (c == '.' && self.rest()[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let len = self
+                .rest()
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(self.rest().len());
+            let n = &self.rest()[..len];
+            self.pos += len;
+            return Some(Atom::new(format!("<mn>{}</mn>", n)));
+        }
+        self.pos += c.len_utf8();
+        let mathml = match c {
+            '{' => self.row(Terminator::Brace)?,
+            '\\' => return self.command(),
+            '-' => mo("\u{2212}"),
+            '*' => mo("\u{2217}"),
+            '\'' => mo("\u{2032}"),
+            '<' => mo("&lt;"),
+            '>' => mo("&gt;"),
+            '+' | '=' | ',' | ';' | ':' | '!' | '?' | '(' | ')' | '[' | ']' | '|' | '/' | '.' => {
+                format!("<mo>{}</mo>", c)
+            }
+            '&' | '#' | '$' | '%' | '~' | '_' | '^' | '}' => return None,
+            c if c.is_alphabetic() => format!("<mi>{}</mi>", c),
+            c => format!("<mo>{}</mo>", escape(&c.to_string())),
+        };
+        Some(Atom::new(mathml))
+    }
+
+    /// Parse the name of a command after the backslash, either a word or a single character.
+    fn command_name(&mut self) -> Option<&'s str> {
+        let rest = self.rest();
+        let len = match rest.find(|c: char| !c.is_ascii_alphabetic()) {
+            Some(0) => rest.chars().next()?.len_utf8(),
+            Some(len) => len,
+            None => rest.len(),
+        };
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn command(&mut self) -> Option<Atom> {
+        let name = self.command_name()?;
+
+        if let Some(s) = symbol(name) {
+            return Some(match s {
+                Symbol::Ident(s) => Atom::new(format!("<mi>{}</mi>", s)),
+                Symbol::Op(s) => Atom::new(mo(s)),
+                Symbol::LargeOp(s) => Atom {
+                    mathml: mo(s),
+                    limits: true,
+                },
+                Symbol::Func => Atom::new(format!("<mi>{}</mi>", name)),
+                Symbol::Limit => Atom {
+                    mathml: format!("<mi>{}</mi>", name),
+                    limits: true,
+                },
+                Symbol::Space(w) => Atom::new(format!(r#"<mspace width="{}"/>"#, w)),
+            });
+        }
+
+        let mathml = match name {
+            "frac" | "dfrac" | "tfrac" => {
+                let num = self.argument()?;
+                let den = self.argument()?;
+                format!("<mfrac>{}{}</mfrac>", num, den)
+            }
+            "binom" => {
+                let n = self.argument()?;
+                let k = self.argument()?;
+                format!(
+                    r#"<mrow><mo>(</mo><mfrac linethickness="0">{}{}</mfrac><mo>)</mo></mrow>"#,
+                    n, k
+                )
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                if self.rest().starts_with('[') {
+                    self.pos += 1;
+                    let index = self.row(Terminator::Bracket)?;
+                    let base = self.argument()?;
+                    format!("<mroot>{}{}</mroot>", base, index)
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.argument()?)
+                }
+            }
+            "text" | "textrm" | "mbox" => {
+                format!("<mtext>{}</mtext>", escape(self.text_argument()?))
+            }
+            "operatorname" => format!("<mi>{}</mi>", escape(self.text_argument()?)),
+            "mathrm" | "mathbf" | "mathit" | "mathbb" | "mathcal" | "mathsf" | "mathtt"
+            | "mathfrak" | "boldsymbol" => {
+                let variant = match name {
+                    "mathrm" => "normal",
+                    "mathbf" | "boldsymbol" => "bold",
+                    "mathit" => "italic",
+                    "mathbb" => "double-struck",
+                    "mathcal" => "script",
+                    "mathsf" => "sans-serif",
+                    "mathtt" => "monospace",
+                    _ => "fraktur",
+                };
+                let text = self.text_argument()?;
+                if !text.chars().all(char::is_alphanumeric) {
+                    return None;
+                }
+                format!(r#"<mi mathvariant="{}">{}</mi>"#, variant, text)
+            }
+            "hat" | "widehat" | "bar" | "overline" | "vec" | "dot" | "ddot" | "tilde"
+            | "widetilde" => {
+                let accent = match name {
+                    "hat" | "widehat" => "^",
+                    "bar" | "overline" => "\u{AF}",
+                    "vec" => "\u{2192}",
+                    "dot" => "\u{2D9}",
+                    "ddot" => "\u{A8}",
+                    _ => "~",
+                };
+                let base = self.argument()?;
+                format!(
+                    r#"<mover accent="true">{}<mo>{}</mo></mover>"#,
+                    base, accent
+                )
+            }
+            "underline" => {
+                let base = self.argument()?;
+                format!(r#"<munder accentunder="true">{}<mo>_</mo></munder>"#, base)
+            }
+            "left" => {
+                let open = self.delimiter()?;
+                let content = self.row(Terminator::Right)?;
+                self.pos += "\\right".len();
+                let close = self.delimiter()?;
+                format!("<mrow>{}{}{}</mrow>", open, content, close)
+            }
+            _ => return None,
+        };
+        Some(Atom::new(mathml))
+    }
+
+    /// Parse the delimiter after `\left` or `\right`.
+    fn delimiter(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        let d = match c {
+            '.' => return Some(String::new()),
+            '(' | ')' | '[' | ']' | '|' | '/' => c.to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '\\' => {
+                let name = self.command_name()?;
+                match symbol(name)? {
+                    Symbol::Op(s) => s.to_string(),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(format!(r#"<mo stretchy="true">{}</mo>"#, d))
+    }
+}
+
+/// Whether the input starts with the `\right` command, and not e.g. `\rightarrow`.
+fn is_right(s: &str) -> bool {
+    matches!(
+        s.strip_prefix("\\right"),
+        Some(s) if !s.starts_with(|c: char| c.is_ascii_alphabetic())
+    )
+}
+
+fn mo(s: &str) -> String {
+    format!("<mo>{}</mo>", s)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+enum Symbol {
+    /// An identifier, e.g. a Greek letter.
+    Ident(&'static str),
+    /// An operator, relation or delimiter.
+    Op(&'static str),
+    /// A large operator whose scripts are limits, e.g. `\sum`.
+    LargeOp(&'static str),
+    /// A function with the same name as the command, e.g. `\sin`.
+    Func,
+    /// A function whose scripts are limits, e.g. `\lim`.
+    Limit,
+    /// Horizontal space of the given width.
+    Space(&'static str),
+}
+
+fn symbol(name: &str) -> Option<Symbol> {
+    use Symbol::*;
+
+    Some(match name {
+        "alpha" => Ident("α"),
+        "beta" => Ident("β"),
+        "gamma" => Ident("γ"),
+        "delta" => Ident("δ"),
+        "epsilon" => Ident("ϵ"),
+        "varepsilon" => Ident("ε"),
+        "zeta" => Ident("ζ"),
+        "eta" => Ident("η"),
+        "theta" => Ident("θ"),
+        "vartheta" => Ident("ϑ"),
+        "iota" => Ident("ι"),
+        "kappa" => Ident("κ"),
+        "lambda" => Ident("λ"),
+        "mu" => Ident("μ"),
+        "nu" => Ident("ν"),
+        "xi" => Ident("ξ"),
+        "pi" => Ident("π"),
+        "varpi" => Ident("ϖ"),
+        "rho" => Ident("ρ"),
+        "varrho" => Ident("ϱ"),
+        "sigma" => Ident("σ"),
+        "varsigma" => Ident("ς"),
+        "tau" => Ident("τ"),
+        "upsilon" => Ident("υ"),
+        "phi" => Ident("ϕ"),
+        "varphi" => Ident("φ"),
+        "chi" => Ident("χ"),
+        "psi" => Ident("ψ"),
+        "omega" => Ident("ω"),
+        "Gamma" => Ident("Γ"),
+        "Delta" => Ident("Δ"),
+        "Theta" => Ident("Θ"),
+        "Lambda" => Ident("Λ"),
+        "Xi" => Ident("Ξ"),
+        "Pi" => Ident("Π"),
+        "Sigma" => Ident("Σ"),
+        "Upsilon" => Ident("Υ"),
+        "Phi" => Ident("Φ"),
+        "Psi" => Ident("Ψ"),
+        "Omega" => Ident("Ω"),
+        "infty" => Ident("∞"),
+        "partial" => Ident("∂"),
+        "nabla" => Ident("∇"),
+        "emptyset" | "varnothing" => Ident("∅"),
+        "hbar" => Ident("ℏ"),
+        "ell" => Ident("ℓ"),
+        "aleph" => Ident("ℵ"),
+        "Re" => Ident("ℜ"),
+        "Im" => Ident("ℑ"),
+        "angle" => Ident("∠"),
+        "prime" => Op("′"),
+        "times" => Op("×"),
+        "cdot" => Op("⋅"),
+        "pm" => Op("±"),
+        "mp" => Op("∓"),
+        "div" => Op("÷"),
+        "ast" => Op("∗"),
+        "star" => Op("⋆"),
+        "circ" => Op("∘"),
+        "bullet" => Op("∙"),
+        "setminus" => Op("∖"),
+        "leq" | "le" => Op("≤"),
+        "geq" | "ge" => Op("≥"),
+        "neq" | "ne" => Op("≠"),
+        "ll" => Op("≪"),
+        "gg" => Op("≫"),
+        "approx" => Op("≈"),
+        "equiv" => Op("≡"),
+        "sim" => Op("∼"),
+        "simeq" => Op("≃"),
+        "cong" => Op("≅"),
+        "propto" => Op("∝"),
+        "perp" => Op("⊥"),
+        "parallel" => Op("∥"),
+        "mid" => Op("∣"),
+        "in" => Op("∈"),
+        "notin" => Op("∉"),
+        "ni" => Op("∋"),
+        "subset" => Op("⊂"),
+        "subseteq" => Op("⊆"),
+        "supset" => Op("⊃"),
+        "supseteq" => Op("⊇"),
+        "cup" => Op("∪"),
+        "cap" => Op("∩"),
+        "wedge" | "land" => Op("∧"),
+        "vee" | "lor" => Op("∨"),
+        "neg" | "lnot" => Op("¬"),
+        "forall" => Op("∀"),
+        "exists" => Op("∃"),
+        "to" | "rightarrow" => Op("→"),
+        "leftarrow" | "gets" => Op("←"),
+        "leftrightarrow" => Op("↔"),
+        "Rightarrow" => Op("⇒"),
+        "Leftarrow" => Op("⇐"),
+        "Leftrightarrow" => Op("⇔"),
+        "implies" => Op("⟹"),
+        "iff" => Op("⟺"),
+        "mapsto" => Op("↦"),
+        "uparrow" => Op("↑"),
+        "downarrow" => Op("↓"),
+        "ldots" | "dots" => Op("…"),
+        "cdots" => Op("⋯"),
+        "vdots" => Op("⋮"),
+        "ddots" => Op("⋱"),
+        "langle" => Op("⟨"),
+        "rangle" => Op("⟩"),
+        "lfloor" => Op("⌊"),
+        "rfloor" => Op("⌋"),
+        "lceil" => Op("⌈"),
+        "rceil" => Op("⌉"),
+        "vert" => Op("|"),
+        "Vert" | "|" => Op("‖"),
+        "{" | "lbrace" => Op("{"),
+        "}" | "rbrace" => Op("}"),
+        "%" => Op("%"),
+        "#" => Op("#"),
+        "$" => Op("$"),
+        "&" => Op("&amp;"),
+        "_" => Op("_"),
+        "sum" => LargeOp("∑"),
+        "prod" => LargeOp("∏"),
+        "coprod" => LargeOp("∐"),
+        "bigcup" => LargeOp("⋃"),
+        "bigcap" => LargeOp("⋂"),
+        "int" => Op("∫"),
+        "iint" => Op("∬"),
+        "iiint" => Op("∭"),
+        "oint" => Op("∮"),
+        "sin" | "cos" | "tan" | "cot" | "sec" | "csc" | "arcsin" | "arccos" | "arctan" | "sinh"
+        | "cosh" | "tanh" | "log" | "ln" | "lg" | "exp" | "det" | "dim" | "ker" | "deg" | "arg"
+        | "hom" | "gcd" => Func,
+        "lim" | "max" | "min" | "sup" | "inf" | "limsup" | "liminf" => Limit,
+        "," => Space("0.1667em"),
+        ":" | ">" => Space("0.2222em"),
+        ";" => Space("0.2778em"),
+        " " => Space("0.25em"),
+        "!" => Space("-0.1667em"),
+        "quad" => Space("1em"),
+        "qquad" => Space("2em"),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_mathml;
+
+    fn inline(latex: &str) -> Option<String> {
+        to_mathml(latex, false).map(|s| {
+            s.strip_prefix("<math>")
+                .and_then(|s| s.strip_suffix("</math>"))
+                .unwrap()
+                .to_string()
+        })
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            inline("x12.5 - y < 3").unwrap(),
+            "<mrow><mi>x</mi><mn>12.5</mn><mo>−</mo><mi>y</mi><mo>&lt;</mo><mn>3</mn></mrow>",
+        );
+        assert_eq!(inline("").unwrap(), "<mrow></mrow>");
+        assert_eq!(inline("{a}").unwrap(), "<mi>a</mi>");
+    }
+
+    #[test]
+    fn scripts() {
+        assert_eq!(
+            inline("x_i^{2n}").unwrap(),
+            "<msubsup><mi>x</mi><mi>i</mi><mrow><mn>2</mn><mi>n</mi></mrow></msubsup>",
+        );
+        assert_eq!(
+            inline("e^23").unwrap(),
+            "<mrow><msup><mi>e</mi><mn>2</mn></msup><mn>3</mn></mrow>",
+        );
+        assert_eq!(
+            inline(r"\sum_{i=0}^n").unwrap(),
+            "<msubsup><mo>∑</mo><mrow><mi>i</mi><mo>=</mo><mn>0</mn></mrow><mi>n</mi></msubsup>",
+        );
+        assert_eq!(
+            to_mathml(r"\lim_{x \to 0}", true).unwrap(),
+            concat!(
+                r#"<math display="block"><munder><mi>lim</mi>"#,
+                "<mrow><mi>x</mi><mo>→</mo><mn>0</mn></mrow></munder></math>",
+            ),
+        );
+        assert_eq!(inline("x^"), None);
+        assert_eq!(inline("x^2^3"), None);
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(
+            inline(r"\frac{1}{\sqrt[3]{x}}").unwrap(),
+            "<mfrac><mn>1</mn><mroot><mi>x</mi><mn>3</mn></mroot></mfrac>",
+        );
+        assert_eq!(
+            inline(r"\text{if } \mathbb{R}").unwrap(),
+            concat!(
+                "<mrow><mtext>if </mtext>",
+                r#"<mi mathvariant="double-struck">R</mi></mrow>"#,
+            ),
+        );
+        assert_eq!(
+            inline(r"\left( \hat{x} \right]").unwrap(),
+            concat!(
+                r#"<mrow><mo stretchy="true">(</mo>"#,
+                r#"<mover accent="true"><mi>x</mi><mo>^</mo></mover>"#,
+                r#"<mo stretchy="true">]</mo></mrow>"#,
+            ),
+        );
+        assert_eq!(
+            inline(r"a\,\{b\}").unwrap(),
+            concat!(
+                r#"<mrow><mi>a</mi><mspace width="0.1667em"/>"#,
+                "<mo>{</mo><mi>b</mi><mo>}</mo></mrow>",
+            ),
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(inline(r"\unknown"), None);
+        assert_eq!(inline(r"\begin{matrix}a & b\end{matrix}"), None);
+        assert_eq!(inline(r"a \\ b"), None);
+        assert_eq!(inline("{a"), None);
+        assert_eq!(inline("a}"), None);
+        assert_eq!(inline(r"\left( a"), None);
+    }
+}
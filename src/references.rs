@@ -0,0 +1,201 @@
+use std::ops::Range;
+
+use crate::Attributes;
+use crate::CowStr;
+use crate::Event;
+use crate::Parser;
+use crate::Set;
+
+/// A link definition within a document, see [`Parser::link_definitions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkDefinition<'s> {
+    /// The label of the definition, e.g. `label` in `[label]: url`.
+    pub label: CowStr<'s>,
+    /// The url of the definition, with any line breaks removed.
+    pub url: CowStr<'s>,
+    /// The attributes of the definition, which are added to any link or image that uses it.
+    pub attrs: Attributes<'s>,
+    /// The location of the definition within the input, excluding its attributes.
+    pub span: Range<usize>,
+}
+
+/// A footnote within a document, see [`Parser::footnote_definitions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FootnoteDefinition<'s> {
+    /// The label of the footnote, e.g. `label` in `[^label]: text`.
+    pub label: CowStr<'s>,
+    /// The location of the footnote and its content within the input.
+    pub span: Range<usize>,
+}
+
+/// A reference link, reference image or footnote reference, see [`Parser::references`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference<'s> {
+    /// The kind of element that contains the reference.
+    pub kind: ReferenceKind,
+    /// The tag of a link or an image, e.g. `tag` in `[text][tag]` or `[tag][]`, or the label of a
+    /// footnote reference, e.g. `label` in `[^label]`.
+    pub tag: CowStr<'s>,
+    /// The location of the whole element within the input.
+    pub span: Range<usize>,
+    /// What the reference has been resolved to.
+    pub resolution: Resolution<'s>,
+}
+
+/// The kind of element that contains a [`Reference`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    Link,
+    Image,
+    Footnote,
+}
+
+/// What a [`Reference`] has been resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution<'s> {
+    /// A link definition or a footnote with the same label as the tag.
+    Definition,
+    /// A heading whose text is the tag, with the id of the heading.
+    Heading { id: CowStr<'s> },
+    /// A destination given by the broken reference callback, see
+    /// [`Parser::broken_reference_callback`].
+    Callback,
+    /// Nothing, the reference is broken.
+    Unresolved,
+}
+
+/// Parse the remaining input of the parser and collect all references, in the order they appear.
+pub(crate) fn collect<'s>(mut parser: Parser<'s>) -> Vec<Reference<'s>> {
+    parser.references = Some(Vec::new());
+    let footnotes = parser
+        .pre_pass
+        .footnotes
+        .iter()
+        .map(|f| f.label.clone())
+        .collect::<Set<_>>();
+
+    let mut events = parser.into_offset_iter();
+    // references to links and images are recorded by the parser when they are resolved
+    while let Some((e, span)) = events.next() {
+        if let Event::FootnoteReference(label) = e {
+            let resolution = if footnotes.contains(&label) {
+                Resolution::Definition
+            } else {
+                Resolution::Unresolved
+            };
+            if let Some(references) = &mut events.parser.references {
+                references.push(Reference {
+                    kind: ReferenceKind::Footnote,
+                    tag: label,
+                    span,
+                    resolution,
+                });
+            }
+        }
+    }
+
+    events.parser.references.take().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::FootnoteDefinition;
+    use super::LinkDefinition;
+    use super::Reference;
+    use super::ReferenceKind::*;
+    use super::Resolution::*;
+    use crate::Attributes;
+    use crate::Parser;
+
+    #[test]
+    fn link_definitions() {
+        let src = "[a]: /a\n\n{.b}\n[b]:\n /b\n c\n\n> [c]:";
+        let p = Parser::new(src);
+        let mut attrs = Attributes::new();
+        attrs.insert("class", "b".into());
+        assert_eq!(
+            p.link_definitions(),
+            &[
+                LinkDefinition {
+                    label: "a".into(),
+                    url: "/a".into(),
+                    attrs: Attributes::new(),
+                    span: 0..8,
+                },
+                LinkDefinition {
+                    label: "b".into(),
+                    url: "/bc".into(),
+                    attrs,
+                    span: 14..26,
+                },
+                LinkDefinition {
+                    label: "c".into(),
+                    url: "".into(),
+                    attrs: Attributes::new(),
+                    span: 28..33,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn footnote_definitions() {
+        let src = "[^a]: a\n\n[^b]:\n  b\n\n  c\n\nd";
+        let p = Parser::new(src);
+        let footnotes = p.footnote_definitions();
+        assert_eq!(
+            footnotes,
+            &[
+                FootnoteDefinition {
+                    label: "a".into(),
+                    span: 0..9,
+                },
+                FootnoteDefinition {
+                    label: "b".into(),
+                    span: 9..25,
+                },
+            ]
+        );
+        let ends = p
+            .into_offset_iter()
+            .filter(|(e, _)| matches!(e, crate::Event::End(crate::Container::Footnote { .. })))
+            .map(|(_, span)| span.end)
+            .collect::<Vec<_>>();
+        assert_eq!(ends, &[9, 25]);
+    }
+
+    #[test]
+    fn references() {
+        let src = concat!(
+            "# h\n",
+            "\n",
+            "[a][b] ![c][] [h][] [d][x] [e](/e) [^f] [^g]\n",
+            "\n",
+            "[b]: /b\n",
+            "[c]: /c\n",
+            "\n",
+            "[^f]: f\n",
+        );
+        let r = |kind, tag: &str, span, resolution| Reference {
+            kind,
+            tag: tag.to_string().into(),
+            span,
+            resolution,
+        };
+        assert_eq!(
+            Parser::new(src).references(),
+            &[
+                r(Link, "b", 5..11, Definition),
+                r(Image, "c", 12..18, Definition),
+                r(Link, "h", 19..24, Heading { id: "h".into() }),
+                r(Link, "x", 25..31, Unresolved),
+                r(Footnote, "f", 40..44, Definition),
+                r(Footnote, "g", 45..49, Unresolved),
+            ]
+        );
+        let refs = Parser::new("[a][b]")
+            .broken_reference_callback(|_| Some(("/b".into(), Attributes::new())))
+            .references();
+        assert_eq!(refs, &[r(Link, "b", 0..6, Callback)]);
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Write;
+
+use crate::Set;
+
+/// A generator of ids for headings.
+///
+/// The parser calls the slugger once for each heading, in the order they appear in the document.
+/// Unless the heading has an explicit id, the resulting id is used for
+/// [`crate::Container::Heading`], [`crate::Container::Section`] and references to the heading.
+///
+/// # Examples
+///
+/// Create lowercase ids:
+///
+/// ```
+/// # use jotdown::*;
+/// #[derive(Default)]
+/// struct Lowercase(DefaultSlugger);
+///
+/// impl Slugger for Lowercase {
+///     fn slug(&mut self, text: &str) -> String {
+///         self.0.slug(&text.to_lowercase())
+///     }
+/// }
+///
+/// let events = Parser::with_slugger(
+///     "# Some Heading",
+///     ParserOptions::default(),
+///     Lowercase::default(),
+/// );
+/// assert_eq!(
+///     events.take(1).collect::<Vec<_>>(),
+///     &[Event::Start(
+///         Container::Section {
+///             id: "some-heading".into()
+///         },
+///         Attributes::new()
+///     )],
+/// );
+/// ```
+pub trait Slugger {
+    /// Create an id from the text of a heading, with formatting removed. Soft breaks are given as
+    /// newlines, and are the only newlines within the text.
+    ///
+    /// The slugger is responsible for making the ids unique within the document, if desired.
+    fn slug(&mut self, text: &str) -> String;
+}
+
+impl<S: Slugger + ?Sized> Slugger for &mut S {
+    fn slug(&mut self, text: &str) -> String {
+        (**self).slug(text)
+    }
+}
+
+/// The [`Slugger`] used by default.
+///
+/// ASCII punctuation except `-` and `_` is removed, and words are joined with `-`. A run of
+/// whitespace within a line becomes a single `-`, while a soft break always adds a `-`, so e.g.
+/// `a  ` followed by a soft break and `b` results in `a--b`. Other characters are kept as is.
+/// Duplicate ids are made unique with a `-1`, `-2`, etc. suffix, and an empty id is replaced by
+/// `s-1`, `s-2`, etc.
+#[derive(Debug, Clone, Default)]
+pub struct DefaultSlugger {
+    used: Set<String>,
+}
+
+impl Slugger for DefaultSlugger {
+    fn slug(&mut self, text: &str) -> String {
+        let mut id = String::new();
+        let mut last_whitespace = true;
+        for c in text.chars() {
+            if c == '\n' {
+                id.push('-');
+            } else if c.is_ascii_whitespace() {
+                if !last_whitespace {
+                    last_whitespace = true;
+                    id.push('-');
+                }
+            } else if !c.is_ascii_punctuation() || matches!(c, '-' | '_') {
+                id.push(c);
+                last_whitespace = false;
+            }
+        }
+        id.drain(id.trim_end_matches('-').len()..);
+
+        // ensure id unique
+        if self.used.contains::<str>(&id) || id.is_empty() {
+            if id.is_empty() {
+                id.push('s');
+            }
+            id.push('-');
+            let i_num = id.len();
+            let mut num = 1;
+            write!(id, "{}", num).unwrap();
+            while self.used.contains::<str>(&id) {
+                num += 1;
+                id.drain(i_num..);
+                write!(id, "{}", num).unwrap();
+            }
+        }
+
+        self.used.insert(id.clone());
+        id
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DefaultSlugger;
+    use super::Slugger;
+
+    #[test]
+    fn words() {
+        let mut s = DefaultSlugger::default();
+        assert_eq!(s.slug("Some  heading text"), "Some-heading-text");
+        assert_eq!(s.slug(" a, b_c-d! "), "a-b_c-d");
+        assert_eq!(s.slug("Ünïcödé ok"), "Ünïcödé-ok");
+    }
+
+    #[test]
+    fn softbreak() {
+        let mut s = DefaultSlugger::default();
+        assert_eq!(s.slug("a\nb"), "a-b");
+        assert_eq!(s.slug("c  \nd"), "c--d");
+        assert_eq!(s.slug("e \t \n \nf"), "e---f");
+    }
+
+    #[test]
+    fn unique() {
+        let mut s = DefaultSlugger::default();
+        assert_eq!(s.slug("a"), "a");
+        assert_eq!(s.slug("a"), "a-1");
+        assert_eq!(s.slug("a"), "a-2");
+        assert_eq!(s.slug("a-1"), "a-1-1");
+        assert_eq!(s.slug("!"), "s-1");
+        assert_eq!(s.slug(""), "s-2");
+    }
+}